@@ -4,6 +4,8 @@ mod services;
 use crate::api::ApiChannel;
 use crate::database::RikDataBase;
 use dotenv::dotenv;
+use routes::Reply;
+use std::io;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::thread;
@@ -50,9 +52,32 @@ impl Server {
 
                     let mut req: Request = server.recv().unwrap();
 
-                    if let Some(res) = router.handle(&mut req, &connection, &internal_sender) {
-                        req.respond(res).unwrap();
-                        continue;
+                    match router.handle(&mut req, &connection, &internal_sender) {
+                        // Streamed responses may last, e.g. when following logs,
+                        // so they are sent without holding up a worker thread
+                        Some(Reply::Response(res)) if res.data_length().is_none() => {
+                            thread::spawn(move || {
+                                if let Err(e) = req.respond(res) {
+                                    event!(Level::DEBUG, "Streamed response interrupted: {}", e);
+                                }
+                            });
+                            continue;
+                        }
+                        Some(Reply::Response(res)) => {
+                            req.respond(res).unwrap();
+                            continue;
+                        }
+                        Some(Reply::Upgrade(mut writer)) => {
+                            thread::spawn(move || {
+                                let mut stream =
+                                    req.upgrade("tcp", tiny_http::Response::empty(101));
+                                if let Err(e) = io::copy(&mut stream, &mut writer) {
+                                    event!(Level::DEBUG, "Upgraded connection interrupted: {}", e);
+                                }
+                            });
+                            continue;
+                        }
+                        None => (),
                     }
                     event!(
                         Level::INFO,
@@ -0,0 +1,79 @@
+use route_recognizer;
+use rusqlite::Connection;
+use std::sync::mpsc::Sender;
+use tiny_http::Header;
+use tracing::{event, Level};
+
+use crate::api::external::routes::ContentType;
+use crate::api::external::services::admin;
+use crate::api::ApiChannel;
+
+use super::HttpResult;
+
+fn scheduler_unavailable(error: impl std::fmt::Display) -> HttpResult {
+    event!(Level::ERROR, "Could not reach the scheduler: {}", error);
+    Ok(
+        tiny_http::Response::from_string(format!("Could not reach the scheduler: {}", error))
+            .with_status_code(tiny_http::StatusCode::from(502)),
+    )
+}
+
+pub fn workers(
+    _: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    _: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    match admin::list_workers() {
+        Ok(workers) => {
+            event!(
+                Level::INFO,
+                "admin.workers, {} workers found",
+                workers.len()
+            );
+            Ok(
+                tiny_http::Response::from_string(serde_json::to_string(&workers)?)
+                    .with_header::<Header>(ContentType::JSON.into())
+                    .with_status_code(tiny_http::StatusCode::from(200)),
+            )
+        }
+        Err(e) => scheduler_unavailable(e),
+    }
+}
+
+pub fn placements(
+    _: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    _: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    match admin::list_placements() {
+        Ok(placements) => {
+            event!(
+                Level::INFO,
+                "admin.placements, {} workloads found",
+                placements.len()
+            );
+            Ok(
+                tiny_http::Response::from_string(serde_json::to_string(&placements)?)
+                    .with_header::<Header>(ContentType::JSON.into())
+                    .with_status_code(tiny_http::StatusCode::from(200)),
+            )
+        }
+        Err(e) => scheduler_unavailable(e),
+    }
+}
+
+pub fn state(
+    _: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    _: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    match admin::dump_state() {
+        Ok(state) => Ok(tiny_http::Response::from_string(state)
+            .with_header::<Header>(ContentType::JSON.into())
+            .with_status_code(tiny_http::StatusCode::from(200))),
+        Err(e) => scheduler_unavailable(e),
+    }
+}
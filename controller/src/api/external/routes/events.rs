@@ -0,0 +1,34 @@
+use route_recognizer;
+use rusqlite::Connection;
+use std::sync::mpsc::Sender;
+use tiny_http::Header;
+use tracing::{event, Level};
+
+use crate::api::external::routes::{query_params, ContentType};
+use crate::api::ApiChannel;
+use crate::database::{EventFilter, EventsRepository};
+
+use super::HttpResult;
+
+/// List events, optionally filtered with the `kind` and `object` of the
+/// involved object and its `namespace`
+pub fn list(
+    req: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    let query = query_params(req.url());
+    let filter = EventFilter {
+        object_kind: query.get("kind").copied(),
+        object_id: query.get("object").copied(),
+        namespace: query.get("namespace").copied(),
+    };
+
+    let events = EventsRepository::list(connection, &filter)?;
+    event!(Level::INFO, "events.list, {} events found", events.len());
+    let body = serde_json::to_string(&events)?;
+    Ok(tiny_http::Response::from_string(body)
+        .with_header::<Header>(ContentType::JSON.into())
+        .with_status_code(tiny_http::StatusCode::from(200)))
+}
@@ -1,17 +1,20 @@
-use definition::workload::WorkloadDefinition;
+use definition::workload::{is_dns_label, WorkloadDefinition};
 use route_recognizer;
 use rusqlite::Connection;
 use std::sync::mpsc::Sender;
-use tracing::{event, Level};
+use tracing::{event, Level, Span};
 
-use crate::api::external::routes::ContentType;
+use crate::api::external::routes::{forwarded_query, query_params, ContentType};
 use crate::api::external::services::element::elements_set_right_name;
 use crate::api::external::services::instance::send_create_instance;
+use crate::api::external::services::logs;
+use crate::api::external::services::sessions::{self, Opening};
 use crate::api::types::element::OnlyId;
 use crate::api::types::instance::InstanceDefinition;
 use crate::api::{ApiChannel, Crud};
 use crate::core::instance::Instance;
 use crate::database::RikRepository;
+use std::io::{Read, Write};
 use tiny_http::Header;
 
 use super::HttpResult;
@@ -61,14 +64,18 @@ pub fn create(
         .with_status_code(tiny_http::StatusCode::from(404)));
     }
 
+    if let Some(name) = instance.name.as_ref().filter(|name| !is_dns_label(name)) {
+        event!(Level::WARN, "Invalid instance name {}", name);
+        return Ok(tiny_http::Response::from_string(format!(
+            "Invalid instance name {}, only lowercase alphanumeric characters and '-' are allowed",
+            name
+        ))
+        .with_status_code(tiny_http::StatusCode::from(400)));
+    }
+
     if instance.name.is_some() {
         // Check name is not used
-        if RikRepository::check_duplicate_name(
-            connection,
-            &format!("/instance/%/default/{}", instance.get_name()),
-        )
-        .is_ok()
-        {
+        if RikRepository::find_one(connection, instance.get_name(), "/instance").is_ok() {
             event!(
                 Level::WARN,
                 "Instance name {} is already used",
@@ -141,6 +148,7 @@ pub fn delete(
             workload_id: Some(instance_def.workload_id),
             workload_definition: Some(workload_def),
             instance_id: Some(delete_id),
+            span: Span::current(),
         })?;
 
         event!(
@@ -157,3 +165,195 @@ pub fn delete(
         )
     }
 }
+
+fn not_found(message: String) -> HttpResult<Box<dyn Read + Send>> {
+    event!(Level::WARN, "{}", message);
+    Ok(tiny_http::Response::from_string(message)
+        .with_status_code(tiny_http::StatusCode::from(404))
+        .boxed())
+}
+
+/// Host of the worker running the `instance` given in the query of a
+/// request, or the response telling why there is none
+fn running_instance(
+    req: &tiny_http::Request,
+    connection: &Connection,
+) -> Result<(Instance, String), HttpResult<Box<dyn Read + Send>>> {
+    let query = query_params(req.url());
+    let instance_id = match query.get("instance") {
+        Some(instance_id) => *instance_id,
+        None => {
+            return Err(Ok(tiny_http::Response::from_string("Missing instance")
+                .with_status_code(tiny_http::StatusCode::from(400))
+                .boxed()))
+        }
+    };
+    // Instances are stored under their id
+    let instance = match RikRepository::find_one(connection, &instance_id.to_string(), "/instance")
+    {
+        Ok(element) => {
+            serde_json::from_value::<Instance>(element.value).map_err(|e| Err(e.into()))?
+        }
+        Err(_) => return Err(not_found(format!("Instance {} not found", instance_id))),
+    };
+    match instance.placement.clone() {
+        Some(placement) if !placement.host_ip.is_empty() => Ok((instance, placement.host_ip)),
+        _ => Err(not_found(format!(
+            "Instance {} is not running on any worker",
+            instance_id
+        ))),
+    }
+}
+
+/// Token the riklets serve logs and sessions to, which are disabled without
+/// one
+fn riklet_token() -> Result<String, HttpResult<Box<dyn Read + Send>>> {
+    sessions::token().ok_or_else(|| {
+        Ok(tiny_http::Response::from_string(
+            "Logs and sessions are disabled, RIKLET_SESSIONS_TOKEN is not set",
+        )
+        .with_status_code(tiny_http::StatusCode::from(503))
+        .boxed())
+    })
+}
+
+/// Logs of the `instance`, proxied from the riklet running it. The
+/// `container`, `tail` and `follow` parameters are passed along.
+pub fn logs(
+    req: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult<Box<dyn Read + Send>> {
+    let token = match riklet_token() {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let (instance, host_ip) = match running_instance(req, connection) {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    match logs::open(
+        &sessions::riklet_address(&host_ip),
+        &token,
+        &instance.id,
+        &forwarded_query(req.url(), &["container", "tail", "follow"]),
+    ) {
+        Ok((status, body)) => {
+            event!(
+                Level::INFO,
+                "instances.logs, streaming logs of {}",
+                instance.id
+            );
+            Ok(tiny_http::Response::new(
+                tiny_http::StatusCode::from(status),
+                vec![ContentType::Text.into()],
+                body,
+                None,
+                None,
+            ))
+        }
+        Err(e) => {
+            event!(Level::ERROR, "Could not get logs of {}: {}", instance.id, e);
+            Ok(tiny_http::Response::from_string(e.to_string())
+                .with_status_code(tiny_http::StatusCode::from(502))
+                .boxed())
+        }
+    }
+}
+
+/// Open a session `kind` on the `instance`, whose output is the body of the
+/// response. Its id, needed to send its input to `instances.input`, is given
+/// in the `Rik-Session` header.
+fn session(
+    req: &tiny_http::Request,
+    connection: &Connection,
+    kind: &str,
+    forwarded: &[&str],
+) -> HttpResult<Box<dyn Read + Send>> {
+    let token = match riklet_token() {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let (instance, host_ip) = match running_instance(req, connection) {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    match sessions::open(
+        &sessions::riklet_address(&host_ip),
+        &token,
+        &instance.id,
+        kind,
+        &forwarded_query(req.url(), forwarded),
+    ) {
+        Ok(Opening::Opened(session)) => {
+            event!(
+                Level::INFO,
+                "instances.{}, session {} opened on {}",
+                kind,
+                session.id(),
+                instance.id
+            );
+            let header = Header::from_bytes(&b"Rik-Session"[..], session.id().as_bytes())
+                .map_err(|_| anyhow::anyhow!("Invalid session id"))?;
+            Ok(tiny_http::Response::new(
+                tiny_http::StatusCode::from(200),
+                vec![header],
+                Box::new(session) as Box<dyn Read + Send>,
+                None,
+                None,
+            ))
+        }
+        Ok(Opening::Refused(status, message)) => Ok(tiny_http::Response::from_string(message)
+            .with_status_code(tiny_http::StatusCode::from(status))
+            .boxed()),
+        Err(e) => {
+            event!(
+                Level::ERROR,
+                "Could not open a session on {}: {}",
+                instance.id,
+                e
+            );
+            Ok(tiny_http::Response::from_string(e.to_string())
+                .with_status_code(tiny_http::StatusCode::from(502))
+                .boxed())
+        }
+    }
+}
+
+/// Run the `command` parameters in a container of the `instance`, given by
+/// `container` unless it has a single one, with a TTY when `tty` is set
+pub fn exec(
+    req: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult<Box<dyn Read + Send>> {
+    session(req, connection, "exec", &["container", "tty", "command"])
+}
+
+/// Attach to the main process of a container of the `instance`
+pub fn attach(
+    req: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult<Box<dyn Read + Send>> {
+    session(req, connection, "attach", &["container"])
+}
+
+/// Input of the open `session`, sent over the upgraded connection
+pub fn input(
+    req: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    _: &Connection,
+    _: &Sender<ApiChannel>,
+) -> Result<Box<dyn Write + Send>, anyhow::Error> {
+    let query = query_params(req.url());
+    let id = query
+        .get("session")
+        .ok_or_else(|| anyhow::anyhow!("Missing session"))?;
+    sessions::take_input(id).ok_or_else(|| anyhow::anyhow!("Session {} not found", id))
+}
@@ -0,0 +1,107 @@
+use route_recognizer;
+use rusqlite::Connection;
+use std::sync::mpsc::Sender;
+use tiny_http::Header;
+use tracing::{event, Level};
+
+use crate::api::external::routes::{query_params, ContentType};
+use crate::api::types::metrics::MetricsSeries;
+use crate::api::ApiChannel;
+use crate::core::metrics_history::{instance_series, worker_series};
+use crate::database::MetricsRepository;
+use definition::now_millis;
+
+use super::HttpResult;
+
+/// Range returned when none is given
+const DEFAULT_RANGE_MS: u64 = 60 * 60 * 1000;
+const DEFAULT_RESOLUTION_MS: u64 = 60 * 1000;
+/// Resolution is lowered when needed to not return more points than this
+const MAX_POINTS: u64 = 1000;
+
+fn bad_request(message: String) -> HttpResult {
+    Ok(
+        tiny_http::Response::from_string(message)
+            .with_status_code(tiny_http::StatusCode::from(400)),
+    )
+}
+
+/// Resolution to use so that `[from, to)` holds at most [MAX_POINTS]
+fn bounded_resolution(from: u64, to: u64, resolution: u64) -> u64 {
+    let minimum = to.saturating_sub(from).div_ceil(MAX_POINTS);
+    resolution.max(minimum).max(1)
+}
+
+pub fn query(
+    req: &mut tiny_http::Request,
+    params: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    let id = params.find("id").unwrap_or_default();
+    let series = match params.find("kind").unwrap_or_default() {
+        "workers" => worker_series(id),
+        "instances" => instance_series(id),
+        kind => return bad_request(format!("Unknown metrics kind {}", kind)),
+    };
+
+    let query = query_params(req.url());
+    let parse = |key: &str| -> Result<Option<u64>, String> {
+        query
+            .get(key)
+            .map(|value| {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid value for {}: {}", key, value))
+            })
+            .transpose()
+    };
+    let (to, from, resolution) = match (parse("to"), parse("from"), parse("resolution")) {
+        (Ok(to), Ok(from), Ok(resolution)) => (to, from, resolution),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return bad_request(e),
+    };
+    let to = to.unwrap_or_else(now_millis);
+    let from = from.unwrap_or_else(|| to.saturating_sub(DEFAULT_RANGE_MS));
+    if from >= to {
+        return bad_request("from must be lower than to".to_string());
+    }
+    let resolution = bounded_resolution(from, to, resolution.unwrap_or(DEFAULT_RESOLUTION_MS));
+
+    let points = MetricsRepository::query(connection, &series, from, to, resolution)?;
+    event!(
+        Level::INFO,
+        "metrics.query, {} points found for {}",
+        points.len(),
+        series
+    );
+    let body = serde_json::to_string(&MetricsSeries {
+        series,
+        from,
+        to,
+        resolution,
+        points,
+    })?;
+    Ok(tiny_http::Response::from_string(body)
+        .with_header::<Header>(ContentType::JSON.into())
+        .with_status_code(tiny_http::StatusCode::from(200)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_resolution() {
+        assert_eq!(bounded_resolution(0, 3_600_000, 60_000), 60_000);
+        assert_eq!(bounded_resolution(0, 3_600_000, 1), 3_600);
+        assert_eq!(bounded_resolution(0, 10, 0), 1);
+    }
+
+    #[test]
+    fn test_query_params() {
+        let params = query_params("/api/v0/metrics.query/workers/w1?from=10&to=20");
+        assert_eq!(params.get("from"), Some(&"10"));
+        assert_eq!(params.get("to"), Some(&"20"));
+        assert!(query_params("/api/v0/metrics.query/workers/w1").is_empty());
+    }
+}
@@ -1,16 +1,24 @@
 use route_recognizer;
 use rusqlite::Connection;
+use std::collections::HashMap;
 use std::io;
 use std::str::FromStr;
 use std::sync::mpsc::Sender;
 use tiny_http::Method;
 use tiny_http::Response;
-use tracing::{event, Level};
+use tracing::{event, info_span, Level};
 
 use crate::api::ApiChannel;
+use crate::metrics::{HTTP_REQUESTS, HTTP_REQUEST_DURATION};
 
+mod admin;
+mod events;
 mod instance;
+mod metrics;
+mod secret;
+mod telemetry;
 mod tenant;
+mod worker;
 mod workload;
 
 type Handler = fn(
@@ -20,10 +28,36 @@ type Handler = fn(
     &Sender<ApiChannel>,
 ) -> Result<tiny_http::Response<io::Cursor<Vec<u8>>>, anyhow::Error>;
 
+/// Handler of a route whose response is sent as it is produced
+type StreamHandler = fn(
+    &mut tiny_http::Request,
+    &route_recognizer::Params,
+    &Connection,
+    &Sender<ApiChannel>,
+) -> HttpResult<Box<dyn io::Read + Send>>;
+
+/// Handler of a route switching the connection to raw bytes, which are
+/// written to the writer it gives
+type UpgradeHandler = fn(
+    &mut tiny_http::Request,
+    &route_recognizer::Params,
+    &Connection,
+    &Sender<ApiChannel>,
+) -> Result<Box<dyn io::Write + Send>, anyhow::Error>;
+
 type HttpResult<T = io::Cursor<Vec<u8>>> = Result<Response<T>, anyhow::Error>;
 
+/// What a request is answered with
+pub enum Reply {
+    Response(tiny_http::ResponseBox),
+    /// The connection is upgraded, and what comes from it is written there
+    Upgrade(Box<dyn io::Write + Send>),
+}
+
 pub enum ContentType {
     JSON,
+    Prometheus,
+    Text,
 }
 
 impl Into<tiny_http::Header> for ContentType {
@@ -32,18 +66,100 @@ impl Into<tiny_http::Header> for ContentType {
             ContentType::JSON => {
                 tiny_http::Header::from_str("Content-Type: application/json").unwrap()
             }
+            ContentType::Prometheus => {
+                tiny_http::Header::from_str("Content-Type: text/plain; version=0.0.4").unwrap()
+            }
+            ContentType::Text => {
+                tiny_http::Header::from_str("Content-Type: text/plain; charset=utf-8").unwrap()
+            }
         }
     }
 }
 
+/// Pairs of the query string of an url whose key is one of `keys`, as they
+/// are so they can be passed along, in the order they are given
+fn forwarded_query(url: &str, keys: &[&str]) -> String {
+    url.split_once('?')
+        .map(|(_, query)| {
+            query
+                .split('&')
+                .filter(|param| {
+                    let key = param.split_once('=').map_or(*param, |(key, _)| key);
+                    keys.contains(&key)
+                })
+                .collect::<Vec<_>>()
+                .join("&")
+        })
+        .unwrap_or_default()
+}
+
+/// Parameters given in the query string of an url
+fn query_params(url: &str) -> HashMap<&str, &str> {
+    url.split_once('?')
+        .map(|(_, query)| {
+            query
+                .split('&')
+                .filter_map(|param| param.split_once('='))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+enum RouteHandler {
+    Buffered(Handler),
+    Streamed(StreamHandler),
+    Upgraded(UpgradeHandler),
+}
+
+/// A handler along with the path it was registered with, used to label metrics
+struct Route {
+    path: String,
+    handler: RouteHandler,
+}
+
+#[derive(Default)]
+struct Routes(route_recognizer::Router<Route>);
+
+impl Routes {
+    fn add(&mut self, path: &str, handler: Handler) {
+        self.0.add(
+            path,
+            Route {
+                path: path.to_string(),
+                handler: RouteHandler::Buffered(handler),
+            },
+        );
+    }
+
+    fn add_stream(&mut self, path: &str, handler: StreamHandler) {
+        self.0.add(
+            path,
+            Route {
+                path: path.to_string(),
+                handler: RouteHandler::Streamed(handler),
+            },
+        );
+    }
+
+    fn add_upgrade(&mut self, path: &str, handler: UpgradeHandler) {
+        self.0.add(
+            path,
+            Route {
+                path: path.to_string(),
+                handler: RouteHandler::Upgraded(handler),
+            },
+        );
+    }
+}
+
 pub struct Router {
-    routes: Vec<(tiny_http::Method, route_recognizer::Router<Handler>)>,
+    routes: Vec<(tiny_http::Method, Routes)>,
 }
 
 impl Router {
     pub fn new() -> Router {
-        let mut get = route_recognizer::Router::<Handler>::new();
-        let mut post = route_recognizer::Router::<Handler>::new();
+        let mut get = Routes::default();
+        let mut post = Routes::default();
 
         let base_path = "/api/v0";
 
@@ -61,10 +177,48 @@ impl Router {
         post.add(&format!("{}/tenants.create", base_path), tenant::create);
         post.add(&format!("{}/tenants.delete", base_path), tenant::delete);
 
+        // Secret related routes
+        get.add(&format!("{}/secrets.list", base_path), secret::get);
+        post.add(&format!("{}/secrets.create", base_path), secret::create);
+        post.add(&format!("{}/secrets.delete", base_path), secret::delete);
+
         // Instance related routes
         get.add(&format!("{}/instances.list", base_path), instance::get);
         post.add(&format!("{}/instances.create", base_path), instance::create);
         post.add(&format!("{}/instances.delete", base_path), instance::delete);
+        get.add_stream(&format!("{}/instances.logs", base_path), instance::logs);
+        post.add_stream(&format!("{}/instances.exec", base_path), instance::exec);
+        post.add_stream(&format!("{}/instances.attach", base_path), instance::attach);
+        post.add_upgrade(&format!("{}/instances.input", base_path), instance::input);
+
+        // Worker related routes
+        get.add(&format!("{}/workers.list", base_path), worker::get);
+        get.add(
+            &format!("{}/workers.get/:workerid", base_path),
+            worker::get_one,
+        );
+
+        // Event related routes
+        get.add(&format!("{}/events.list", base_path), events::list);
+
+        // Metrics history related routes
+        get.add(
+            &format!("{}/metrics.query/:kind/:id", base_path),
+            metrics::query,
+        );
+
+        // Scheduler admin related routes
+        get.add(&format!("{}/admin.workers", base_path), admin::workers);
+        get.add(
+            &format!("{}/admin.placements", base_path),
+            admin::placements,
+        );
+        get.add(&format!("{}/admin.state", base_path), admin::state);
+
+        // Operational endpoints
+        get.add("/metrics", telemetry::metrics);
+        get.add("/healthz", telemetry::liveness);
+        get.add("/readyz", telemetry::readiness);
 
         Router {
             routes: vec![(Method::Get, get), (Method::Post, post)],
@@ -76,26 +230,60 @@ impl Router {
         request: &mut tiny_http::Request,
         connection: &Connection,
         internal_sender: &Sender<ApiChannel>,
-    ) -> Option<tiny_http::Response<io::Cursor<Vec<u8>>>> {
+    ) -> Option<Reply> {
         self.routes
             .iter()
             .find(|&(method, _)| method == request.method())
             .and_then(|(_, routes)| {
-                if let Ok(res) = routes.recognize(request.url()) {
+                let path = request.url().split('?').next().unwrap_or_default();
+                if let Ok(res) = routes.0.recognize(path) {
                     event!(
                         Level::INFO,
                         "Route found, method: {}, path: {}",
                         request.method(),
                         request.url()
                     );
-                    Some(
-                        res.handler()(request, res.params(), connection, internal_sender)
-                            .unwrap_or_else(|error| {
-                                event!(Level::ERROR, "Could not handle route: {}", error);
+                    let route = res.handler();
+                    let method = request.method().to_string();
+                    let timer = HTTP_REQUEST_DURATION
+                        .with_label_values(&[&method, &route.path])
+                        .start_timer();
+                    let span = info_span!("http_request", %method, route = %route.path);
+                    let reply = span
+                        .in_scope(|| {
+                            let params = res.params();
+                            match route.handler {
+                                RouteHandler::Buffered(handler) => {
+                                    handler(request, params, connection, internal_sender)
+                                        .map(|response| Reply::Response(response.boxed()))
+                                }
+                                RouteHandler::Streamed(handler) => {
+                                    handler(request, params, connection, internal_sender)
+                                        .map(Reply::Response)
+                                }
+                                RouteHandler::Upgraded(handler) => {
+                                    handler(request, params, connection, internal_sender)
+                                        .map(Reply::Upgrade)
+                                }
+                            }
+                        })
+                        .unwrap_or_else(|error| {
+                            event!(Level::ERROR, "Could not handle route: {}", error);
+                            Reply::Response(
                                 tiny_http::Response::from_string(error.to_string())
                                     .with_status_code(tiny_http::StatusCode::from(400))
-                            }),
-                    )
+                                    .boxed(),
+                            )
+                        });
+                    timer.observe_duration();
+                    let status = match &reply {
+                        Reply::Response(response) => response.status_code().0,
+                        Reply::Upgrade(_) => 101,
+                    };
+                    HTTP_REQUESTS
+                        .with_label_values(&[&method, &route.path, &status.to_string()])
+                        .inc();
+                    Some(reply)
                 } else {
                     None
                 }
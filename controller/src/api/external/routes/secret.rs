@@ -0,0 +1,94 @@
+use route_recognizer;
+use rusqlite::Connection;
+use std::sync::mpsc::Sender;
+use tiny_http::Header;
+use tracing::{event, Level};
+
+use super::HttpResult;
+use crate::api::external::routes::{query_params, ContentType};
+use crate::api::types::secret::{Secret, SecretRef};
+use crate::api::ApiChannel;
+use crate::database::{SecretsKey, SecretsRepository};
+
+/// List the secrets, of the `namespace` given in the query string if any,
+/// with their keys but not their values
+pub fn get(
+    req: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    let query = query_params(req.url());
+    let secrets = SecretsRepository::list(connection, query.get("namespace").copied())?;
+    event!(Level::INFO, "secrets.list, {} secrets found", secrets.len());
+    Ok(
+        tiny_http::Response::from_string(serde_json::to_string(&secrets)?)
+            .with_header::<Header>(ContentType::JSON.into())
+            .with_status_code(tiny_http::StatusCode::from(200)),
+    )
+}
+
+pub fn create(
+    req: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    let mut content = String::new();
+    req.as_reader().read_to_string(&mut content)?;
+    let secret: Secret = serde_json::from_str(&content)?;
+
+    if let Err(e) = secret.validate() {
+        event!(Level::WARN, "secrets.create, {}", e);
+        return Ok(
+            tiny_http::Response::from_string(e).with_status_code(tiny_http::StatusCode::from(400))
+        );
+    }
+
+    let key = SecretsKey::load()?;
+    if SecretsRepository::create(connection, &key, &secret)? {
+        event!(
+            Level::INFO,
+            "secrets.create, secret {} created in {}",
+            secret.name,
+            secret.namespace
+        );
+        Ok(
+            tiny_http::Response::from_string(serde_json::to_string(&secret.metadata())?)
+                .with_header::<Header>(ContentType::JSON.into())
+                .with_status_code(tiny_http::StatusCode::from(200)),
+        )
+    } else {
+        event!(Level::WARN, "secrets.create, name already used");
+        Ok(tiny_http::Response::from_string("Name already used")
+            .with_status_code(tiny_http::StatusCode::from(409)))
+    }
+}
+
+pub fn delete(
+    req: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    let mut content = String::new();
+    req.as_reader().read_to_string(&mut content)?;
+    let SecretRef { name, namespace } = serde_json::from_str(&content)?;
+
+    if SecretsRepository::delete(connection, &namespace, &name)? {
+        event!(
+            Level::INFO,
+            "secrets.delete, secret {} deleted from {}",
+            name,
+            namespace
+        );
+        Ok(tiny_http::Response::from_string("").with_status_code(tiny_http::StatusCode::from(204)))
+    } else {
+        event!(Level::WARN, "secrets.delete, secret not found");
+        Ok(tiny_http::Response::from_string(format!(
+            "Secret {} not found in namespace {}",
+            name, namespace
+        ))
+        .with_status_code(tiny_http::StatusCode::from(404)))
+    }
+}
@@ -0,0 +1,52 @@
+use route_recognizer;
+use rusqlite::Connection;
+use std::io;
+use std::sync::mpsc::Sender;
+use tiny_http::Header;
+
+use crate::api::external::routes::ContentType;
+use crate::api::ApiChannel;
+use crate::database::RikRepository;
+use crate::{health, metrics};
+
+use super::HttpResult;
+
+pub fn metrics(
+    _: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    _: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    Ok(tiny_http::Response::from_string(metrics::render())
+        .with_header::<Header>(ContentType::Prometheus.into())
+        .with_status_code(tiny_http::StatusCode::from(200)))
+}
+
+pub fn liveness(
+    _: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    _: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    Ok(health_response(health::liveness()))
+}
+
+pub fn readiness(
+    _: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    let database = RikRepository::check(connection).is_ok();
+    Ok(health_response(health::readiness(database)))
+}
+
+fn health_response((healthy, body): (bool, String)) -> tiny_http::Response<io::Cursor<Vec<u8>>> {
+    let status = match healthy {
+        true => 200,
+        false => 503,
+    };
+    tiny_http::Response::from_string(body)
+        .with_header::<Header>(ContentType::JSON.into())
+        .with_status_code(tiny_http::StatusCode::from(status))
+}
@@ -0,0 +1,114 @@
+use route_recognizer;
+use rusqlite::Connection;
+use std::sync::mpsc::Sender;
+use tiny_http::Header;
+use tracing::{event, Level};
+
+use crate::api::external::routes::ContentType;
+use crate::api::external::services::worker::describe_workers;
+use crate::api::ApiChannel;
+use crate::core::worker::Worker;
+use crate::database::RikRepository;
+
+use super::HttpResult;
+
+pub fn get(
+    _: &mut tiny_http::Request,
+    _: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    let workers: Vec<Worker> = match RikRepository::find_all(connection, "/worker") {
+        Ok(elements) => elements
+            .into_iter()
+            .filter_map(|element| serde_json::from_value(element.value).ok())
+            .collect(),
+        Err(_) => {
+            return Ok(tiny_http::Response::from_string("Cannot find workers")
+                .with_status_code(tiny_http::StatusCode::from(500)))
+        }
+    };
+
+    let workers_json = serde_json::to_string(&describe_workers(connection, workers)?)?;
+    event!(Level::INFO, "workers.get, workers found");
+    Ok(tiny_http::Response::from_string(workers_json)
+        .with_header::<Header>(ContentType::JSON.into())
+        .with_status_code(tiny_http::StatusCode::from(200)))
+}
+
+pub fn get_one(
+    _: &mut tiny_http::Request,
+    params: &route_recognizer::Params,
+    connection: &Connection,
+    _: &Sender<ApiChannel>,
+) -> HttpResult {
+    let worker_id = params.find("workerid").unwrap_or_default().to_string();
+
+    let worker: Worker = match RikRepository::find_one(connection, &worker_id, "/worker") {
+        Ok(element) => serde_json::from_value(element.value)?,
+        Err(_) => {
+            event!(Level::WARN, "Worker id {} not found", worker_id);
+            return Ok(tiny_http::Response::from_string(format!(
+                "Worker id {} not found",
+                worker_id
+            ))
+            .with_status_code(tiny_http::StatusCode::from(404)));
+        }
+    };
+
+    let worker = describe_workers(connection, vec![worker])?.pop();
+    Ok(
+        tiny_http::Response::from_string(serde_json::to_string(&worker)?)
+            .with_header::<Header>(ContentType::JSON.into())
+            .with_status_code(tiny_http::StatusCode::from(200)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::external::services::worker::describe_workers;
+    use crate::core::instance::Instance;
+    use crate::core::worker::{Worker, WorkerState};
+    use crate::database::{RikDataBase, RikRepository};
+    use crate::tests::fixtures::db_connection;
+    use definition::workload::{Spec, WorkloadKind};
+    use proto::common::{ResourceStatus, WorkerMetric};
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_describe_workers_with_instances(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        let mut instance = Instance::new(
+            "workload".to_string(),
+            WorkloadKind::Pod,
+            Some("instance-1".to_string()),
+            Spec {
+                containers: vec![],
+                function: None,
+                termination_grace_period_seconds: None,
+                restart_policy: Default::default(),
+                share_process_namespace: false,
+                volumes: vec![],
+            },
+        );
+        instance.worker_id = Some("worker-1".to_string());
+        RikRepository::insert(
+            &connection,
+            &instance.get_full_name(),
+            &serde_json::to_string(&instance).unwrap(),
+        )
+        .unwrap();
+
+        let worker = Worker::new(
+            "worker-1".to_string(),
+            "127.0.0.1:4995".to_string(),
+            WorkerMetric {
+                status: ResourceStatus::Running.into(),
+                metrics: None,
+            },
+        );
+        let workers = describe_workers(&connection, vec![worker]).unwrap();
+        assert_eq!(workers[0].state, WorkerState::Ready);
+        assert_eq!(workers[0].instances, vec!["instance-1"]);
+    }
+}
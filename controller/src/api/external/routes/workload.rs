@@ -11,7 +11,7 @@ use rusqlite::Connection;
 use serde_json::json;
 use std::sync::mpsc::Sender;
 use tiny_http::Header;
-use tracing::{event, Level};
+use tracing::{event, Level, Span};
 
 pub fn get(
     _: &mut tiny_http::Request,
@@ -85,6 +85,12 @@ pub fn create(
     if workload.replicas.is_none() {
         workload.replicas = Some(1);
     }
+    if let Err(e) = workload.validate() {
+        event!(Level::WARN, "workload.create, {}", e);
+        return Ok(
+            tiny_http::Response::from_string(e).with_status_code(tiny_http::StatusCode::from(400))
+        );
+    }
     let namespace = "default";
     let name = format!(
         "/workload/{}/{}/{}",
@@ -92,7 +98,7 @@ pub fn create(
     );
 
     // Check name is not used
-    if RikRepository::check_duplicate_name(connection, &name).is_ok() {
+    if RikRepository::find_by_name(connection, &name).is_ok() {
         event!(Level::WARN, "workload.create, name already used");
         return Ok(tiny_http::Response::from_string("Name already used")
             .with_status_code(tiny_http::StatusCode::from(404)));
@@ -136,6 +142,7 @@ pub fn delete(
                 workload_id: Some(delete_id),
                 workload_definition: Some(definition),
                 instance_id: None,
+                span: Span::current(),
             })
             .unwrap();
         RikRepository::delete(connection, &workload.id).unwrap();
@@ -0,0 +1,81 @@
+use crate::api::types::admin::{Placement, SchedulerWorker};
+use crate::api::RikError;
+use crate::core::scheduler_url;
+use once_cell::sync::{Lazy, OnceCell};
+use proto::admin::admin_client::AdminClient;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::runtime::{Builder, Runtime};
+use tonic::transport::Channel;
+
+/// Routes are handled outside of any tokio runtime, so requests to the scheduler
+/// are run to completion on this one, shared by every admin request
+static RUNTIME: OnceCell<Runtime> = OnceCell::new();
+
+/// Connection to the scheduler, opened on the first admin request. The channel
+/// reconnects by itself, so it is kept for the lifetime of the controller
+static CLIENT: Lazy<Mutex<Option<AdminClient<Channel>>>> = Lazy::new(Default::default);
+
+fn communication_error<E: std::fmt::Display>(error: E) -> RikError {
+    RikError::InternalCommunicationError(error.to_string())
+}
+
+fn block_on<F: Future>(future: F) -> Result<F::Output, RikError> {
+    let runtime = RUNTIME.get_or_try_init(|| {
+        Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(communication_error)
+    })?;
+    Ok(runtime.block_on(future))
+}
+
+async fn connect() -> Result<AdminClient<Channel>, RikError> {
+    if let Some(client) = CLIENT.lock().unwrap().as_ref() {
+        return Ok(client.clone());
+    }
+    let client = AdminClient::connect(scheduler_url())
+        .await
+        .map_err(communication_error)?;
+    *CLIENT.lock().unwrap() = Some(client.clone());
+    Ok(client)
+}
+
+pub fn list_workers() -> Result<Vec<SchedulerWorker>, RikError> {
+    block_on(async {
+        let workers = connect()
+            .await?
+            .list_workers(())
+            .await
+            .map_err(communication_error)?
+            .into_inner()
+            .workers;
+        Ok(workers.into_iter().map(Into::into).collect())
+    })?
+}
+
+pub fn list_placements() -> Result<Vec<Placement>, RikError> {
+    block_on(async {
+        let workloads = connect()
+            .await?
+            .list_placements(())
+            .await
+            .map_err(communication_error)?
+            .into_inner()
+            .workloads;
+        Ok(workloads.into_iter().map(Into::into).collect())
+    })?
+}
+
+pub fn dump_state() -> Result<String, RikError> {
+    block_on(async {
+        Ok(connect()
+            .await?
+            .dump_state(())
+            .await
+            .map_err(communication_error)?
+            .into_inner()
+            .json)
+    })?
+}
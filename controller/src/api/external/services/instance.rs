@@ -4,6 +4,7 @@ use crate::database::RikRepository;
 use definition::workload::WorkloadDefinition;
 use rusqlite::Connection;
 use std::sync::mpsc::Sender;
+use tracing::Span;
 
 pub fn send_create_instance(
     connection: &Connection,
@@ -25,6 +26,7 @@ pub fn send_create_instance(
             workload_id: Some(workload_id),
             workload_definition: Some(workload),
             instance_id: Some(instance_name),
+            span: Span::current(),
         })
         .unwrap();
 }
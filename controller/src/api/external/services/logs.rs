@@ -0,0 +1,96 @@
+use crate::api::RikError;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn communication_error<E: std::fmt::Display>(error: E) -> RikError {
+    RikError::InternalCommunicationError(format!("Could not reach the riklet: {}", error))
+}
+
+/// Request the logs of an instance to the riklet listening on `address`,
+/// which serves them along with the sessions to the holders of `token`.
+///
+/// The request is made in HTTP/1.0 so the riklet closes the connection at
+/// the end of the logs, and the body can be passed along as it is read while
+/// following them. Gives the status of the response along with its body.
+pub fn open(
+    address: &str,
+    token: &str,
+    instance_id: &str,
+    query: &str,
+) -> Result<(u16, Box<dyn Read + Send>), RikError> {
+    let socket_address = address
+        .to_socket_addrs()
+        .map_err(communication_error)?
+        .next()
+        .ok_or_else(|| communication_error(format!("invalid address {}", address)))?;
+    let mut stream = TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT)
+        .map_err(communication_error)?;
+    write!(
+        stream,
+        "GET /instances/{}/logs?{} HTTP/1.0\r\nHost: {}\r\nAuthorization: Bearer {}\r\n\r\n",
+        instance_id, query, address, token
+    )
+    .map_err(communication_error)?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader
+        .read_line(&mut status_line)
+        .map_err(communication_error)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| communication_error(format!("invalid response {}", status_line.trim())))?;
+    // Headers are not passed along
+    loop {
+        let mut header = String::new();
+        let read = reader.read_line(&mut header).map_err(communication_error)?;
+        if read == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+    Ok((status, Box::new(reader)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_open_logs() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut header = String::new();
+            while header != "\r\n" {
+                header.clear();
+                reader.read_line(&mut header).unwrap();
+                request.push_str(&header);
+            }
+            stream
+                .write_all(b"HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\nline 1\nline 2\n")
+                .unwrap();
+            request
+        });
+
+        let (status, mut body) = open(&address, "token", "instance-1", "tail=2").unwrap();
+        let mut content = String::new();
+        body.read_to_string(&mut content).unwrap();
+
+        assert_eq!(status, 200);
+        assert_eq!(content, "line 1\nline 2\n");
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /instances/instance-1/logs?tail=2 HTTP/1.0\r\n"));
+        assert!(request.contains("\r\nAuthorization: Bearer token\r\n"));
+    }
+}
@@ -1,2 +1,6 @@
+pub mod admin;
 pub mod element;
 pub mod instance;
+pub mod logs;
+pub mod sessions;
+pub mod worker;
@@ -0,0 +1,247 @@
+//! Interactive sessions in the containers of the instances, opened on the
+//! riklet running them. The output of a session is streamed as the response
+//! opening it, while its input comes on a second connection, given the id of
+//! the session, since responses can't be read from and written to at once.
+//!
+//! Riklets serve sessions on a listener of their own, only opened to the
+//! controller presenting the token they share with it.
+use super::logs::communication_error;
+use crate::api::RikError;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const DEFAULT_RIKLET_SESSIONS_PORT: u16 = 4999;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Port the riklets serve sessions on, overridden with `RIKLET_SESSIONS_PORT`
+fn riklet_sessions_port() -> u16 {
+    std::env::var("RIKLET_SESSIONS_PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_RIKLET_SESSIONS_PORT)
+}
+
+/// Address of the sessions listener of the riklet running on a worker
+pub fn riklet_address(host_ip: &str) -> String {
+    match host_ip.parse::<IpAddr>() {
+        Ok(ip) => SocketAddr::new(ip, riklet_sessions_port()).to_string(),
+        Err(_) => format!("{}:{}", host_ip, riklet_sessions_port()),
+    }
+}
+
+/// Token shared with the riklets to open sessions, from
+/// `RIKLET_SESSIONS_TOKEN`. Sessions are disabled without one.
+pub fn token() -> Option<String> {
+    std::env::var("RIKLET_SESSIONS_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+/// Connections to the riklets the input of the open sessions is written to,
+/// by session id
+static INPUTS: Lazy<Mutex<HashMap<String, TcpStream>>> = Lazy::new(Default::default);
+
+/// Output of a session, which is over once it is dropped
+pub struct Session {
+    id: String,
+    stream: TcpStream,
+}
+
+impl Session {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Read for Session {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        INPUTS.lock().unwrap().remove(&self.id);
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Answer of a riklet to the opening of a session
+pub enum Opening {
+    Opened(Session),
+    /// The riklet refused it, with its status and message
+    Refused(u16, String),
+}
+
+/// Read a line ending with CRLF, byte per byte so nothing past the headers
+/// is consumed
+fn read_line(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+    while !line.ends_with(b"\r\n") {
+        if stream.read(&mut byte)? == 0 {
+            break;
+        }
+        line.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&line).trim_end().to_string())
+}
+
+/// Open a session `kind`, `exec` or `attach`, on an instance of the riklet
+/// listening on `address`, authenticated with `token`. The connection is
+/// upgraded to raw bytes once the riklet accepts it.
+pub fn open(
+    address: &str,
+    token: &str,
+    instance_id: &str,
+    kind: &str,
+    query: &str,
+) -> Result<Opening, RikError> {
+    let socket_address = address
+        .to_socket_addrs()
+        .map_err(communication_error)?
+        .next()
+        .ok_or_else(|| communication_error(format!("invalid address {}", address)))?;
+    let mut stream = TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT)
+        .map_err(communication_error)?;
+    write!(
+        stream,
+        "POST /instances/{}/{}?{} HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\nConnection: Upgrade\r\nUpgrade: tcp\r\nContent-Length: 0\r\n\r\n",
+        instance_id, kind, query, address, token
+    )
+    .map_err(communication_error)?;
+
+    let status_line = read_line(&mut stream).map_err(communication_error)?;
+    let status: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| communication_error(format!("invalid response {}", status_line)))?;
+    let mut content_length = 0;
+    loop {
+        let header = read_line(&mut stream).map_err(communication_error)?;
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    if status != 101 {
+        let mut message = String::new();
+        stream
+            .take(content_length)
+            .read_to_string(&mut message)
+            .map_err(communication_error)?;
+        return Ok(Opening::Refused(status, message));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let input = stream.try_clone().map_err(communication_error)?;
+    INPUTS.lock().unwrap().insert(id.clone(), input);
+    Ok(Opening::Opened(Session { id, stream }))
+}
+
+/// Writer of the input of an open session, which can only be taken once
+pub fn take_input(id: &str) -> Option<Box<dyn Write + Send>> {
+    INPUTS
+        .lock()
+        .unwrap()
+        .remove(id)
+        .map(|input| Box::new(Input(input)) as Box<dyn Write + Send>)
+}
+
+/// Input of a session, closed once the client is done with it so the
+/// process reading it sees its end
+struct Input(TcpStream);
+
+impl Write for Input {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown(Shutdown::Write);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serve a single connection, answering its request with `response`
+    /// then echoing what comes. Gives back the request line and headers.
+    fn riklet(response: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut header = String::new();
+            while header != "\r\n" {
+                header.clear();
+                reader.read_line(&mut header).unwrap();
+                request.push_str(&header);
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            std::io::copy(&mut reader, &mut stream).unwrap();
+            request
+        });
+        (address, server)
+    }
+
+    #[test]
+    fn test_open_session() {
+        let (address, server) = riklet("HTTP/1.1 101 Switching Protocols\r\nUpgrade: tcp\r\n\r\n");
+
+        let mut session =
+            match open(&address, "secret", "instance-1", "exec", "command=sh").unwrap() {
+                Opening::Opened(session) => session,
+                Opening::Refused(status, message) => panic!("refused: {} {}", status, message),
+            };
+        let mut input = take_input(session.id()).unwrap();
+        assert!(take_input(session.id()).is_none());
+
+        input.write_all(b"echo\n").unwrap();
+        drop(input);
+        let mut output = String::new();
+        session.read_to_string(&mut output).unwrap();
+
+        assert_eq!(output, "echo\n");
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /instances/instance-1/exec?command=sh HTTP/1.1\r\n"));
+        assert!(request.contains("\r\nAuthorization: Bearer secret\r\n"));
+    }
+
+    #[test]
+    fn test_refused_session() {
+        let (address, _) =
+            riklet("HTTP/1.1 404 Not Found\r\nContent-Length: 12\r\n\r\nNo container");
+
+        match open(&address, "secret", "instance-1", "attach", "").unwrap() {
+            Opening::Refused(status, message) => {
+                assert_eq!(status, 404);
+                assert_eq!(message, "No container");
+            }
+            Opening::Opened(_) => panic!("session should be refused"),
+        }
+    }
+}
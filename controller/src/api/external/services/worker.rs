@@ -0,0 +1,30 @@
+use crate::api::types::worker::WorkerDescription;
+use crate::core::instance::Instance;
+use crate::core::worker::Worker;
+use crate::database::{DatabaseError, RikRepository};
+use definition::now_millis;
+use rusqlite::Connection;
+
+/// Describe workers with the id of the instances placed on each of them
+pub fn describe_workers(
+    connection: &Connection,
+    workers: Vec<Worker>,
+) -> Result<Vec<WorkerDescription>, DatabaseError> {
+    let instances: Vec<Instance> = RikRepository::find_all(connection, "/instance")?
+        .into_iter()
+        .filter_map(|element| serde_json::from_value(element.value).ok())
+        .collect();
+    let now = now_millis();
+
+    Ok(workers
+        .into_iter()
+        .map(|worker| {
+            let hosted = instances
+                .iter()
+                .filter(|instance| instance.worker_id.as_deref() == Some(worker.id.as_str()))
+                .map(|instance| instance.id.clone())
+                .collect();
+            WorkerDescription::new(worker, hosted, now)
+        })
+        .collect())
+}
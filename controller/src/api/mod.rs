@@ -4,6 +4,7 @@ pub mod types;
 use definition::workload::WorkloadDefinition;
 use std::fmt::{Debug, Display, Formatter, Result};
 use thiserror::Error;
+use tracing::Span;
 
 use crate::database::DatabaseError;
 
@@ -33,6 +34,12 @@ pub enum RikError {
 
     #[error("Invalid name: {0}")]
     InvalidName(String),
+
+    #[error("No node port available: {0}")]
+    NoPortAvailable(String),
+
+    #[error("Invalid secret reference: {0}")]
+    InvalidSecretReference(String),
 }
 
 pub struct ApiChannel {
@@ -40,6 +47,8 @@ pub struct ApiChannel {
     pub workload_id: Option<String>,
     pub instance_id: Option<String>,
     pub workload_definition: Option<WorkloadDefinition>,
+    /// Span of the request, so the work it triggers is part of the same trace
+    pub span: Span,
 }
 impl Display for ApiChannel {
     fn fmt(&self, f: &mut Formatter) -> Result {
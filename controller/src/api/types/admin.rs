@@ -0,0 +1,150 @@
+use node_metrics::metrics::Metrics;
+use proto::admin::{InstancePlacement, WorkerInfo, WorkerState, WorkloadPlacement};
+use proto::common::{ResourceStatus, WorkerCapabilities, WorkloadKind};
+use serde::{Deserialize, Serialize};
+
+/// A worker as seen by the scheduler
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SchedulerWorker {
+    pub id: String,
+    pub address: String,
+    pub state: String,
+    /// Unix timestamp in milliseconds
+    pub last_heartbeat: Option<u64>,
+    pub metrics: Option<Metrics>,
+    pub capabilities: Option<Capabilities>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Capabilities {
+    pub kinds: Vec<String>,
+    pub arch: String,
+    pub runtimes: Vec<String>,
+}
+
+/// Instances of a workload with the worker they are placed on
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Placement {
+    pub workload_id: String,
+    pub replicas: u32,
+    pub status: String,
+    pub instances: Vec<InstanceAssignment>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InstanceAssignment {
+    pub instance_id: String,
+    pub worker_id: Option<String>,
+    pub status: String,
+}
+
+fn status_name(status: i32) -> String {
+    ResourceStatus::from_i32(status)
+        .unwrap_or_default()
+        .as_str_name()
+        .to_string()
+}
+
+impl From<WorkerCapabilities> for Capabilities {
+    fn from(value: WorkerCapabilities) -> Self {
+        Self {
+            kinds: value
+                .kinds
+                .into_iter()
+                .filter_map(WorkloadKind::from_i32)
+                .map(|kind| kind.as_str_name().to_string())
+                .collect(),
+            arch: value.arch,
+            runtimes: value.runtimes,
+        }
+    }
+}
+
+impl From<WorkerInfo> for SchedulerWorker {
+    fn from(value: WorkerInfo) -> Self {
+        Self {
+            state: WorkerState::from_i32(value.state)
+                .unwrap_or_default()
+                .as_str_name()
+                .to_string(),
+            id: value.id,
+            address: value.address,
+            last_heartbeat: value.last_heartbeat,
+            metrics: value
+                .metrics
+                .and_then(|metrics| Metrics::try_from(metrics).ok()),
+            capabilities: value.capabilities.map(Into::into),
+        }
+    }
+}
+
+impl From<InstancePlacement> for InstanceAssignment {
+    fn from(value: InstancePlacement) -> Self {
+        Self {
+            status: status_name(value.status),
+            instance_id: value.instance_id,
+            worker_id: value.worker_id,
+        }
+    }
+}
+
+impl From<WorkloadPlacement> for Placement {
+    fn from(value: WorkloadPlacement) -> Self {
+        Self {
+            status: status_name(value.status),
+            workload_id: value.workload_id,
+            replicas: value.replicas,
+            instances: value.instances.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placement_from_proto() {
+        let placement = Placement::from(WorkloadPlacement {
+            workload_id: "workload".to_string(),
+            replicas: 2,
+            status: ResourceStatus::Running.into(),
+            instances: vec![
+                InstancePlacement {
+                    instance_id: "instance-1".to_string(),
+                    worker_id: Some("worker-1".to_string()),
+                    status: ResourceStatus::Running.into(),
+                },
+                InstancePlacement {
+                    instance_id: "instance-2".to_string(),
+                    worker_id: None,
+                    status: ResourceStatus::Pending.into(),
+                },
+            ],
+        });
+        assert_eq!(placement.status, "RUNNING");
+        assert_eq!(
+            placement.instances[0].worker_id.as_deref(),
+            Some("worker-1")
+        );
+        assert_eq!(placement.instances[1].status, "PENDING");
+    }
+
+    #[test]
+    fn test_worker_from_proto() {
+        let worker = SchedulerWorker::from(WorkerInfo {
+            id: "worker-1".to_string(),
+            address: "127.0.0.1:4995".to_string(),
+            state: WorkerState::Ready.into(),
+            last_heartbeat: Some(1000),
+            metrics: None,
+            capabilities: Some(WorkerCapabilities {
+                kinds: vec![WorkloadKind::Pod.into()],
+                arch: "x86_64".to_string(),
+                runtimes: vec!["runc".to_string()],
+            }),
+        });
+        assert_eq!(worker.state, "READY");
+        assert_eq!(worker.capabilities.unwrap().kinds, vec!["POD"]);
+    }
+}
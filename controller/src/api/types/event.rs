@@ -0,0 +1,39 @@
+use proto::common::ClusterEvent;
+use serde::{Deserialize, Serialize};
+
+/// Something that happened to an object of the cluster, occurrences of the
+/// same event are merged and counted
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Kind of the involved object, e.g. instance or worker
+    pub object_kind: String,
+    pub object_id: String,
+    /// Empty for objects which are not namespaced, such as workers. Reported
+    /// events carry none, the one of their instance is filled in when recorded
+    pub namespace: String,
+    pub reason: String,
+    pub message: String,
+    /// Component which reported the event
+    pub source: String,
+    pub count: u32,
+    /// Unix timestamp in milliseconds of the first occurrence
+    pub first_timestamp: u64,
+    /// Unix timestamp in milliseconds of the last occurrence
+    pub last_timestamp: u64,
+}
+
+impl From<ClusterEvent> for Event {
+    fn from(event: ClusterEvent) -> Self {
+        Self {
+            object_kind: event.object_kind,
+            object_id: event.object_id,
+            namespace: String::new(),
+            reason: event.reason,
+            message: event.message,
+            source: event.source,
+            count: 1,
+            first_timestamp: event.timestamp,
+            last_timestamp: event.timestamp,
+        }
+    }
+}
@@ -50,6 +50,7 @@ impl InstanceStatus {
             4 => "Terminated".to_string(),
             5 => "Creating".to_string(),
             6 => "Destroying".to_string(),
+            7 => "Succeeded".to_string(),
             _ => "Creating".to_string(),
         };
 
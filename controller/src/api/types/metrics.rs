@@ -0,0 +1,13 @@
+use crate::database::MetricPoint;
+use serde::{Deserialize, Serialize};
+
+/// History of a worker or an instance over a time range
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MetricsSeries {
+    pub series: String,
+    pub from: u64,
+    pub to: u64,
+    /// Interval in milliseconds over which values are averaged
+    pub resolution: u64,
+    pub points: Vec<MetricPoint>,
+}
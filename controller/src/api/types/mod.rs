@@ -1,3 +1,8 @@
+pub mod admin;
 pub mod element;
+pub mod event;
 pub mod instance;
+pub mod metrics;
+pub mod secret;
 pub mod tenant;
+pub mod worker;
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Namespace of the secrets which are not given one, the only one of the
+/// workloads for now
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+fn default_namespace() -> String {
+    DEFAULT_NAMESPACE.to_string()
+}
+
+/// Whether `value` can name a secret or one of its keys, which become file
+/// names when the secret is mounted
+fn is_valid_name(value: &str) -> bool {
+    !value.is_empty()
+        && value != "."
+        && value != ".."
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Secret as it is created. Its values are never given back by the API.
+#[derive(Deserialize)]
+pub struct Secret {
+    pub name: String,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    /// Values by key
+    pub data: BTreeMap<String, String>,
+}
+
+impl Secret {
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_valid_name(&self.name) {
+            return Err(format!("Invalid secret name {}", self.name));
+        }
+        if self.data.is_empty() {
+            return Err("A secret needs at least one key".to_string());
+        }
+        match self.data.keys().find(|key| !is_valid_name(key)) {
+            Some(key) => Err(format!(
+                "Invalid key {}, only alphanumeric characters, '-', '_' and '.' are allowed",
+                key
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn metadata(&self) -> SecretMetadata {
+        SecretMetadata {
+            name: self.name.clone(),
+            namespace: self.namespace.clone(),
+            keys: self.data.keys().cloned().collect(),
+        }
+    }
+}
+
+/// What is listed of a secret
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SecretMetadata {
+    pub name: String,
+    pub namespace: String,
+    pub keys: Vec<String>,
+}
+
+/// Secret to delete
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SecretRef {
+    pub name: String,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let secret = |name: &str, keys: &[&str]| Secret {
+            name: name.to_string(),
+            namespace: default_namespace(),
+            data: keys
+                .iter()
+                .map(|key| (key.to_string(), "value".to_string()))
+                .collect(),
+        };
+
+        assert!(secret("db", &["password", "tls.crt", "API_KEY"])
+            .validate()
+            .is_ok());
+        assert!(secret("", &["password"]).validate().is_err());
+        assert!(secret("db/prod", &["password"]).validate().is_err());
+        assert!(secret("db", &[]).validate().is_err());
+        assert!(secret("db", &["../password"]).validate().is_err());
+        assert!(secret("db", &[".."]).validate().is_err());
+    }
+}
@@ -0,0 +1,28 @@
+use crate::core::worker::{Worker, WorkerState};
+use node_metrics::metrics::Metrics;
+use serde::{Deserialize, Serialize};
+
+/// A worker of the cluster along with the instances it hosts
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WorkerDescription {
+    pub id: String,
+    pub address: String,
+    pub state: WorkerState,
+    /// Unix timestamp in milliseconds of the last update received
+    pub last_seen: u64,
+    pub metrics: Option<Metrics>,
+    pub instances: Vec<String>,
+}
+
+impl WorkerDescription {
+    pub fn new(worker: Worker, instances: Vec<String>, now: u64) -> Self {
+        Self {
+            state: worker.state_at(now),
+            id: worker.id,
+            address: worker.address,
+            last_seen: worker.last_seen,
+            metrics: worker.metrics,
+            instances,
+        }
+    }
+}
@@ -1,30 +1,40 @@
+use crate::api::types::event::Event;
 use crate::api::{ApiChannel, Crud, RikError};
+use crate::core::events::{EventRecorder, EVENT_SOURCE};
 use crate::core::instance::Instance;
 use crate::core::instance_repository::InstanceRepositoryImpl;
 use crate::core::instance_service::InstanceServiceImpl;
+use crate::core::metrics_history::MetricsHistory;
 use crate::core::worker_repository::WorkerRepositoryImpl;
 use crate::core::worker_service::WorkerServiceImpl;
 use crate::core::{InstanceService, Listener, WorkerService};
 use crate::database::RikDataBase;
 use definition::workload::WorkloadDefinition;
+use definition::InstanceStatus;
 
-use proto::common::{InstanceMetric, WorkerMetric};
+use proto::common::{ClusterEvent, InstanceMetric, WorkerMetric};
 use std::net::SocketAddr;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
 use std::thread;
-use tracing::{error, event, Level};
+use tracing::{error, event, info_span, Instrument, Level, Span};
 
 pub enum CoreInternalEvent {
-    InstanceStatusUpdate(InstanceMetric),
+    InstanceStatusUpdate {
+        identifier: String,
+        metric: InstanceMetric,
+    },
     WorkerStatusUpdate {
         identifier: String,
         address: SocketAddr,
         metric: WorkerMetric,
     },
+    /// Event reported by the scheduler or a worker
+    ClusterEvent(ClusterEvent),
     Legacy(ApiChannel),
-    CreateInstance(Instance, WorkloadDefinition),
-    DeleteInstance(Instance, WorkloadDefinition),
+    /// The span is the one of the request asking for it
+    CreateInstance(Instance, WorkloadDefinition, Span),
+    DeleteInstance(Instance, WorkloadDefinition, Span),
 }
 
 /// Core is meant to be a mediator between controller components
@@ -36,6 +46,8 @@ pub enum CoreInternalEvent {
 pub struct Core {
     instance_service: InstanceServiceImpl,
     worker_service: WorkerServiceImpl,
+    metrics_history: MetricsHistory,
+    events: EventRecorder,
 
     internal_receiver: Receiver<CoreInternalEvent>,
     internal_sender: Sender<CoreInternalEvent>,
@@ -48,11 +60,13 @@ impl Core {
         let instance_repo = InstanceRepositoryImpl::new(database.clone());
         let instance_svc = InstanceServiceImpl::new(instance_repo, internal_sender.clone()).await?;
 
-        let worker_repo = WorkerRepositoryImpl::new(database);
+        let worker_repo = WorkerRepositoryImpl::new(database.clone());
         let worker_svc = WorkerServiceImpl::new(worker_repo);
         Ok(Core {
             instance_service: instance_svc,
             worker_service: worker_svc,
+            metrics_history: MetricsHistory::new(database.clone()),
+            events: EventRecorder::new(database),
             internal_receiver,
             internal_sender,
         })
@@ -74,6 +88,7 @@ impl Core {
     /// Handle messages that are from Legacy events
     /// Waiting to be removed when legacy code is removed
     #[tracing::instrument(
+        parent = &notification.span,
         skip(self, notification),
         fields(
             workload_id = %notification.workload_id.as_ref().unwrap_or(&String::from("None")), 
@@ -91,27 +106,56 @@ impl Core {
             Crud::Create => {
                 let instance: Instance = notification.into();
                 self.internal_sender
-                    .send(CoreInternalEvent::CreateInstance(instance, definition))
+                    .send(CoreInternalEvent::CreateInstance(
+                        instance,
+                        definition,
+                        Span::current(),
+                    ))
                     .unwrap();
             }
             Crud::Delete => {
                 let instance: Instance = notification.into();
                 self.internal_sender
-                    .send(CoreInternalEvent::DeleteInstance(instance, definition))
+                    .send(CoreInternalEvent::DeleteInstance(
+                        instance,
+                        definition,
+                        Span::current(),
+                    ))
                     .unwrap();
             }
         };
     }
 
+    /// Record an event about an instance, reported by the controller itself
+    fn record_event(&mut self, instance: &Instance, reason: &str, message: String) {
+        let event = ClusterEvent::instance(&instance.id, reason, message, EVENT_SOURCE.to_string());
+        let mut event: Event = event.into();
+        // The instance may not be stored anymore, e.g. when its creation failed
+        event.namespace = instance.namespace.clone();
+        self.events.record(event);
+    }
+
     pub async fn listen_notification(mut self, receiver: Receiver<ApiChannel>) {
         self.instance_service.run_listen_thread();
         Core::run_legacy_listener(receiver, self.get_sender());
         loop {
             let message = self.internal_receiver.recv().unwrap();
             match message {
-                CoreInternalEvent::InstanceStatusUpdate(instance_metric) => self
-                    .instance_service
-                    .handle_instance_status_update(instance_metric),
+                CoreInternalEvent::InstanceStatusUpdate { identifier, metric } => {
+                    let span =
+                        info_span!("instance_status_update", instance_id = %metric.instance_id);
+                    telemetry::set_parent(&span, &metric.trace_context);
+                    let _entered = span.enter();
+                    // Updates sent by a worker, or on its behalf, are identified by its id
+                    let worker_id = match self.worker_service.is_registered(&identifier) {
+                        true => Some(identifier),
+                        false => None,
+                    };
+                    self.metrics_history
+                        .record_instance(&metric.instance_id, &InstanceStatus::from(metric.status));
+                    self.instance_service
+                        .handle_instance_status_update(metric, worker_id)
+                }
                 CoreInternalEvent::WorkerStatusUpdate {
                     identifier,
                     address,
@@ -124,24 +168,54 @@ impl Core {
                         address,
                         metric
                     );
-                    self.worker_service
-                        .handle_metric_update(identifier, address, metric)
-                        .unwrap()
+                    self.metrics_history.record_worker(&identifier, &metric);
+                    if let Err(e) = self.worker_service.handle_metric_update(
+                        identifier.clone(),
+                        address,
+                        metric,
+                    ) {
+                        error!(
+                            "Could not update the status of worker {}: {}",
+                            identifier, e
+                        );
+                    }
                 }
+                CoreInternalEvent::ClusterEvent(event) => self.events.record(event.into()),
                 CoreInternalEvent::Legacy(notification) => {
                     self.handle_legacy_notification(notification).await
                 }
-                CoreInternalEvent::CreateInstance(instance, definition) => {
-                    self.instance_service
-                        .create_instance(instance, definition)
+                CoreInternalEvent::CreateInstance(instance, definition, span) => {
+                    let message = format!("Instance of workload {} created", instance.workload_id);
+                    match self
+                        .instance_service
+                        .create_instance(instance.clone(), definition)
+                        .instrument(span)
                         .await
-                        .unwrap();
+                    {
+                        Ok(()) => self.record_event(&instance, "Created", message),
+                        Err(e) => {
+                            error!("Could not create instance {}: {}", instance.id, e);
+                            self.record_event(&instance, "FailedCreate", e.to_string())
+                        }
+                    }
                 }
-                CoreInternalEvent::DeleteInstance(instance, definition) => {
-                    self.instance_service
-                        .delete_instance(instance, definition)
+                CoreInternalEvent::DeleteInstance(instance, definition, span) => {
+                    match self
+                        .instance_service
+                        .delete_instance(instance.clone(), definition)
+                        .instrument(span)
                         .await
-                        .unwrap();
+                    {
+                        Ok(()) => self.record_event(
+                            &instance,
+                            "Deleting",
+                            "Instance deletion requested".to_string(),
+                        ),
+                        Err(e) => {
+                            error!("Could not delete instance {}: {}", instance.id, e);
+                            self.record_event(&instance, "FailedDelete", e.to_string())
+                        }
+                    }
                 }
             }
         }
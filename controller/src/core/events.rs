@@ -0,0 +1,137 @@
+use crate::api::types::event::Event;
+use crate::core::instance::Instance;
+use crate::database::{EventFilter, EventsRepository, RikDataBase, RikRepository};
+use definition::now_millis;
+use rusqlite::Connection;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Delay between two expirations of old events
+const EXPIRATION_INTERVAL_MS: u64 = 60 * 1000;
+/// Source of the events reported by the controller
+pub const EVENT_SOURCE: &str = "controller";
+
+/// Records the events reported by the components of the cluster so they
+/// can be listed later on
+pub struct EventRecorder {
+    database: Arc<RikDataBase>,
+    last_expiration: u64,
+}
+
+impl EventRecorder {
+    pub fn new(database: Arc<RikDataBase>) -> EventRecorder {
+        EventRecorder {
+            database,
+            last_expiration: 0,
+        }
+    }
+
+    pub fn record(&mut self, mut event: Event) {
+        info!(
+            "Event on {} {}: {} {}",
+            event.object_kind, event.object_id, event.reason, event.message
+        );
+        let connection = match self.database.open() {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Could not record event on {}: {}", event.object_id, e);
+                return;
+            }
+        };
+        if event.object_kind == "instance" && event.namespace.is_empty() {
+            event.namespace = instance_namespace(&connection, &event.object_id).unwrap_or_default();
+        }
+        if let Err(e) = EventsRepository::record(&connection, &event) {
+            error!("Could not record event on {}: {}", event.object_id, e);
+        }
+        let now = now_millis();
+        if now.saturating_sub(self.last_expiration) > EXPIRATION_INTERVAL_MS {
+            self.last_expiration = now;
+            if let Err(e) = EventsRepository::expire(&connection, now) {
+                error!("Could not expire old events: {}", e);
+            }
+        }
+    }
+}
+
+/// Namespace of an instance, taken from the events already recorded about it
+/// once the instance is deleted
+fn instance_namespace(connection: &Connection, instance_id: &str) -> Option<String> {
+    let instance = RikRepository::find_one(connection, &instance_id.to_string(), "/instance")
+        .ok()
+        .and_then(|element| serde_json::from_value::<Instance>(element.value).ok());
+    if let Some(instance) = instance {
+        return Some(instance.namespace);
+    }
+    let filter = EventFilter {
+        object_kind: Some("instance"),
+        object_id: Some(instance_id),
+        ..Default::default()
+    };
+    EventsRepository::list(connection, &filter)
+        .ok()?
+        .into_iter()
+        .next()
+        .map(|event| event.namespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::db_connection;
+    use definition::workload::{Spec, WorkloadKind};
+    use proto::common::ClusterEvent;
+    use rstest::rstest;
+
+    fn namespaces(connection: &Connection, instance_id: &str) -> Vec<String> {
+        let filter = EventFilter {
+            object_id: Some(instance_id),
+            ..Default::default()
+        };
+        EventsRepository::list(connection, &filter)
+            .unwrap()
+            .into_iter()
+            .map(|event| event.namespace)
+            .collect()
+    }
+
+    #[rstest]
+    fn test_instance_namespace(db_connection: Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        let mut instance = Instance::new(
+            "workload".to_string(),
+            WorkloadKind::Pod,
+            Some("namespaced-instance".to_string()),
+            Spec {
+                containers: vec![],
+                function: None,
+                termination_grace_period_seconds: None,
+                restart_policy: Default::default(),
+                share_process_namespace: false,
+                volumes: vec![],
+            },
+        );
+        instance.namespace = "other".to_string();
+        RikRepository::upsert(
+            &connection,
+            &instance.id,
+            &instance.get_full_name(),
+            &serde_json::to_string(&instance).unwrap(),
+            "/instance",
+        )
+        .unwrap();
+
+        let mut recorder = EventRecorder::new(db_connection.clone());
+        let event = |reason: &str| {
+            ClusterEvent::instance(&instance.id, reason, String::new(), "riklet".to_string())
+        };
+        recorder.record(event("Started").into());
+        RikRepository::delete(&connection, &instance.id).unwrap();
+        recorder.record(event("Killing").into());
+
+        assert_eq!(
+            namespaces(&connection, &instance.id),
+            vec!["other", "other"]
+        );
+    }
+}
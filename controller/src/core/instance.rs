@@ -1,8 +1,49 @@
 use crate::api::ApiChannel;
-use definition::workload::{Spec, WorkloadKind};
+use definition::now_millis;
+use definition::workload::{Protocol, Spec, WorkloadKind};
 use definition::InstanceStatus;
 use names::{Generator, Name};
+use proto::common::InstancePlacement;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Port of a worker forwarded to an instance
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlacementPort {
+    pub port: u32,
+    pub target_port: u32,
+    #[serde(default)]
+    pub protocol: Protocol,
+}
+
+/// Where an instance runs and how to reach it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Placement {
+    /// IP address of the worker
+    pub host_ip: String,
+    /// IP address given to the microVM of a function
+    pub guest_ip: Option<String>,
+    /// Ports exposed on the worker
+    pub ports: Vec<PlacementPort>,
+}
+
+impl From<InstancePlacement> for Placement {
+    fn from(value: InstancePlacement) -> Self {
+        Self {
+            host_ip: value.host_ip,
+            guest_ip: value.guest_ip,
+            ports: value
+                .ports
+                .into_iter()
+                .map(|port| PlacementPort {
+                    port: port.port,
+                    target_port: port.target_port,
+                    protocol: port.protocol().into(),
+                })
+                .collect(),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Instance {
@@ -19,6 +60,27 @@ pub struct Instance {
     pub status: InstanceStatus,
 
     pub spec: Spec,
+
+    /// Worker the instance was placed on by the scheduler
+    #[serde(default)]
+    pub worker_id: Option<String>,
+
+    /// Short cause of the current status, e.g. ImageError
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Human readable explanation of the current status
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Exit code of the container or microVM, when it exited
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Unix timestamp in milliseconds at which the instance last entered
+    /// each status, keyed by status
+    #[serde(default)]
+    pub phases: BTreeMap<String, u64>,
+    /// Reported by the worker once the instance is running
+    #[serde(default)]
+    pub placement: Option<Placement>,
 }
 
 impl From<ApiChannel> for Instance {
@@ -31,6 +93,12 @@ impl From<ApiChannel> for Instance {
             id: value.instance_id.unwrap(),
             status: InstanceStatus::Pending,
             spec: workload_definition.spec,
+            worker_id: None,
+            reason: None,
+            message: None,
+            exit_code: None,
+            phases: Self::initial_phases(),
+            placement: None,
         }
     }
 }
@@ -44,9 +112,28 @@ impl Instance {
             id: id.unwrap_or_else(Self::generate_name),
             status: InstanceStatus::Pending,
             spec,
+            worker_id: None,
+            reason: None,
+            message: None,
+            exit_code: None,
+            phases: Self::initial_phases(),
+            placement: None,
         }
     }
 
+    fn initial_phases() -> BTreeMap<String, u64> {
+        BTreeMap::from([(InstanceStatus::Pending.to_string(), now_millis())])
+    }
+
+    /// Move the instance to `status`, the time it was entered at is only
+    /// kept when the status changes
+    pub fn set_status(&mut self, status: InstanceStatus, timestamp: u64) {
+        if self.status != status || !self.phases.contains_key(&status.to_string()) {
+            self.phases.insert(status.to_string(), timestamp);
+        }
+        self.status = status;
+    }
+
     pub fn generate_name() -> String {
         let mut random_name_generator = Generator::with_naming(Name::Numbered);
         random_name_generator.next().unwrap()
@@ -56,3 +143,35 @@ impl Instance {
         format!("/instance/{}/{}/{}", self.kind, self.namespace, self.id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_status_phases() {
+        let mut instance = Instance::new(
+            "workload".to_string(),
+            WorkloadKind::Pod,
+            None,
+            Spec {
+                containers: vec![],
+                function: None,
+                termination_grace_period_seconds: None,
+                restart_policy: Default::default(),
+                share_process_namespace: false,
+                volumes: vec![],
+            },
+        );
+        assert!(instance.phases.contains_key("Pending"));
+
+        instance.set_status(InstanceStatus::Running, 10);
+        instance.set_status(InstanceStatus::Running, 20);
+        assert_eq!(instance.phases["Running"], 10);
+
+        instance.set_status(InstanceStatus::Failed, 30);
+        instance.set_status(InstanceStatus::Running, 40);
+        assert_eq!(instance.phases["Running"], 40);
+        assert_eq!(instance.phases["Failed"], 30);
+    }
+}
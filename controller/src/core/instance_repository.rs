@@ -1,10 +1,16 @@
 use crate::api::RikError;
 use crate::core::instance::Instance;
 use crate::core::InstanceRepository;
-use crate::database::{RikDataBase, RikRepository};
+use crate::database::{PortsRepository, RikDataBase, RikRepository, SecretsKey, SecretsRepository};
+use definition::workload::{Protocol, SecretItems, SecretValue, Spec, VolumeSource};
 use rusqlite::Connection;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 use std::sync::Arc;
 
+/// Node ports given to the instances which did not request one
+const WORKLOAD_PORTS: Range<u16> = 45000..50000;
+
 pub struct InstanceRepositoryImpl {
     database: Arc<RikDataBase>,
 }
@@ -27,11 +33,8 @@ impl InstanceRepositoryImpl {
 impl InstanceRepository for InstanceRepositoryImpl {
     fn fetch_instance(&self, instance_id: String) -> Result<Instance, RikError> {
         let conn = self.get_connection()?;
-        let element = RikRepository::check_duplicate_name(
-            &conn,
-            &format!("/instance/%/default/{}", &instance_id),
-        )
-        .map_err(|_| RikError::InvalidName(instance_id))?;
+        let element = RikRepository::find_one(&conn, &instance_id, "/instance")
+            .map_err(|_| RikError::InvalidName(instance_id))?;
 
         serde_json::from_value::<Instance>(element.value).map_err(|e| {
             RikError::InternalCommunicationError(format!("Could not parse instance: {}", e))
@@ -57,15 +60,92 @@ impl InstanceRepository for InstanceRepositoryImpl {
         let connection = self.get_connection()?;
         RikRepository::delete(&connection, &instance.id).map_err(|e| {
             RikError::InternalCommunicationError(format!("Could not delete instance: {}", e))
+        })?;
+        PortsRepository::release(&connection, &instance.id).map_err(|e| {
+            RikError::InternalCommunicationError(format!("Could not release port: {}", e))
+        })
+    }
+
+    fn reserve_port(
+        &self,
+        instance_id: &str,
+        requested: Option<u16>,
+        protocol: Protocol,
+    ) -> Result<u16, RikError> {
+        let connection = self.get_connection()?;
+        PortsRepository::reserve(
+            &connection,
+            instance_id,
+            requested,
+            protocol,
+            WORKLOAD_PORTS,
+        )
+        .map_err(RikError::DatabaseError)?
+        .ok_or_else(|| {
+            RikError::NoPortAvailable(format!(
+                "all {} ports from {} to {} are reserved",
+                protocol,
+                WORKLOAD_PORTS.start,
+                WORKLOAD_PORTS.end - 1
+            ))
         })
     }
+
+    fn resolve_secrets(&self, namespace: &str, spec: &mut Spec) -> Result<(), RikError> {
+        let connection = self.get_connection()?;
+        let key = SecretsKey::load().map_err(RikError::DatabaseError)?;
+        // Secrets are read once, however many times they are referenced
+        let mut secrets: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+        let mut secret = |name: &str| -> Result<BTreeMap<String, String>, RikError> {
+            if let Some(data) = secrets.get(name) {
+                return Ok(data.clone());
+            }
+            let data = SecretsRepository::get(&connection, &key, namespace, name)
+                .map_err(RikError::DatabaseError)?
+                .ok_or_else(|| {
+                    RikError::InvalidSecretReference(format!(
+                        "secret {} not found in namespace {}",
+                        name, namespace
+                    ))
+                })?;
+            secrets.insert(name.to_string(), data.clone());
+            Ok(data)
+        };
+
+        for container in &mut spec.containers {
+            for variable in container.env.iter_mut().flatten() {
+                if let Some(source) = variable.value_from.as_mut() {
+                    let reference = &source.secret_key_ref;
+                    let value =
+                        secret(&reference.name)?
+                            .remove(&reference.key)
+                            .ok_or_else(|| {
+                                RikError::InvalidSecretReference(format!(
+                                    "key {} not found in secret {}",
+                                    reference.key, reference.name
+                                ))
+                            })?;
+                    source.value = SecretValue(value);
+                }
+            }
+        }
+        for volume in &mut spec.volumes {
+            if let VolumeSource::Secret { secret_name, items } = &mut volume.source {
+                *items = SecretItems(secret(secret_name)?);
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::types::secret::Secret;
     use crate::tests::fixtures::db_connection;
-    use definition::workload::{Spec, WorkloadKind};
+    use definition::workload::{
+        Container, EnvConfig, EnvSource, SecretKeyRef, Volume, WorkloadKind,
+    };
     use rstest::rstest;
 
     #[rstest]
@@ -78,6 +158,10 @@ mod tests {
         let spec = Spec {
             containers: vec![],
             function: None,
+            termination_grace_period_seconds: None,
+            restart_policy: Default::default(),
+            share_process_namespace: false,
+            volumes: vec![],
         };
 
         let instance = Instance::new(
@@ -108,6 +192,10 @@ mod tests {
         let spec = Spec {
             containers: vec![],
             function: None,
+            termination_grace_period_seconds: None,
+            restart_policy: Default::default(),
+            share_process_namespace: false,
+            volumes: vec![],
         };
 
         let instance = Instance::new(
@@ -145,6 +233,10 @@ mod tests {
         let spec = Spec {
             containers: vec![],
             function: None,
+            termination_grace_period_seconds: None,
+            restart_policy: Default::default(),
+            share_process_namespace: false,
+            volumes: vec![],
         };
 
         let instance = Instance::new(
@@ -175,6 +267,10 @@ mod tests {
         let spec = Spec {
             containers: vec![],
             function: None,
+            termination_grace_period_seconds: None,
+            restart_policy: Default::default(),
+            share_process_namespace: false,
+            volumes: vec![],
         };
 
         let instance = Instance::new(
@@ -194,4 +290,82 @@ mod tests {
 
         assert_eq!(fetch_instance.id, instance_id);
     }
+
+    #[rstest]
+    fn test_resolve_secrets(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        let secret = Secret {
+            name: "database".to_string(),
+            namespace: "default".to_string(),
+            data: [("password".to_string(), "hunter2".to_string())].into(),
+        };
+        SecretsRepository::create(&connection, &SecretsKey::load().unwrap(), &secret).unwrap();
+
+        let env = |name: &str, key: &str| EnvConfig {
+            name: "PASSWORD".to_string(),
+            value: String::new(),
+            value_from: Some(EnvSource {
+                secret_key_ref: SecretKeyRef {
+                    name: name.to_string(),
+                    key: key.to_string(),
+                },
+                value: SecretValue::default(),
+            }),
+        };
+        let spec = |env: EnvConfig, secret_name: &str| Spec {
+            containers: vec![Container {
+                name: "app".to_string(),
+                image: "app:latest".to_string(),
+                env: Some(vec![env]),
+                ports: None,
+                command: None,
+                args: None,
+                working_dir: None,
+                user: None,
+                volume_mounts: vec![],
+            }],
+            function: None,
+            termination_grace_period_seconds: None,
+            restart_policy: Default::default(),
+            share_process_namespace: false,
+            volumes: vec![Volume {
+                name: "credentials".to_string(),
+                source: VolumeSource::Secret {
+                    secret_name: secret_name.to_string(),
+                    items: SecretItems::default(),
+                },
+            }],
+        };
+        let instance_repository = InstanceRepositoryImpl::new(db_connection);
+
+        let mut resolved = spec(env("database", "password"), "database");
+        instance_repository
+            .resolve_secrets("default", &mut resolved)
+            .unwrap();
+        let variable = &resolved.containers[0].env.as_ref().unwrap()[0];
+        assert_eq!(variable.resolved_value(), "hunter2");
+        // The values are neither printed nor serialized
+        assert!(!format!("{:?}", resolved).contains("hunter2"));
+        assert!(!serde_json::to_string(&resolved)
+            .unwrap()
+            .contains("hunter2"));
+        assert_eq!(
+            resolved.volumes[0].source,
+            VolumeSource::Secret {
+                secret_name: "database".to_string(),
+                items: SecretItems(secret.data.clone()),
+            }
+        );
+
+        for (mut invalid, namespace) in [
+            (spec(env("database", "user"), "database"), "default"),
+            (spec(env("database", "password"), "missing"), "default"),
+            (spec(env("database", "password"), "database"), "other"),
+        ] {
+            assert!(matches!(
+                instance_repository.resolve_secrets(namespace, &mut invalid),
+                Err(RikError::InvalidSecretReference(_))
+            ));
+        }
+    }
 }
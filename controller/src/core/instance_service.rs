@@ -3,7 +3,9 @@ use crate::core::core::CoreInternalEvent;
 use crate::core::instance::Instance;
 use crate::core::instance_repository::InstanceRepositoryImpl;
 use crate::core::{with_backoff, InstanceRepository, InstanceService, Listener};
+use crate::health;
 use async_trait::async_trait;
+use definition::now_millis;
 use definition::workload::{WorkloadDefinition, WorkloadKind};
 use definition::InstanceStatus;
 use dotenv::dotenv;
@@ -11,20 +13,16 @@ use proto::common::worker_status::Status;
 use proto::common::InstanceMetric;
 use proto::controller::controller_client::ControllerClient;
 use proto::controller::WorkloadScheduling;
-use rand::Rng;
 use std::net::SocketAddr;
-use std::ops::Range;
 use std::str::FromStr;
 use std::sync::mpsc::Sender;
-use tracing::{error, event, info, Level};
+use tracing::{error, event, info, warn, Level};
 
-const WORKLOAD_PORTS: Range<u16> = 45000..50000;
 const DEFAULT_SCHEDULER_URL: &str = "http://localhost:4996";
 
-pub fn mutate_function_port(mut workload: WorkloadDefinition) -> WorkloadDefinition {
-    let random_port = rand::thread_rng().gen_range(WORKLOAD_PORTS);
-    workload.set_function_port(random_port);
-    workload
+/// Address of the scheduler, overridden with `SCHEDULER_URL`
+pub(crate) fn scheduler_url() -> String {
+    std::env::var("SCHEDULER_URL").unwrap_or_else(|_| DEFAULT_SCHEDULER_URL.to_string())
 }
 
 pub struct InstanceServiceImpl {
@@ -39,6 +37,7 @@ impl Listener for InstanceServiceImpl {
         let sender = self.sender.clone();
         tokio::spawn(async move {
             let mut stream = client.get_status_updates(()).await.unwrap().into_inner();
+            let _connected = health::SCHEDULER.guard();
             while let Some(notification) = stream.message().await.unwrap() {
                 let status = notification.status.unwrap();
                 match status {
@@ -49,9 +48,15 @@ impl Listener for InstanceServiceImpl {
                             &notification.identifier
                         );
                         sender
-                            .send(CoreInternalEvent::InstanceStatusUpdate(metric))
+                            .send(CoreInternalEvent::InstanceStatusUpdate {
+                                identifier: notification.identifier,
+                                metric,
+                            })
                             .unwrap();
                     }
+                    Status::Event(event) => {
+                        sender.send(CoreInternalEvent::ClusterEvent(event)).unwrap();
+                    }
                     Status::Worker(metric) => {
                         sender
                             .send(CoreInternalEvent::WorkerStatusUpdate {
@@ -76,8 +81,7 @@ impl InstanceServiceImpl {
         sender: Sender<CoreInternalEvent>,
     ) -> Result<InstanceServiceImpl, RikError> {
         dotenv().ok();
-        let scheduler_url =
-            std::env::var("SCHEDULER_URL").unwrap_or_else(|_| DEFAULT_SCHEDULER_URL.to_string());
+        let scheduler_url = scheduler_url();
 
         let controller_client =
             with_backoff(|| async { Ok(ControllerClient::connect(scheduler_url.clone()).await?) })
@@ -91,6 +95,7 @@ impl InstanceServiceImpl {
         Ok(client)
     }
 
+    #[tracing::instrument(skip_all, fields(instance_id = %instance.id))]
     async fn schedule_instance(
         &mut self,
         instance: Instance,
@@ -99,14 +104,32 @@ impl InstanceServiceImpl {
     ) -> Result<(), tonic::Status> {
         let scheduling = WorkloadScheduling {
             workload_id: instance.workload_id.clone(),
-            definition: serde_json::to_string(&workload_def).unwrap(),
+            definition: Some(workload_def.into()),
             action: action as i32,
             instance_id: instance.id.clone(),
         };
-        let request = tonic::Request::new(scheduling);
+        let mut request = tonic::Request::new(scheduling);
+        telemetry::inject_metadata(request.metadata_mut());
         self.client.schedule_instance(request).await?;
         Ok(())
     }
+
+    async fn register_and_schedule(
+        &mut self,
+        instance: Instance,
+        mut workload_def: WorkloadDefinition,
+    ) -> Result<(), RikError> {
+        // Only what is sent to the scheduler holds the values of the secrets,
+        // which are resolved first so a missing one leaves nothing behind
+        self.service
+            .resolve_secrets(&instance.namespace, &mut workload_def.spec)?;
+        self.service.register_instance(instance.clone())?;
+        self.schedule_instance(instance, workload_def, Crud::Create)
+            .await
+            .map_err(|e| {
+                RikError::InternalCommunicationError(format!("Could not schedule instance: {}", e))
+            })
+    }
 }
 
 #[async_trait]
@@ -119,16 +142,27 @@ impl InstanceService for InstanceServiceImpl {
         event!(Level::INFO, "Schedule instance {}", instance.id);
 
         if instance.kind == WorkloadKind::Function {
-            workload_def = mutate_function_port(workload_def);
+            let requested = workload_def.function_port();
+            let protocol = workload_def.function_protocol();
+            let port = self
+                .service
+                .reserve_port(&instance.id, requested, protocol)?;
+            if requested.is_none() {
+                workload_def.set_function_port(port);
+            }
         }
 
         instance.spec = workload_def.spec.clone();
-        self.service.register_instance(instance.clone())?;
-        self.schedule_instance(instance, workload_def, Crud::Create)
-            .await
-            .map_err(|e| {
-                RikError::InternalCommunicationError(format!("Could not schedule instance: {}", e))
-            })
+        let result = self
+            .register_and_schedule(instance.clone(), workload_def)
+            .await;
+        // The instance will never run, its record and port are given back
+        if result.is_err() {
+            if let Err(e) = self.service.delete_instance(instance) {
+                error!("Could not roll back the creation of an instance: {}", e);
+            }
+        }
+        result
     }
 
     async fn delete_instance(
@@ -144,18 +178,64 @@ impl InstanceService for InstanceServiceImpl {
             })
     }
 
-    fn handle_instance_status_update(&mut self, instance_metric: InstanceMetric) {
+    fn handle_instance_status_update(
+        &mut self,
+        instance_metric: InstanceMetric,
+        worker_id: Option<String>,
+    ) {
         let new_status = InstanceStatus::from(instance_metric.status);
-        let mut instance = self
+        let mut instance = match self
             .service
             .fetch_instance(instance_metric.instance_id.clone())
-            .unwrap();
+        {
+            Ok(instance) => instance,
+            Err(e) => {
+                error!(
+                    "Could not update the status of instance {}: {}",
+                    instance_metric.instance_id, e
+                );
+                return;
+            }
+        };
         info!(
             "Instance {}, status update, {} -> {}",
             instance.id, instance.status, &new_status
         );
+        let details = instance_metric.details.clone().unwrap_or_default();
+        if let Some(reason) = &details.reason {
+            warn!(
+                "Instance {} is {}: {} {}",
+                instance.id,
+                &new_status,
+                reason,
+                details.message.as_deref().unwrap_or_default()
+            );
+        }
 
-        instance.status = new_status;
+        // Statuses are only timestamped by up to date components
+        let timestamp = match instance_metric.timestamp {
+            0 => now_millis(),
+            timestamp => timestamp,
+        };
+        instance.set_status(new_status, timestamp);
+        instance.reason = details.reason;
+        instance.message = details.message;
+        instance.exit_code = details.exit_code;
+        match instance_metric.placement {
+            Some(placement) => instance.placement = Some(placement.into()),
+            // The instance is (re)scheduled, it may not run on the same worker
+            None if matches!(
+                instance.status,
+                InstanceStatus::Pending | InstanceStatus::Creating
+            ) =>
+            {
+                instance.placement = None
+            }
+            None => (),
+        }
+        if worker_id.is_some() {
+            instance.worker_id = worker_id;
+        }
 
         let repo_update_rs = match instance.status {
             InstanceStatus::Terminated => self.service.delete_instance(instance),
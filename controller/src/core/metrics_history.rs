@@ -0,0 +1,161 @@
+use crate::database::{MetricsRepository, RikDataBase};
+use definition::now_millis;
+use definition::InstanceStatus;
+use proto::common::WorkerMetric;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::error;
+
+/// Delay between two compactions of the history
+const COMPACTION_INTERVAL_MS: u64 = 10 * 60 * 1000;
+/// Delay between two samples of the instance statuses, workers report
+/// their metrics at the same pace
+const INSTANCE_SAMPLING_INTERVAL_MS: u64 = 15 * 1000;
+
+pub fn worker_series(worker_id: &str) -> String {
+    format!("worker/{}", worker_id)
+}
+
+pub fn instance_series(instance_id: &str) -> String {
+    format!("instance/{}", instance_id)
+}
+
+/// Records the metrics received from the scheduler so they can be queried later on
+pub struct MetricsHistory {
+    database: Arc<RikDataBase>,
+    last_compaction: u64,
+    /// Last known status of each instance, sampled at a fixed interval
+    instances: HashMap<String, InstanceStatus>,
+    last_sample: u64,
+}
+
+impl MetricsHistory {
+    pub fn new(database: Arc<RikDataBase>) -> MetricsHistory {
+        MetricsHistory {
+            database,
+            last_compaction: 0,
+            instances: HashMap::new(),
+            last_sample: 0,
+        }
+    }
+
+    pub fn record_worker(&mut self, worker_id: &str, metric: &WorkerMetric) {
+        let metrics = match &metric.metrics {
+            Some(metrics) => metrics,
+            None => return,
+        };
+        let mut values = Vec::new();
+        if let Some(cpu) = &metrics.cpu {
+            values.push(("cpu_total", cpu.total as f64));
+            values.push(("cpu_free", cpu.free as f64));
+        }
+        if let Some(memory) = &metrics.memory {
+            values.push(("memory_total", memory.total as f64));
+            values.push(("memory_free", memory.free as f64));
+        }
+        if !metrics.disks.is_empty() {
+            let disk_total: u64 = metrics.disks.iter().map(|disk| disk.total).sum();
+            let disk_free: u64 = metrics.disks.iter().map(|disk| disk.free).sum();
+            values.push(("disk_total", disk_total as f64));
+            values.push(("disk_free", disk_free as f64));
+        }
+        self.record(&worker_series(worker_id), &values);
+        self.sample_instances(now_millis());
+    }
+
+    /// Instances only report their status on transitions, the last one is
+    /// kept and sampled as gauges along with the worker metrics.
+    pub fn record_instance(&mut self, instance_id: &str, status: &InstanceStatus) {
+        if *status == InstanceStatus::Terminated {
+            self.instances.remove(instance_id);
+        } else {
+            self.instances
+                .insert(instance_id.to_string(), status.clone());
+        }
+    }
+
+    /// Record the status of every known instance, at most once per
+    /// [INSTANCE_SAMPLING_INTERVAL_MS], so that once averaged the gauges give
+    /// the share of time spent running or failed.
+    fn sample_instances(&mut self, now: u64) {
+        if now.saturating_sub(self.last_sample) < INSTANCE_SAMPLING_INTERVAL_MS {
+            return;
+        }
+        self.last_sample = now;
+        let gauge = |expected: bool| if expected { 1.0 } else { 0.0 };
+        let samples: Vec<_> = self
+            .instances
+            .iter()
+            .map(|(instance_id, status)| {
+                (
+                    instance_series(instance_id),
+                    [
+                        ("running", gauge(*status == InstanceStatus::Running)),
+                        ("failed", gauge(*status == InstanceStatus::Failed)),
+                    ],
+                )
+            })
+            .collect();
+        for (series, values) in samples {
+            self.record(&series, &values);
+        }
+    }
+
+    fn record(&mut self, series: &str, values: &[(&str, f64)]) {
+        let connection = match self.database.open() {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Could not record metrics of {}: {}", series, e);
+                return;
+            }
+        };
+        let now = now_millis();
+        if let Err(e) = MetricsRepository::insert(&connection, series, now, values) {
+            error!("Could not record metrics of {}: {}", series, e);
+        }
+        if now.saturating_sub(self.last_compaction) > COMPACTION_INTERVAL_MS {
+            self.last_compaction = now;
+            if let Err(e) = MetricsRepository::compact(&connection, now) {
+                error!("Could not compact metrics history: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::db_connection;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_instances_sampled(db_connection: Arc<RikDataBase>) {
+        let mut history = MetricsHistory::new(db_connection.clone());
+        history.record_instance("sampled-running", &InstanceStatus::Running);
+        history.record_instance("sampled-failed", &InstanceStatus::Failed);
+        history.record_instance("sampled-terminated", &InstanceStatus::Running);
+        history.record_instance("sampled-terminated", &InstanceStatus::Terminated);
+
+        // Transitions alone are not recorded, only samples are
+        let connection = db_connection.open().unwrap();
+        let query = |instance_id: &str| {
+            MetricsRepository::query(
+                &connection,
+                &instance_series(instance_id),
+                0,
+                u64::MAX / 2,
+                u64::MAX / 2,
+            )
+            .unwrap()
+        };
+        assert!(query("sampled-running").is_empty());
+
+        history.sample_instances(INSTANCE_SAMPLING_INTERVAL_MS);
+        history.sample_instances(INSTANCE_SAMPLING_INTERVAL_MS + 1);
+        let running = query("sampled-running");
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].values["running"], 1.0);
+        assert_eq!(query("sampled-failed")[0].values["failed"], 1.0);
+        assert!(query("sampled-terminated").is_empty());
+    }
+}
@@ -1,9 +1,10 @@
 use crate::api::RikError;
 
 use crate::core::instance::Instance;
+use crate::core::worker::Worker;
 use async_trait::async_trait;
 use backoff::ExponentialBackoff;
-use definition::workload::WorkloadDefinition;
+use definition::workload::{Protocol, Spec, WorkloadDefinition};
 use proto::common::{InstanceMetric, WorkerMetric};
 use std::future::Future;
 use std::net::SocketAddr;
@@ -11,12 +12,17 @@ use std::time::Duration;
 use tracing::{event, Level};
 
 pub mod core;
+mod events;
 pub mod instance;
 mod instance_repository;
 mod instance_service;
+pub mod metrics_history;
+pub mod worker;
 mod worker_repository;
 mod worker_service;
 
+pub(crate) use instance_service::scheduler_url;
+
 trait Listener {
     fn run_listen_thread(&mut self);
 }
@@ -33,13 +39,28 @@ trait InstanceService {
         instance: Instance,
         workload_def: WorkloadDefinition,
     ) -> Result<(), RikError>;
-    fn handle_instance_status_update(&mut self, instance_metric: InstanceMetric);
+    fn handle_instance_status_update(
+        &mut self,
+        instance_metric: InstanceMetric,
+        worker_id: Option<String>,
+    );
 }
 
 trait InstanceRepository {
     fn fetch_instance(&self, instance_id: String) -> Result<Instance, RikError>;
     fn register_instance(&self, instance: Instance) -> Result<(), RikError>;
     fn delete_instance(&self, instance: Instance) -> Result<(), RikError>;
+    /// Reserve the node port exposing an instance for `protocol`, the
+    /// requested one if any
+    fn reserve_port(
+        &self,
+        instance_id: &str,
+        requested: Option<u16>,
+        protocol: Protocol,
+    ) -> Result<u16, RikError>;
+    /// Replace the references of `spec` to secrets of `namespace` with
+    /// their values
+    fn resolve_secrets(&self, namespace: &str, spec: &mut Spec) -> Result<(), RikError>;
 }
 
 trait WorkerService {
@@ -49,11 +70,12 @@ trait WorkerService {
         address: SocketAddr,
         metric: WorkerMetric,
     ) -> Result<(), RikError>;
+    fn is_registered(&self, identifier: &str) -> bool;
 }
 
 trait WorkerRepository {
-    fn fetch_worker_address(&self, worker_id: String) -> Result<String, RikError>;
-    fn register_worker(&self, worker_id: String, address: String) -> Result<(), RikError>;
+    fn fetch_worker(&self, worker_id: String) -> Result<Worker, RikError>;
+    fn register_worker(&self, worker: Worker) -> Result<(), RikError>;
 }
 
 /// Create an exponential backoff function that retries a function until it succeeds or the timeout
@@ -0,0 +1,79 @@
+use definition::now_millis;
+use node_metrics::metrics::Metrics;
+use proto::common::{ResourceStatus, WorkerMetric};
+use serde::{Deserialize, Serialize};
+
+/// Workers send their metrics every 15 seconds, a worker which missed
+/// a few of them is considered gone.
+const WORKER_TIMEOUT_MS: u64 = 60_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerState {
+    Ready,
+    NotReady,
+}
+
+/// Last known state of a worker, as reported by the scheduler
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Worker {
+    pub id: String,
+    pub address: String,
+    pub state: WorkerState,
+    /// Unix timestamp in milliseconds of the last update received
+    pub last_seen: u64,
+    pub metrics: Option<Metrics>,
+}
+
+impl Worker {
+    pub fn new(id: String, address: String, metric: WorkerMetric) -> Self {
+        let state = match ResourceStatus::from_i32(metric.status) {
+            Some(ResourceStatus::Running) => WorkerState::Ready,
+            _ => WorkerState::NotReady,
+        };
+        Self {
+            id,
+            address,
+            state,
+            last_seen: now_millis(),
+            metrics: metric
+                .metrics
+                .and_then(|metrics| Metrics::try_from(metrics).ok()),
+        }
+    }
+
+    /// State of the worker at `now`, a worker which stopped reporting is not ready
+    pub fn state_at(&self, now: u64) -> WorkerState {
+        if now.saturating_sub(self.last_seen) > WORKER_TIMEOUT_MS {
+            return WorkerState::NotReady;
+        }
+        self.state
+    }
+
+    pub fn get_full_name(&self) -> String {
+        // "any" might correspond to the feature the worker can execute in the future
+        // (container riklet vs dummy riklet vs function riklet)
+        format!("/worker/any/{}", self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_state_at() {
+        let worker = Worker::new(
+            "worker".to_string(),
+            "127.0.0.1:4995".to_string(),
+            WorkerMetric {
+                status: ResourceStatus::Running.into(),
+                metrics: None,
+            },
+        );
+        assert_eq!(worker.state_at(worker.last_seen), WorkerState::Ready);
+        assert_eq!(
+            worker.state_at(worker.last_seen + WORKER_TIMEOUT_MS + 1),
+            WorkerState::NotReady
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::api::RikError;
+use crate::core::worker::Worker;
 use crate::core::WorkerRepository;
 use crate::database::{RikDataBase, RikRepository};
 use rusqlite::Connection;
@@ -24,26 +25,23 @@ impl WorkerRepositoryImpl {
 }
 
 impl WorkerRepository for WorkerRepositoryImpl {
-    fn fetch_worker_address(&self, worker_id: String) -> Result<String, RikError> {
+    fn fetch_worker(&self, worker_id: String) -> Result<Worker, RikError> {
         let conn = self.get_connection()?;
-        // "any" might correspond to the feature the worker can execute in the future
-        // (container riklet vs dummy riklet vs function riklet)
-        let element =
-            RikRepository::check_duplicate_name(&conn, &format!("/worker/any/{}", &worker_id))
-                .map_err(|_| RikError::InvalidName(worker_id))?;
+        let element = RikRepository::find_one(&conn, &worker_id, "/worker")
+            .map_err(|_| RikError::InvalidName(worker_id))?;
 
-        serde_json::from_value::<String>(element.value).map_err(|e| {
+        serde_json::from_value::<Worker>(element.value).map_err(|e| {
             RikError::InternalCommunicationError(format!("Could not parse worker: {}", e))
         })
     }
 
-    fn register_worker(&self, worker_id: String, address: String) -> Result<(), RikError> {
+    fn register_worker(&self, worker: Worker) -> Result<(), RikError> {
         let connection = self.get_connection()?;
         match RikRepository::upsert(
             &connection,
-            &worker_id,
-            &format!("/worker/any/{}", &worker_id),
-            &serde_json::to_string(&address).unwrap(),
+            &worker.id,
+            &worker.get_full_name(),
+            &serde_json::to_string(&worker).unwrap(),
             "/worker",
         ) {
             Ok(_) => Ok(()),
@@ -58,39 +56,52 @@ impl WorkerRepository for WorkerRepositoryImpl {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::worker::WorkerState;
     use crate::tests::fixtures::db_connection;
+    use proto::common::{ResourceStatus, WorkerMetric};
     use rstest::rstest;
 
+    fn worker(id: &str, address: &str) -> Worker {
+        Worker::new(
+            id.to_string(),
+            address.to_string(),
+            WorkerMetric {
+                status: ResourceStatus::Running.into(),
+                metrics: None,
+            },
+        )
+    }
+
     #[rstest]
-    fn test_fetch_worker_address_ok(db_connection: std::sync::Arc<RikDataBase>) {
+    fn test_fetch_worker_ok(db_connection: std::sync::Arc<RikDataBase>) {
         let connection = db_connection.open().unwrap();
         connection.execute("DELETE FROM cluster", []).unwrap();
         let worker_id = "test-worker";
         let address = "http://localhost:8080";
         let worker_repository = WorkerRepositoryImpl::new(db_connection);
         worker_repository
-            .register_worker(worker_id.to_string(), address.to_string())
+            .register_worker(worker(worker_id, address))
             .unwrap();
 
-        let fetched_address = worker_repository
-            .fetch_worker_address(worker_id.to_string())
+        let fetched = worker_repository
+            .fetch_worker(worker_id.to_string())
             .unwrap();
-        assert_eq!(fetched_address, address);
+        assert_eq!(fetched.address, address);
+        assert_eq!(fetched.state, WorkerState::Ready);
     }
 
     #[rstest]
-    fn test_fetch_worker_address_not_found(db_connection: std::sync::Arc<RikDataBase>) {
+    fn test_fetch_worker_not_found(db_connection: std::sync::Arc<RikDataBase>) {
         let worker_repository = WorkerRepositoryImpl::new(db_connection);
-        let result = worker_repository.fetch_worker_address("test-worker".to_string());
+        let result = worker_repository.fetch_worker("test-worker".to_string());
         assert!(result.is_err());
     }
 
     #[rstest]
     fn test_register_worker_ok(db_connection: std::sync::Arc<RikDataBase>) {
         let worker_repository = WorkerRepositoryImpl::new(db_connection);
-        let worker_id = "test-worker";
-        let address = "http://localhost:8080";
-        let result = worker_repository.register_worker(worker_id.to_string(), address.to_string());
+        let result =
+            worker_repository.register_worker(worker("test-worker", "http://localhost:8080"));
         assert!(result.is_ok());
     }
 
@@ -98,19 +109,18 @@ mod tests {
     fn test_update_worker_addr(db_connection: std::sync::Arc<RikDataBase>) {
         let worker_repository = WorkerRepositoryImpl::new(db_connection);
         let worker_id = "test-worker";
-        let address = "http://localhost:8080";
         worker_repository
-            .register_worker(worker_id.to_string(), address.to_string())
+            .register_worker(worker(worker_id, "http://localhost:8080"))
             .unwrap();
 
         let new_address = "http://localhost:8081";
         worker_repository
-            .register_worker(worker_id.to_string(), new_address.to_string())
+            .register_worker(worker(worker_id, new_address))
             .unwrap();
 
-        let fetched_address = worker_repository
-            .fetch_worker_address(worker_id.to_string())
+        let fetched = worker_repository
+            .fetch_worker(worker_id.to_string())
             .unwrap();
-        assert_eq!(fetched_address, new_address);
+        assert_eq!(fetched.address, new_address);
     }
 }
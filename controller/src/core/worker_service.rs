@@ -1,4 +1,5 @@
 use crate::api::RikError;
+use crate::core::worker::Worker;
 use crate::core::worker_repository::WorkerRepositoryImpl;
 use crate::core::{WorkerRepository, WorkerService};
 use proto::common::WorkerMetric;
@@ -19,9 +20,69 @@ impl WorkerService for WorkerServiceImpl {
         &mut self,
         identifier: String,
         address: SocketAddr,
-        _metric: WorkerMetric,
+        metric: WorkerMetric,
     ) -> Result<(), RikError> {
-        self.repository
-            .register_worker(identifier, address.to_string())
+        let mut worker = Worker::new(identifier, address.to_string(), metric);
+        // Status updates, e.g. when a worker registers again, carry no metrics
+        if worker.metrics.is_none() {
+            if let Ok(previous) = self.repository.fetch_worker(worker.id.clone()) {
+                worker.metrics = previous.metrics;
+            }
+        }
+        self.repository.register_worker(worker)
+    }
+
+    fn is_registered(&self, identifier: &str) -> bool {
+        self.repository.fetch_worker(identifier.to_string()).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::RikDataBase;
+    use crate::tests::fixtures::db_connection;
+    use proto::common::ResourceStatus;
+    use proto::metrics::{CpuMetrics, MemoryMetrics, NodeMetrics};
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    fn metric(metrics: Option<NodeMetrics>) -> WorkerMetric {
+        WorkerMetric {
+            status: ResourceStatus::Running.into(),
+            metrics,
+        }
+    }
+
+    #[rstest]
+    fn test_metrics_kept_on_status_update(db_connection: Arc<RikDataBase>) {
+        let mut service = WorkerServiceImpl::new(WorkerRepositoryImpl::new(db_connection.clone()));
+        let address: SocketAddr = "127.0.0.1:4995".parse().unwrap();
+        let reported = NodeMetrics {
+            cpu: Some(CpuMetrics {
+                total: 4,
+                free: 75.0,
+            }),
+            memory: Some(MemoryMetrics {
+                total: 2048,
+                free: 1024,
+            }),
+            disks: vec![],
+        };
+        service
+            .handle_metric_update(
+                "metrics-worker".to_string(),
+                address,
+                metric(Some(reported)),
+            )
+            .unwrap();
+        service
+            .handle_metric_update("metrics-worker".to_string(), address, metric(None))
+            .unwrap();
+
+        let worker = WorkerRepositoryImpl::new(db_connection)
+            .fetch_worker("metrics-worker".to_string())
+            .unwrap();
+        assert_eq!(worker.metrics.unwrap().memory.free, 1024);
     }
 }
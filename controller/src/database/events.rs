@@ -0,0 +1,190 @@
+//! Events reported about the objects of the cluster
+use super::{DatabaseError, Result};
+use crate::api::types::event::Event;
+use rusqlite::{params, Connection};
+
+/// Events are kept for an hour after their last occurrence
+pub const EVENTS_RETENTION_MS: u64 = 60 * 60 * 1000;
+
+/// Criteria the listed events must match, unset ones match any event
+#[derive(Default)]
+pub struct EventFilter<'a> {
+    pub object_kind: Option<&'a str>,
+    pub object_id: Option<&'a str>,
+    pub namespace: Option<&'a str>,
+}
+
+pub struct EventsRepository {}
+impl EventsRepository {
+    pub fn init_table(connection: &Connection) -> Result<()> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS events (
+                object_kind     TEXT NOT NULL,
+                object_id       TEXT NOT NULL,
+                namespace       TEXT NOT NULL,
+                reason          TEXT NOT NULL,
+                message         TEXT NOT NULL,
+                source          TEXT NOT NULL,
+                count           INTEGER NOT NULL,
+                first_timestamp INTEGER NOT NULL,
+                last_timestamp  INTEGER NOT NULL
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS events_occurrence_index
+                ON events (object_kind, object_id, reason, message, source);
+            CREATE INDEX IF NOT EXISTS events_last_timestamp_index ON events (last_timestamp);",
+            )
+            .map_err(DatabaseError::sql)
+    }
+
+    /// Store an event, or count one more occurrence when the same event was
+    /// already reported about this object
+    pub fn record(connection: &Connection, event: &Event) -> Result<()> {
+        connection
+            .execute(
+                "INSERT INTO events (object_kind, object_id, namespace, reason, message, source,
+                count, first_timestamp, last_timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                ON CONFLICT (object_kind, object_id, reason, message, source) DO UPDATE SET
+                count = count + excluded.count,
+                first_timestamp = MIN(first_timestamp, excluded.first_timestamp),
+                last_timestamp = MAX(last_timestamp, excluded.last_timestamp)",
+                params![
+                    event.object_kind,
+                    event.object_id,
+                    event.namespace,
+                    event.reason,
+                    event.message,
+                    event.source,
+                    event.count,
+                    event.first_timestamp as i64,
+                    event.last_timestamp as i64
+                ],
+            )
+            .map_err(DatabaseError::sql)?;
+        Ok(())
+    }
+
+    /// Events matching `filter`, the most recent first
+    pub fn list(connection: &Connection, filter: &EventFilter) -> Result<Vec<Event>> {
+        let mut stmt = connection
+            .prepare(
+                "SELECT object_kind, object_id, namespace, reason, message, source, count,
+                first_timestamp, last_timestamp FROM events
+                WHERE (?1 IS NULL OR object_kind = ?1) AND (?2 IS NULL OR object_id = ?2)
+                AND (?3 IS NULL OR namespace = ?3)
+                ORDER BY last_timestamp DESC",
+            )
+            .map_err(DatabaseError::sql)?;
+        let rows = stmt
+            .query_map(
+                params![filter.object_kind, filter.object_id, filter.namespace],
+                |row| {
+                    Ok(Event {
+                        object_kind: row.get(0)?,
+                        object_id: row.get(1)?,
+                        namespace: row.get(2)?,
+                        reason: row.get(3)?,
+                        message: row.get(4)?,
+                        source: row.get(5)?,
+                        count: row.get(6)?,
+                        first_timestamp: row.get::<_, i64>(7)? as u64,
+                        last_timestamp: row.get::<_, i64>(8)? as u64,
+                    })
+                },
+            )
+            .map_err(DatabaseError::sql)?;
+
+        let mut events: Vec<Event> = Vec::new();
+        for event in rows {
+            events.push(event.map_err(DatabaseError::sql)?);
+        }
+        Ok(events)
+    }
+
+    /// Drop the events which did not occur for [EVENTS_RETENTION_MS]
+    pub fn expire(connection: &Connection, now: u64) -> Result<()> {
+        let limit = now.saturating_sub(EVENTS_RETENTION_MS) as i64;
+        connection
+            .execute(
+                "DELETE FROM events WHERE last_timestamp < ?1",
+                params![limit],
+            )
+            .map_err(DatabaseError::sql)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::RikDataBase;
+    use crate::tests::fixtures::db_connection;
+    use rstest::rstest;
+
+    fn event(object_id: &str, reason: &str, timestamp: u64) -> Event {
+        Event {
+            object_kind: "instance".to_string(),
+            object_id: object_id.to_string(),
+            namespace: "default".to_string(),
+            reason: reason.to_string(),
+            message: "message".to_string(),
+            source: "riklet/node-1".to_string(),
+            count: 1,
+            first_timestamp: timestamp,
+            last_timestamp: timestamp,
+        }
+    }
+
+    #[rstest]
+    fn test_record_occurrences(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        EventsRepository::record(&connection, &event("i1", "Failed", 10)).unwrap();
+        EventsRepository::record(&connection, &event("i1", "Failed", 20)).unwrap();
+        EventsRepository::record(&connection, &event("i1", "Started", 30)).unwrap();
+
+        let events = EventsRepository::list(&connection, &EventFilter::default()).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].reason, "Started");
+        assert_eq!(events[1].count, 2);
+        assert_eq!(events[1].first_timestamp, 10);
+        assert_eq!(events[1].last_timestamp, 20);
+    }
+
+    #[rstest]
+    fn test_list_filtered(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        EventsRepository::record(&connection, &event("i1", "Started", 10)).unwrap();
+        EventsRepository::record(&connection, &event("i2", "Started", 10)).unwrap();
+
+        let filter = EventFilter {
+            object_kind: Some("instance"),
+            object_id: Some("i2"),
+            ..Default::default()
+        };
+        let events = EventsRepository::list(&connection, &filter).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].object_id, "i2");
+
+        let filter = EventFilter {
+            namespace: Some("other"),
+            ..Default::default()
+        };
+        assert!(EventsRepository::list(&connection, &filter)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[rstest]
+    fn test_expire(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        EventsRepository::record(&connection, &event("i1", "Started", 0)).unwrap();
+        EventsRepository::record(&connection, &event("i2", "Started", EVENTS_RETENTION_MS))
+            .unwrap();
+
+        EventsRepository::expire(&connection, EVENTS_RETENTION_MS + 1).unwrap();
+
+        let events = EventsRepository::list(&connection, &EventFilter::default()).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].object_id, "i2");
+    }
+}
@@ -0,0 +1,186 @@
+//! Time series of worker and instance metrics
+use super::{DatabaseError, Result};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Raw points are kept for a day
+pub const RAW_RETENTION_MS: u64 = 24 * 60 * 60 * 1000;
+/// Older points are averaged over this interval
+pub const DOWNSAMPLED_RESOLUTION_MS: u64 = 5 * 60 * 1000;
+/// Downsampled points are kept for a week
+pub const DOWNSAMPLED_RETENTION_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+/// Values of a series averaged over `[timestamp, timestamp + resolution)`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct MetricPoint {
+    pub timestamp: u64,
+    pub values: BTreeMap<String, f64>,
+}
+
+pub struct MetricsRepository {}
+impl MetricsRepository {
+    pub fn init_table(connection: &Connection) -> Result<()> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS metrics (
+                series          TEXT NOT NULL,
+                name            TEXT NOT NULL,
+                timestamp       INTEGER NOT NULL,
+                value           REAL NOT NULL,
+                downsampled     INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS metrics_series_timestamp_index ON metrics (series, timestamp);",
+            )
+            .map_err(DatabaseError::sql)
+    }
+
+    pub fn insert(
+        connection: &Connection,
+        series: &str,
+        timestamp: u64,
+        values: &[(&str, f64)],
+    ) -> Result<()> {
+        let mut stmt = connection
+            .prepare("INSERT INTO metrics (series, name, timestamp, value) VALUES (?1, ?2, ?3, ?4)")
+            .map_err(DatabaseError::sql)?;
+        for (name, value) in values {
+            stmt.execute(params![series, name, timestamp as i64, value])
+                .map_err(DatabaseError::sql)?;
+        }
+        Ok(())
+    }
+
+    /// Average raw points older than [RAW_RETENTION_MS] and drop the ones
+    /// older than [DOWNSAMPLED_RETENTION_MS], so the history stays bounded.
+    pub fn compact(connection: &Connection, now: u64) -> Result<()> {
+        let raw_limit = now.saturating_sub(RAW_RETENTION_MS) as i64;
+        let downsampled_limit = now.saturating_sub(DOWNSAMPLED_RETENTION_MS) as i64;
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(DatabaseError::sql)?;
+        transaction
+            .execute(
+                "INSERT INTO metrics (series, name, timestamp, value, downsampled)
+                SELECT series, name, timestamp / ?2 * ?2, AVG(value), 1 FROM metrics
+                WHERE downsampled = 0 AND timestamp < ?1
+                GROUP BY series, name, timestamp / ?2",
+                params![raw_limit, DOWNSAMPLED_RESOLUTION_MS as i64],
+            )
+            .map_err(DatabaseError::sql)?;
+        transaction
+            .execute(
+                "DELETE FROM metrics WHERE downsampled = 0 AND timestamp < ?1",
+                params![raw_limit],
+            )
+            .map_err(DatabaseError::sql)?;
+        transaction
+            .execute(
+                "DELETE FROM metrics WHERE timestamp < ?1",
+                params![downsampled_limit],
+            )
+            .map_err(DatabaseError::sql)?;
+        transaction.commit().map_err(DatabaseError::sql)
+    }
+
+    /// Points of `series` in `[from, to)`, averaged over `resolution` milliseconds
+    pub fn query(
+        connection: &Connection,
+        series: &str,
+        from: u64,
+        to: u64,
+        resolution: u64,
+    ) -> Result<Vec<MetricPoint>> {
+        let mut stmt = connection
+            .prepare(
+                "SELECT timestamp / ?4 * ?4 AS bucket, name, AVG(value) FROM metrics
+                WHERE series = ?1 AND timestamp >= ?2 AND timestamp < ?3
+                GROUP BY bucket, name ORDER BY bucket",
+            )
+            .map_err(DatabaseError::sql)?;
+        let rows = stmt
+            .query_map(
+                params![series, from as i64, to as i64, resolution.max(1) as i64],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, f64>(2)?,
+                    ))
+                },
+            )
+            .map_err(DatabaseError::sql)?;
+
+        let mut points: Vec<MetricPoint> = Vec::new();
+        for row in rows {
+            let (bucket, name, value) = row.map_err(DatabaseError::sql)?;
+            let timestamp = bucket as u64;
+            match points.last_mut() {
+                Some(point) if point.timestamp == timestamp => {
+                    point.values.insert(name, value);
+                }
+                _ => points.push(MetricPoint {
+                    timestamp,
+                    values: BTreeMap::from([(name, value)]),
+                }),
+            }
+        }
+        Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::RikDataBase;
+    use crate::tests::fixtures::db_connection;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_query_downsampled(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        for (timestamp, value) in [(0, 10.0), (15_000, 20.0), (60_000, 40.0)] {
+            MetricsRepository::insert(&connection, "worker/w1", timestamp, &[("cpu_free", value)])
+                .unwrap();
+        }
+        MetricsRepository::insert(&connection, "worker/w2", 0, &[("cpu_free", 99.0)]).unwrap();
+
+        let points =
+            MetricsRepository::query(&connection, "worker/w1", 0, 120_000, 60_000).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].timestamp, 0);
+        assert_eq!(points[0].values["cpu_free"], 15.0);
+        assert_eq!(points[1].values["cpu_free"], 40.0);
+    }
+
+    #[rstest]
+    fn test_compact(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        let now = DOWNSAMPLED_RETENTION_MS + RAW_RETENTION_MS;
+        // Too old to be kept at all
+        MetricsRepository::insert(&connection, "worker/w1", 0, &[("cpu_free", 1.0)]).unwrap();
+        // Old enough to be downsampled
+        for value in [10.0, 20.0] {
+            MetricsRepository::insert(
+                &connection,
+                "worker/w1",
+                RAW_RETENTION_MS,
+                &[("cpu_free", value)],
+            )
+            .unwrap();
+        }
+        // Recent
+        MetricsRepository::insert(&connection, "worker/w1", now, &[("cpu_free", 30.0)]).unwrap();
+
+        MetricsRepository::compact(&connection, now).unwrap();
+
+        let count: i64 = connection
+            .query_row("SELECT COUNT(*) FROM metrics", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+        let points = MetricsRepository::query(&connection, "worker/w1", 0, now + 1, 1).unwrap();
+        assert_eq!(points[0].timestamp, RAW_RETENTION_MS);
+        assert_eq!(points[0].values["cpu_free"], 15.0);
+        assert_eq!(points[1].values["cpu_free"], 30.0);
+    }
+}
@@ -1,4 +1,14 @@
 use crate::api::types::element::Element;
+use crate::metrics::DATABASE_ERRORS;
+
+mod events;
+mod metrics;
+mod ports;
+mod secrets;
+pub use events::{EventFilter, EventsRepository};
+pub use metrics::{MetricPoint, MetricsRepository};
+pub use ports::PortsRepository;
+pub use secrets::{SecretsKey, SecretsRepository};
 
 use dotenv::dotenv;
 use rusqlite::{params, Connection};
@@ -13,6 +23,35 @@ pub enum DatabaseError {
 
     #[error("Io error: {0}")]
     IoError(std::io::Error),
+
+    #[error("Crypto error: {0}")]
+    CryptoError(openssl::error::ErrorStack),
+
+    #[error("Invalid secret: {0}")]
+    InvalidSecret(String),
+}
+
+impl DatabaseError {
+    /// Errors are built through these so they are counted in the exposed metrics
+    fn sql(error: rusqlite::Error) -> Self {
+        DATABASE_ERRORS.inc();
+        DatabaseError::SqlError(error)
+    }
+
+    fn io(error: std::io::Error) -> Self {
+        DATABASE_ERRORS.inc();
+        DatabaseError::IoError(error)
+    }
+
+    fn crypto(error: openssl::error::ErrorStack) -> Self {
+        DATABASE_ERRORS.inc();
+        DatabaseError::CryptoError(error)
+    }
+
+    fn invalid_secret(message: String) -> Self {
+        DATABASE_ERRORS.inc();
+        DatabaseError::InvalidSecret(message)
+    }
 }
 
 type Result<T> = std::result::Result<T, DatabaseError>;
@@ -41,7 +80,11 @@ impl RikDataBase {
             CREATE INDEX IF NOT EXISTS cluster_name_index ON cluster (name);
             CREATE INDEX IF NOT EXISTS cluster_name_id_index ON cluster (name,id);",
             )
-            .map_err(DatabaseError::SqlError)?;
+            .map_err(DatabaseError::sql)?;
+        MetricsRepository::init_table(&connection)?;
+        EventsRepository::init_table(&connection)?;
+        PortsRepository::init_table(&connection)?;
+        SecretsRepository::init_table(&connection)?;
         Ok(())
     }
 
@@ -51,15 +94,22 @@ impl RikDataBase {
         dotenv().ok();
         let file_path =
             std::env::var("DATABASE_LOCATION").unwrap_or("/var/lib/rik/data/".to_string());
-        std::fs::create_dir_all(&file_path).map_err(DatabaseError::IoError)?;
+        std::fs::create_dir_all(&file_path).map_err(DatabaseError::io)?;
 
         let database_path = format!("{}{}.db", file_path, self.name);
-        Connection::open(database_path).map_err(DatabaseError::SqlError)
+        Connection::open(database_path).map_err(DatabaseError::sql)
     }
 }
 
 pub struct RikRepository {}
 impl RikRepository {
+    /// Make sure the database can be queried
+    pub fn check(connection: &Connection) -> Result<()> {
+        connection
+            .query_row("SELECT COUNT(*) FROM cluster", [], |_| Ok(()))
+            .map_err(DatabaseError::sql)
+    }
+
     pub fn insert(connection: &Connection, name: &str, value: &str) -> Result<String> {
         let id = Uuid::new_v4().to_string();
         connection
@@ -74,53 +124,45 @@ impl RikRepository {
     pub fn delete(connection: &Connection, id: &String) -> Result<()> {
         connection
             .execute("DELETE FROM cluster WHERE id = (?1)", params![id])
-            .map_err(DatabaseError::SqlError)?;
+            .map_err(DatabaseError::sql)?;
         Ok(())
     }
 
     pub fn find_one(connection: &Connection, id: &String, element_type: &str) -> Result<Element> {
         let mut stmt = connection
-            .prepare(&format!(
-                "SELECT id, name, value FROM cluster WHERE id = '{}' AND name LIKE '{}%'",
-                id, element_type
-            ))
-            .map_err(DatabaseError::SqlError)?;
-        stmt.query_row([], |row| {
+            .prepare("SELECT id, name, value FROM cluster WHERE id = ?1 AND name LIKE ?2")
+            .map_err(DatabaseError::sql)?;
+        stmt.query_row(params![id, format!("{}%", element_type)], |row| {
             Ok(Element::new(row.get(0)?, row.get(1)?, row.get(2)?))
         })
-        .map_err(DatabaseError::SqlError)
+        .map_err(DatabaseError::sql)
     }
 
-    pub fn check_duplicate_name(connection: &Connection, name: &str) -> Result<Element> {
+    /// Element stored under exactly `name`
+    pub fn find_by_name(connection: &Connection, name: &str) -> Result<Element> {
         let mut stmt = connection
-            .prepare(&format!(
-                "SELECT id, name, value FROM cluster WHERE name LIKE '{}%'",
-                name
-            ))
-            .map_err(DatabaseError::SqlError)?;
-        stmt.query_row([], |row| {
+            .prepare("SELECT id, name, value FROM cluster WHERE name = ?1")
+            .map_err(DatabaseError::sql)?;
+        stmt.query_row(params![name], |row| {
             Ok(Element::new(row.get(0)?, row.get(1)?, row.get(2)?))
         })
-        .map_err(DatabaseError::SqlError)
+        .map_err(DatabaseError::sql)
     }
 
     // TODO: add pagination
     pub fn find_all(connection: &Connection, element_type: &str) -> Result<Vec<Element>> {
         let mut stmt = connection
-            .prepare(&format!(
-                "SELECT id, name, value FROM cluster WHERE name LIKE '{}%'",
-                element_type
-            ))
+            .prepare("SELECT id, name, value FROM cluster WHERE name LIKE ?1")
             .unwrap();
         let elements_iter = stmt
-            .query_map([], |row| {
+            .query_map(params![format!("{}%", element_type)], |row| {
                 Ok(Element::new(row.get(0)?, row.get(1)?, row.get(2)?))
             })
             .unwrap();
 
         let mut elements: Vec<Element> = Vec::new();
         for element in elements_iter {
-            elements.push(element.map_err(DatabaseError::SqlError)?);
+            elements.push(element.map_err(DatabaseError::sql)?);
         }
         Ok(elements)
     }
@@ -131,7 +173,7 @@ impl RikRepository {
                 "UPDATE cluster SET value=(?1) WHERE id = (?2)",
                 params![value, id],
             )
-            .map_err(DatabaseError::SqlError)?;
+            .map_err(DatabaseError::sql)?;
         Ok(())
     }
 
@@ -151,7 +193,7 @@ impl RikRepository {
                     "INSERT INTO cluster (id, name, value) VALUES (?1, ?2, ?3)",
                     params![id, name, value],
                 )
-                .map_err(DatabaseError::SqlError)?;
+                .map_err(DatabaseError::sql)?;
             Ok(id.to_string())
         }
     }
@@ -166,6 +208,14 @@ mod test {
     use rstest::rstest;
     use uuid::Uuid;
 
+    #[rstest]
+    fn test_check(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        assert!(RikRepository::check(&connection).is_ok());
+        connection.execute("DROP TABLE cluster", []).unwrap();
+        assert!(RikRepository::check(&connection).is_err());
+    }
+
     #[rstest]
     fn test_insert_and_find_ok(db_connection: std::sync::Arc<RikDataBase>) {
         let connection = db_connection.open().unwrap();
@@ -215,25 +265,20 @@ mod test {
     }
 
     #[rstest]
-    fn test_check_duplicate_name(db_connection: std::sync::Arc<RikDataBase>) {
+    fn test_find_by_name(db_connection: std::sync::Arc<RikDataBase>) {
         let connection = db_connection.open().unwrap();
-        connection.execute("DELETE FROM cluster", []).unwrap();
-        let name = "/workload/pods/default/test-workload";
-        let value = "{\"data\": \"test\"}";
-        let inserted_id = match RikRepository::insert(&connection, name, value) {
-            Ok(v) => v,
-            Err(_) => panic!("Test failed on database insert"),
-        };
-        let duplicate = match RikRepository::check_duplicate_name(
-            &connection,
-            &format!("/workload/%/default/{}", "test-workload"),
-        ) {
-            Ok(v) => v,
-            Err(_) => panic!("Test failed on check duplicate"),
-        };
-        assert_eq!(duplicate.id, inserted_id);
-        assert_eq!(duplicate.name, name);
-        assert_eq!(duplicate.value, serde_json::json!({"data": "test"}));
+        let name = "/workload/pods/default/exact-workload";
+        RikRepository::insert(&connection, name, "{}").unwrap();
+
+        assert_eq!(
+            RikRepository::find_by_name(&connection, name).unwrap().name,
+            name
+        );
+        assert!(RikRepository::find_by_name(&connection, "/workload/pods/default/exact").is_err());
+        assert!(
+            RikRepository::find_by_name(&connection, "/workload/%/default/exact-workload").is_err()
+        );
+        assert!(RikRepository::find_by_name(&connection, "' OR '1'='1").is_err());
     }
 
     #[rstest]
@@ -0,0 +1,181 @@
+//! Node ports exposing the instances of the cluster
+use super::{DatabaseError, Result};
+use definition::workload::Protocol;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashSet;
+use std::ops::Range;
+
+pub struct PortsRepository {}
+impl PortsRepository {
+    pub fn init_table(connection: &Connection) -> Result<()> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS ports (
+                instance_id TEXT PRIMARY KEY,
+                port        INTEGER NOT NULL,
+                protocol    TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS ports_port_index ON ports (port, protocol);",
+            )
+            .map_err(DatabaseError::sql)
+    }
+
+    /// Port reserved by an instance, along with its protocol
+    pub fn get(connection: &Connection, instance_id: &str) -> Result<Option<(u16, String)>> {
+        connection
+            .query_row(
+                "SELECT port, protocol FROM ports WHERE instance_id = ?1",
+                params![instance_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(DatabaseError::sql)
+    }
+
+    /// Reserve a port for an instance, which keeps the one it already has.
+    ///
+    /// A port is taken once per `protocol`. A requested port is always
+    /// granted: replicas of a workload share it, and the scheduler places
+    /// instances using the same port on different workers. Otherwise the
+    /// first port of `range` nobody reserved for `protocol` is given, or
+    /// `None` when they are all taken.
+    pub fn reserve(
+        connection: &Connection,
+        instance_id: &str,
+        requested: Option<u16>,
+        protocol: Protocol,
+        range: Range<u16>,
+    ) -> Result<Option<u16>> {
+        let protocol = protocol.to_string();
+        if let Some((port, reserved_protocol)) = Self::get(connection, instance_id)? {
+            if requested.unwrap_or(port) == port && reserved_protocol == protocol {
+                return Ok(Some(port));
+            }
+        }
+
+        let port = match requested {
+            Some(port) => port,
+            None => {
+                let mut stmt = connection
+                    .prepare(
+                        "SELECT port FROM ports WHERE port >= ?1 AND port < ?2 AND protocol = ?3",
+                    )
+                    .map_err(DatabaseError::sql)?;
+                let rows = stmt
+                    .query_map(params![range.start, range.end, protocol], |row| {
+                        row.get::<_, u16>(0)
+                    })
+                    .map_err(DatabaseError::sql)?;
+                let mut used = HashSet::new();
+                for port in rows {
+                    used.insert(port.map_err(DatabaseError::sql)?);
+                }
+                match range.into_iter().find(|port| !used.contains(port)) {
+                    Some(port) => port,
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        connection
+            .execute(
+                "INSERT INTO ports (instance_id, port, protocol) VALUES (?1, ?2, ?3)
+                ON CONFLICT (instance_id) DO UPDATE
+                SET port = excluded.port, protocol = excluded.protocol",
+                params![instance_id, port, protocol],
+            )
+            .map_err(DatabaseError::sql)?;
+        Ok(Some(port))
+    }
+
+    /// Give back the port of an instance, so it can be reserved again
+    pub fn release(connection: &Connection, instance_id: &str) -> Result<()> {
+        connection
+            .execute(
+                "DELETE FROM ports WHERE instance_id = ?1",
+                params![instance_id],
+            )
+            .map_err(DatabaseError::sql)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::RikDataBase;
+    use crate::tests::fixtures::db_connection;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_reserve_distinct_ports(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+
+        let first =
+            PortsRepository::reserve(&connection, "i1", None, Protocol::Tcp, 100..103).unwrap();
+        let second =
+            PortsRepository::reserve(&connection, "i2", None, Protocol::Tcp, 100..103).unwrap();
+        assert_eq!(first, Some(100));
+        assert_eq!(second, Some(101));
+        // An instance keeps its port
+        assert_eq!(
+            PortsRepository::reserve(&connection, "i1", None, Protocol::Tcp, 100..103).unwrap(),
+            Some(100)
+        );
+    }
+
+    #[rstest]
+    fn test_reserve_requested_port(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+
+        let requested =
+            PortsRepository::reserve(&connection, "i1", Some(100), Protocol::Tcp, 100..102)
+                .unwrap();
+        let replica =
+            PortsRepository::reserve(&connection, "i2", Some(100), Protocol::Tcp, 100..102)
+                .unwrap();
+        assert_eq!(requested, Some(100));
+        assert_eq!(replica, Some(100));
+        // Requested ports are not given to other instances
+        assert_eq!(
+            PortsRepository::reserve(&connection, "i3", None, Protocol::Tcp, 100..102).unwrap(),
+            Some(101)
+        );
+    }
+
+    #[rstest]
+    fn test_reserve_per_protocol(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+
+        let tcp = PortsRepository::reserve(&connection, "i1", None, Protocol::Tcp, 100..102);
+        let udp = PortsRepository::reserve(&connection, "i2", None, Protocol::Udp, 100..102);
+        assert_eq!(tcp.unwrap(), Some(100));
+        assert_eq!(udp.unwrap(), Some(100));
+        assert_eq!(
+            PortsRepository::get(&connection, "i2").unwrap(),
+            Some((100, "UDP".to_string()))
+        );
+        assert_eq!(
+            PortsRepository::reserve(&connection, "i3", None, Protocol::Tcp, 100..102).unwrap(),
+            Some(101)
+        );
+    }
+
+    #[rstest]
+    fn test_exhausted_and_released(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+
+        PortsRepository::reserve(&connection, "i1", None, Protocol::Tcp, 100..101).unwrap();
+        assert_eq!(
+            PortsRepository::reserve(&connection, "i2", None, Protocol::Tcp, 100..101).unwrap(),
+            None
+        );
+
+        PortsRepository::release(&connection, "i1").unwrap();
+        assert_eq!(PortsRepository::get(&connection, "i1").unwrap(), None);
+        assert_eq!(
+            PortsRepository::reserve(&connection, "i2", None, Protocol::Tcp, 100..101).unwrap(),
+            Some(100)
+        );
+    }
+}
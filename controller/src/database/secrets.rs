@@ -0,0 +1,299 @@
+//! Secrets of the namespaces, whose values are encrypted at rest with
+//! AES-256-GCM
+use super::{DatabaseError, Result};
+use crate::api::types::secret::{Secret, SecretMetadata};
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use uuid::Uuid;
+
+const DEFAULT_KEY_LOCATION: &str = "/var/lib/rik/secrets.key";
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const TAG_LENGTH: usize = 16;
+
+/// Key the values of the secrets are encrypted with
+pub struct SecretsKey([u8; KEY_LENGTH]);
+
+impl SecretsKey {
+    /// Key in the file at `SECRETS_KEY_LOCATION`, generated when there is none
+    pub fn load() -> Result<Self> {
+        let location = std::env::var("SECRETS_KEY_LOCATION")
+            .unwrap_or_else(|_| DEFAULT_KEY_LOCATION.to_string());
+        Self::load_from(Path::new(&location))
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        match std::fs::read(path) {
+            Ok(key) => key.try_into().map(SecretsKey).map_err(|_| {
+                DatabaseError::invalid_secret(format!(
+                    "key {} is not {} bytes long",
+                    path.display(),
+                    KEY_LENGTH
+                ))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::generate(path),
+            Err(e) => Err(DatabaseError::io(e)),
+        }
+    }
+
+    /// Write a new key at `path`, only readable by its owner. It is written
+    /// aside then linked, so it is never read half written, and the key of
+    /// whoever linked it first is kept.
+    fn generate(path: &Path) -> Result<Self> {
+        let mut key = [0; KEY_LENGTH];
+        rand_bytes(&mut key).map_err(DatabaseError::crypto)?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(DatabaseError::io)?;
+        }
+
+        let temporary = path.with_extension(Uuid::new_v4().to_string());
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&temporary)
+            .and_then(|mut file| file.write_all(&key))
+            .map_err(DatabaseError::io)?;
+        let linked = std::fs::hard_link(&temporary, path);
+        std::fs::remove_file(&temporary).map_err(DatabaseError::io)?;
+        match linked {
+            Ok(()) => Ok(SecretsKey(key)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Self::load_from(path),
+            Err(e) => Err(DatabaseError::io(e)),
+        }
+    }
+
+    /// Nonce, tag and ciphertext of `data`, which can only be decrypted
+    /// along with the same `aad`
+    fn encrypt(&self, aad: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0; NONCE_LENGTH];
+        rand_bytes(&mut nonce).map_err(DatabaseError::crypto)?;
+        let mut tag = [0; TAG_LENGTH];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(&nonce),
+            aad,
+            data,
+            &mut tag,
+        )
+        .map_err(DatabaseError::crypto)?;
+        Ok([&nonce[..], &tag, &ciphertext].concat())
+    }
+
+    fn decrypt(&self, aad: &[u8], encrypted: &[u8]) -> Result<Vec<u8>> {
+        if encrypted.len() < NONCE_LENGTH + TAG_LENGTH {
+            return Err(DatabaseError::invalid_secret("truncated value".to_string()));
+        }
+        let (nonce, encrypted) = encrypted.split_at(NONCE_LENGTH);
+        let (tag, ciphertext) = encrypted.split_at(TAG_LENGTH);
+        decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(nonce),
+            aad,
+            ciphertext,
+            tag,
+        )
+        .map_err(DatabaseError::crypto)
+    }
+}
+
+/// Values are bound to the secret they belong to, so they can't be moved
+/// to another one
+fn additional_data(namespace: &str, name: &str) -> Vec<u8> {
+    format!("{}/{}", namespace, name).into_bytes()
+}
+
+pub struct SecretsRepository {}
+impl SecretsRepository {
+    pub fn init_table(connection: &Connection) -> Result<()> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS secrets (
+                namespace   TEXT NOT NULL,
+                name        TEXT NOT NULL,
+                keys        TEXT NOT NULL,
+                data        BLOB NOT NULL,
+                PRIMARY KEY (namespace, name)
+            );",
+            )
+            .map_err(DatabaseError::sql)
+    }
+
+    /// Store a secret, unless its namespace already has one of this name.
+    /// Its keys are kept in clear so secrets can be listed.
+    pub fn create(connection: &Connection, key: &SecretsKey, secret: &Secret) -> Result<bool> {
+        let keys = serde_json::to_string(&secret.data.keys().collect::<Vec<_>>())
+            .map_err(|e| DatabaseError::invalid_secret(e.to_string()))?;
+        let data = serde_json::to_vec(&secret.data)
+            .map_err(|e| DatabaseError::invalid_secret(e.to_string()))?;
+        let data = key.encrypt(&additional_data(&secret.namespace, &secret.name), &data)?;
+        let inserted = connection
+            .execute(
+                "INSERT INTO secrets (namespace, name, keys, data) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (namespace, name) DO NOTHING",
+                params![secret.namespace, secret.name, keys, data],
+            )
+            .map_err(DatabaseError::sql)?;
+        Ok(inserted == 1)
+    }
+
+    /// Secrets of `namespace`, or of all of them, without their values
+    pub fn list(connection: &Connection, namespace: Option<&str>) -> Result<Vec<SecretMetadata>> {
+        let mut stmt = connection
+            .prepare(
+                "SELECT namespace, name, keys FROM secrets WHERE (?1 IS NULL OR namespace = ?1)
+                ORDER BY namespace, name",
+            )
+            .map_err(DatabaseError::sql)?;
+        let rows = stmt
+            .query_map(params![namespace], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?))
+            })
+            .map_err(DatabaseError::sql)?;
+
+        let mut secrets = Vec::new();
+        for row in rows {
+            let (namespace, name, keys) = row.map_err(DatabaseError::sql)?;
+            secrets.push(SecretMetadata {
+                namespace,
+                name,
+                keys: serde_json::from_str(&keys)
+                    .map_err(|e| DatabaseError::invalid_secret(e.to_string()))?,
+            });
+        }
+        Ok(secrets)
+    }
+
+    /// Values of the secret `name` of `namespace` by key
+    pub fn get(
+        connection: &Connection,
+        key: &SecretsKey,
+        namespace: &str,
+        name: &str,
+    ) -> Result<Option<BTreeMap<String, String>>> {
+        let data: Option<Vec<u8>> = connection
+            .query_row(
+                "SELECT data FROM secrets WHERE namespace = ?1 AND name = ?2",
+                params![namespace, name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(DatabaseError::sql)?;
+        match data {
+            Some(data) => {
+                let data = key.decrypt(&additional_data(namespace, name), &data)?;
+                serde_json::from_slice(&data)
+                    .map(Some)
+                    .map_err(|e| DatabaseError::invalid_secret(e.to_string()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Delete a secret, returns whether it existed
+    pub fn delete(connection: &Connection, namespace: &str, name: &str) -> Result<bool> {
+        let deleted = connection
+            .execute(
+                "DELETE FROM secrets WHERE namespace = ?1 AND name = ?2",
+                params![namespace, name],
+            )
+            .map_err(DatabaseError::sql)?;
+        Ok(deleted == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::RikDataBase;
+    use crate::tests::fixtures::db_connection;
+    use rstest::rstest;
+
+    fn secret(namespace: &str, name: &str) -> Secret {
+        Secret {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            data: [
+                ("password".to_string(), "hunter2".to_string()),
+                ("user".to_string(), "admin".to_string()),
+            ]
+            .into(),
+        }
+    }
+
+    #[rstest]
+    fn test_create_get_delete(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        let key = SecretsKey::load().unwrap();
+
+        assert!(SecretsRepository::create(&connection, &key, &secret("default", "db")).unwrap());
+        assert!(!SecretsRepository::create(&connection, &key, &secret("default", "db")).unwrap());
+        assert!(SecretsRepository::create(&connection, &key, &secret("other", "db")).unwrap());
+
+        assert_eq!(
+            SecretsRepository::get(&connection, &key, "default", "db").unwrap(),
+            Some(secret("default", "db").data)
+        );
+        assert_eq!(
+            SecretsRepository::get(&connection, &key, "default", "missing").unwrap(),
+            None
+        );
+        assert_eq!(
+            SecretsRepository::list(&connection, Some("other")).unwrap(),
+            vec![SecretMetadata {
+                namespace: "other".to_string(),
+                name: "db".to_string(),
+                keys: vec!["password".to_string(), "user".to_string()],
+            }]
+        );
+        assert_eq!(SecretsRepository::list(&connection, None).unwrap().len(), 2);
+
+        assert!(SecretsRepository::delete(&connection, "default", "db").unwrap());
+        assert!(!SecretsRepository::delete(&connection, "default", "db").unwrap());
+        assert_eq!(SecretsRepository::list(&connection, None).unwrap().len(), 1);
+    }
+
+    #[rstest]
+    fn test_values_encrypted(db_connection: std::sync::Arc<RikDataBase>) {
+        let connection = db_connection.open().unwrap();
+        let key = SecretsKey::load().unwrap();
+        SecretsRepository::create(&connection, &key, &secret("default", "db")).unwrap();
+
+        let data: Vec<u8> = connection
+            .query_row("SELECT data FROM secrets", [], |row| row.get(0))
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&data).contains("hunter2"));
+
+        // Values can't be read with another key, or moved to another secret
+        let other_key = SecretsKey([0; KEY_LENGTH]);
+        assert!(SecretsRepository::get(&connection, &other_key, "default", "db").is_err());
+        connection
+            .execute("UPDATE secrets SET name = 'stolen'", [])
+            .unwrap();
+        assert!(SecretsRepository::get(&connection, &key, "default", "stolen").is_err());
+    }
+
+    #[test]
+    fn test_key_generated_once() {
+        let path = std::env::temp_dir()
+            .join(format!("rik-secrets-{}", Uuid::new_v4()))
+            .join("secrets.key");
+
+        let key = SecretsKey::load_from(&path).unwrap();
+        assert_eq!(SecretsKey::load_from(&path).unwrap().0, key.0);
+        let mode = std::fs::metadata(&path).unwrap().permissions();
+        assert_eq!(
+            std::os::unix::fs::PermissionsExt::mode(&mode) & 0o777,
+            0o600
+        );
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
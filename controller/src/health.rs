@@ -0,0 +1,18 @@
+//! Liveness and readiness of the controller, served on `/healthz` and `/readyz`
+use probes::health::{report, Check};
+
+/// Thread mediating events between the API, the database and the scheduler
+pub static CORE: Check = Check::new("core");
+/// Stream of status updates coming from the scheduler
+pub static SCHEDULER: Check = Check::new("scheduler");
+
+/// Whether the controller is able to make progress at all
+pub fn liveness() -> (bool, String) {
+    report(&[CORE.status()])
+}
+
+/// Whether the controller is able to serve requests, given the database
+/// is reachable
+pub fn readiness(database: bool) -> (bool, String) {
+    report(&[CORE.status(), ("database", database), SCHEDULER.status()])
+}
@@ -1,6 +1,8 @@
 mod api;
 mod core;
 mod database;
+mod health;
+mod metrics;
 mod tests;
 
 use std::sync::mpsc::channel;
@@ -8,28 +10,14 @@ use std::thread;
 
 use crate::{api::RikError, database::RikDataBase};
 use api::{external, ApiChannel};
-use tracing::{error, event, metadata::LevelFilter, Level};
-use tracing_subscriber::{
-    fmt, prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt, EnvFilter,
-};
+use tracing::{error, event, Level};
 
 use crate::core::core::Core;
 use tokio::runtime::Builder;
 
-fn logger_setup() {
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(
-            EnvFilter::builder()
-                .with_default_directive(LevelFilter::INFO.into())
-                .from_env_lossy(),
-        )
-        .init();
-}
-
 #[tokio::main]
 async fn main() {
-    logger_setup();
+    let _telemetry = telemetry::init("rik-controller").expect("Failed to set up telemetry");
     event!(Level::INFO, "Starting Rik");
     let db = RikDataBase::new(String::from("rik"));
     if let Err(e) = db.init_tables() {
@@ -45,6 +33,7 @@ async fn main() {
     let mut threads = Vec::new();
 
     threads.push(thread::spawn(move || -> Result<(), RikError> {
+        let _alive = health::CORE.guard();
         let future = async move { internal_api.listen_notification(legacy_receiver).await };
         Builder::new_multi_thread()
             .enable_all()
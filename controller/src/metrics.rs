@@ -0,0 +1,58 @@
+//! Operational metrics of the controller, exposed in Prometheus format on `/metrics`
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec, HistogramVec,
+    IntCounter, IntCounterVec, TextEncoder,
+};
+
+pub static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "rik_controller_http_requests_total",
+        "Requests handled by the API",
+        &["method", "route", "status"]
+    )
+    .unwrap()
+});
+
+pub static HTTP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "rik_controller_http_request_duration_seconds",
+        "Time spent handling requests of the API",
+        &["method", "route"]
+    )
+    .unwrap()
+});
+
+pub static DATABASE_ERRORS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "rik_controller_database_errors_total",
+        "Errors returned by the database"
+    )
+    .unwrap()
+});
+
+/// Encode every registered metric in Prometheus text format
+pub fn render() -> String {
+    // Metrics are registered lazily, make sure they show up before being used
+    Lazy::force(&HTTP_REQUESTS);
+    Lazy::force(&HTTP_REQUEST_DURATION);
+    Lazy::force(&DATABASE_ERRORS);
+    TextEncoder::new()
+        .encode_to_string(&prometheus::gather())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_registered_metrics() {
+        HTTP_REQUESTS
+            .with_label_values(&["GET", "/api/v0/workloads.list", "200"])
+            .inc();
+        let output = render();
+        assert!(output.contains("rik_controller_http_requests_total{method=\"GET\",route=\"/api/v0/workloads.list\",status=\"200\"}"));
+        assert!(output.contains("rik_controller_database_errors_total"));
+    }
+}
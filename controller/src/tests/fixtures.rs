@@ -9,6 +9,7 @@ use std::sync::mpsc::{Receiver, Sender};
 pub fn db_connection() -> std::sync::Arc<RikDataBase> {
     let mut generator = Generator::default();
     std::env::set_var("DATABASE_LOCATION", "/tmp/riktest");
+    std::env::set_var("SECRETS_KEY_LOCATION", "/tmp/riktest/secrets.key");
     let db = RikDataBase::new(generator.next().unwrap());
     db.init_tables().unwrap();
     db
@@ -1,34 +1,273 @@
 use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
 pub mod workload {
     use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashSet};
     use std::fmt::Display;
+    use std::str::FromStr;
     use tracing::error;
 
     const DEFAULT_FUNCTION_RUNTIME_PORT: u16 = 8080;
+    /// Ports of the node the container ports of type NodePort are exposed on
+    pub const NODE_PORT_RANGE: std::ops::Range<u16> = 30000..32768;
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
     pub struct EnvConfig {
         pub name: String,
+        #[serde(default)]
         pub value: String,
+        /// Where the value is read from when the instance is scheduled,
+        /// instead of `value`
+        #[serde(default, rename = "valueFrom", skip_serializing_if = "Option::is_none")]
+        pub value_from: Option<EnvSource>,
+    }
+
+    impl EnvConfig {
+        /// Value the variable is given, the one read from its source when it
+        /// has one
+        pub fn resolved_value(&self) -> &str {
+            match &self.value_from {
+                Some(source) => &source.value.0,
+                None => &self.value,
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct EnvSource {
+        #[serde(rename = "secretKeyRef")]
+        pub secret_key_ref: SecretKeyRef,
+        /// Value read from the secret when the instance is scheduled
+        #[serde(skip)]
+        pub value: SecretValue,
+    }
+
+    /// Value of a secret. It is only known once the instance is scheduled,
+    /// and never serialized or printed.
+    #[derive(Clone, Default, PartialEq, Eq)]
+    pub struct SecretValue(pub String);
+
+    impl std::fmt::Debug for SecretValue {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("<redacted>")
+        }
+    }
+
+    /// Key of a secret of the namespace of the workload
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct SecretKeyRef {
+        /// Name of the secret
+        pub name: String,
+        pub key: String,
+    }
+
+    /// Values of a secret by key. They are only known once the instance is
+    /// scheduled, and never serialized or printed.
+    #[derive(Clone, Default, PartialEq, Eq)]
+    pub struct SecretItems(pub BTreeMap<String, String>);
+
+    impl std::fmt::Debug for SecretItems {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_set().entries(self.0.keys()).finish()
+        }
+    }
+
+    /// Transport protocol of an exposed port
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    #[serde(rename_all = "UPPERCASE")]
+    pub enum Protocol {
+        #[default]
+        Tcp,
+        Udp,
+        Sctp,
+    }
+
+    impl Protocol {
+        /// Name of the protocol as iptables knows it
+        pub fn iptables_name(&self) -> &'static str {
+            match self {
+                Protocol::Tcp => "tcp",
+                Protocol::Udp => "udp",
+                Protocol::Sctp => "sctp",
+            }
+        }
+    }
+
+    impl Display for Protocol {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Protocol::Tcp => write!(f, "TCP"),
+                Protocol::Udp => write!(f, "UDP"),
+                Protocol::Sctp => write!(f, "SCTP"),
+            }
+        }
+    }
+
+    impl FromStr for Protocol {
+        type Err = String;
+
+        fn from_str(value: &str) -> Result<Self, Self::Err> {
+            match value.to_ascii_uppercase().as_str() {
+                "TCP" => Ok(Protocol::Tcp),
+                "UDP" => Ok(Protocol::Udp),
+                "SCTP" => Ok(Protocol::Sctp),
+                _ => Err(format!(
+                    "unknown protocol {}, expected TCP, UDP or SCTP",
+                    value
+                )),
+            }
+        }
+    }
+
+    /// Port of a node a workload is reachable on
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct HostPort {
+        pub port: u16,
+        pub protocol: Protocol,
+    }
+
+    impl Display for HostPort {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}/{}", self.port, self.protocol)
+        }
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
     pub struct PortConfig {
         pub port: u16,
         pub target_port: u16,
-        pub protocol: Option<String>,
+        /// TCP when not given
+        #[serde(default)]
+        pub protocol: Option<Protocol>,
         pub r#type: String,
     }
 
+    impl PortConfig {
+        /// Whether the port is exposed on the node running the container, on
+        /// `port`, or on a port chosen by the node when it is 0
+        pub fn is_node_port(&self) -> bool {
+            self.r#type.eq_ignore_ascii_case("NodePort")
+        }
+
+        pub fn protocol(&self) -> Protocol {
+            self.protocol.unwrap_or_default()
+        }
+    }
+
+    /// User the processes of a container run as
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ContainerUser {
+        pub uid: u32,
+        /// Group of the image when not given
+        pub gid: Option<u32>,
+    }
+
+    /// Where the files of a volume are
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub enum VolumeSource {
+        /// Empty directory created along with the instance and removed with it
+        #[serde(rename = "emptyDir")]
+        EmptyDir {},
+        /// Directory or file of the node
+        #[serde(rename = "hostPath")]
+        HostPath { path: String },
+        /// Files named after the keys of a secret of the namespace of the
+        /// workload, holding its values
+        #[serde(rename = "secret")]
+        Secret {
+            #[serde(rename = "secretName")]
+            secret_name: String,
+            #[serde(skip)]
+            items: SecretItems,
+        },
+    }
+
+    /// Files the containers of a pod can mount, and share
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct Volume {
+        pub name: String,
+        #[serde(flatten)]
+        pub source: VolumeSource,
+    }
+
+    /// Whether `name` is a DNS label: at most 63 lowercase alphanumeric
+    /// characters or '-', starting and ending with an alphanumeric one. The
+    /// names of the instances, of their containers and of their volumes name
+    /// files and directories on the nodes, so they must be ones.
+    pub fn is_dns_label(name: &str) -> bool {
+        let alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+        !name.is_empty()
+            && name.len() <= 63
+            && name.chars().all(|c| alphanumeric(c) || c == '-')
+            && name.starts_with(alphanumeric)
+            && name.ends_with(alphanumeric)
+    }
+
+    /// Check the volumes of a pod have valid and distinct names
+    pub fn validate_volumes(volumes: &[Volume]) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for volume in volumes {
+            if !is_dns_label(&volume.name) {
+                return Err(format!(
+                    "Invalid volume name {}, only lowercase alphanumeric characters and '-' are allowed",
+                    volume.name
+                ));
+            }
+            if !names.insert(volume.name.as_str()) {
+                return Err(format!("Volume {} is defined more than once", volume.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the containers of a pod have valid and distinct names
+    pub fn validate_container_names<'a>(
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        for name in names {
+            if !is_dns_label(name) {
+                return Err(format!(
+                    "Invalid container name {}, only lowercase alphanumeric characters and '-' are allowed",
+                    name
+                ));
+            }
+            if !seen.insert(name) {
+                return Err(format!("Container {} is defined more than once", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Volume of the pod mounted in a container
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct VolumeMount {
+        /// Name of the volume
+        pub name: String,
+        #[serde(rename = "mountPath")]
+        pub mount_path: String,
+        #[serde(default, rename = "readOnly")]
+        pub read_only: bool,
+    }
+
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
     pub struct Container {
         pub name: String,
         pub image: String,
         pub env: Option<Vec<EnvConfig>>,
         pub ports: Option<PortConfig>,
+        /// Replaces the entrypoint and the command of the image
+        pub command: Option<Vec<String>>,
+        /// Replaces the command of the image, given to the entrypoint
+        pub args: Option<Vec<String>>,
+        #[serde(rename = "workingDir")]
+        pub working_dir: Option<String>,
+        pub user: Option<ContainerUser>,
+        #[serde(default, rename = "volumeMounts")]
+        pub volume_mounts: Vec<VolumeMount>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -52,6 +291,8 @@ pub mod workload {
         pub target_port: u16,
         #[serde(rename = "type")]
         pub port_type: NetworkPortExposureType,
+        #[serde(default)]
+        pub protocol: Protocol,
     }
 
     impl FunctionPort {
@@ -62,6 +303,7 @@ pub mod workload {
                 port,
                 target_port: DEFAULT_FUNCTION_RUNTIME_PORT,
                 port_type: NetworkPortExposureType::NodePort,
+                protocol: Protocol::Tcp,
             }
         }
     }
@@ -78,6 +320,40 @@ pub mod workload {
         pub containers: Vec<Container>,
         #[serde(default)]
         pub function: Option<Function>,
+        /// Seconds the containers are given to stop once asked to, before
+        /// being killed
+        #[serde(default, rename = "terminationGracePeriodSeconds")]
+        pub termination_grace_period_seconds: Option<u64>,
+        #[serde(default, rename = "restartPolicy")]
+        pub restart_policy: RestartPolicy,
+        /// Whether the containers of a pod see each other's processes, they
+        /// always share their network and IPC namespaces
+        #[serde(default, rename = "shareProcessNamespace")]
+        pub share_process_namespace: bool,
+        #[serde(default)]
+        pub volumes: Vec<Volume>,
+    }
+
+    /// When the containers of a pod are restarted once they exited
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum RestartPolicy {
+        #[default]
+        Always,
+        /// Only when they exited with an error
+        OnFailure,
+        Never,
+    }
+
+    impl RestartPolicy {
+        /// Whether a container which exited with `code` is restarted, an
+        /// unknown code counting as an error
+        pub fn allows_restart(&self, code: Option<i32>) -> bool {
+            match self {
+                RestartPolicy::Always => true,
+                RestartPolicy::OnFailure => code != Some(0),
+                RestartPolicy::Never => false,
+            }
+        }
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -123,6 +399,78 @@ pub mod workload {
             self.kind == WorkloadKind::Function
         }
 
+        /// Check the workload can be run, telling what is wrong otherwise
+        pub fn validate(&self) -> Result<(), String> {
+            let node_ports = self
+                .spec
+                .containers
+                .iter()
+                .filter_map(|container| container.ports.as_ref())
+                .filter(|ports| ports.is_node_port() && ports.port != 0);
+            for ports in node_ports {
+                if !NODE_PORT_RANGE.contains(&ports.port) {
+                    return Err(format!(
+                        "Node port {} is out of the range {}-{}",
+                        ports.port,
+                        NODE_PORT_RANGE.start,
+                        NODE_PORT_RANGE.end - 1
+                    ));
+                }
+            }
+            validate_container_names(
+                self.spec
+                    .containers
+                    .iter()
+                    .map(|container| container.name.as_str()),
+            )?;
+            validate_volumes(&self.spec.volumes)
+        }
+
+        /// Port of the node the function is exposed on, when one was given
+        pub fn function_port(&self) -> Option<u16> {
+            self.spec
+                .function
+                .as_ref()
+                .and_then(|function| function.exposure.as_ref())
+                .map(|exposure| exposure.port)
+        }
+
+        /// Ports of the node the workload is reachable on, except the ones
+        /// left for the node to choose
+        pub fn host_ports(&self) -> Vec<HostPort> {
+            let function_port = self
+                .spec
+                .function
+                .as_ref()
+                .and_then(|function| function.exposure.as_ref())
+                .map(|exposure| HostPort {
+                    port: exposure.port,
+                    protocol: exposure.protocol,
+                });
+            let container_ports = self
+                .spec
+                .containers
+                .iter()
+                .filter_map(|container| container.ports.as_ref())
+                .filter(|ports| ports.is_node_port() && ports.port != 0)
+                .map(|ports| HostPort {
+                    port: ports.port,
+                    protocol: ports.protocol(),
+                });
+            function_port.into_iter().chain(container_ports).collect()
+        }
+
+        /// Protocol the function is exposed with, TCP unless its exposure
+        /// tells otherwise
+        pub fn function_protocol(&self) -> Protocol {
+            self.spec
+                .function
+                .as_ref()
+                .and_then(|function| function.exposure.as_ref())
+                .map(|exposure| exposure.protocol)
+                .unwrap_or_default()
+        }
+
         pub fn set_function_port(&mut self, port: u16) {
             if !self.is_function() {
                 error!("Cannot set function port on non-function workload");
@@ -142,6 +490,7 @@ pub enum InstanceStatus {
     Terminated,
     Creating,
     Destroying,
+    Succeeded,
 }
 
 impl Display for InstanceStatus {
@@ -153,6 +502,7 @@ impl Display for InstanceStatus {
             InstanceStatus::Terminated => write!(f, "Terminated"),
             InstanceStatus::Creating => write!(f, "Creating"),
             InstanceStatus::Destroying => write!(f, "Destroying"),
+            InstanceStatus::Succeeded => write!(f, "Succeeded"),
         }
     }
 }
@@ -166,6 +516,7 @@ impl From<InstanceStatus> for i32 {
             InstanceStatus::Terminated => 4,
             InstanceStatus::Creating => 5,
             InstanceStatus::Destroying => 6,
+            InstanceStatus::Succeeded => 7,
         }
     }
 }
@@ -178,7 +529,17 @@ impl From<i32> for InstanceStatus {
             4 => InstanceStatus::Terminated,
             5 => InstanceStatus::Creating,
             6 => InstanceStatus::Destroying,
+            7 => InstanceStatus::Succeeded,
             _ => InstanceStatus::Pending,
         }
     }
 }
+
+/// Current Unix timestamp in milliseconds, as carried by the messages and
+/// stored along the objects of the cluster
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
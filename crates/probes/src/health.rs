@@ -0,0 +1,93 @@
+//! Checks flagging the components of a daemon up while they are running,
+//! reported on `/healthz` and `/readyz`
+use serde_json::{json, Map, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A component of the daemon, flagged up while it is running
+pub struct Check {
+    name: &'static str,
+    up: AtomicBool,
+}
+
+impl Check {
+    pub const fn new(name: &'static str) -> Check {
+        Check {
+            name,
+            up: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set(&self, up: bool) {
+        self.up.store(up, Ordering::SeqCst);
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.up.load(Ordering::SeqCst)
+    }
+
+    /// Flag the component as up until the guard is dropped, which also
+    /// happens when the task or thread holding it panics
+    pub fn guard(&'static self) -> CheckGuard {
+        self.set(true);
+        CheckGuard(self)
+    }
+
+    /// Name of the check along with whether it is up, as given to [report]
+    pub fn status(&self) -> (&'static str, bool) {
+        (self.name, self.is_up())
+    }
+}
+
+pub struct CheckGuard(&'static Check);
+
+impl Drop for CheckGuard {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// Tell if every check is up, along with a JSON description of each of them
+pub fn report(checks: &[(&str, bool)]) -> (bool, String) {
+    let healthy = checks.iter().all(|(_, up)| *up);
+    let checks: Map<String, Value> = checks
+        .iter()
+        .map(|(name, up)| (name.to_string(), Value::Bool(*up)))
+        .collect();
+    (
+        healthy,
+        json!({ "healthy": healthy, "checks": checks }).to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FIRST: Check = Check::new("first");
+    static SECOND: Check = Check::new("second");
+
+    #[test]
+    fn test_report_checks() {
+        let _first = FIRST.guard();
+        let (healthy, _) = report(&[FIRST.status(), SECOND.status()]);
+        assert!(!healthy);
+
+        {
+            let _second = SECOND.guard();
+            let (healthy, body) = report(&[FIRST.status(), SECOND.status()]);
+            assert!(healthy);
+            assert_eq!(
+                body,
+                r#"{"checks":{"first":true,"second":true},"healthy":true}"#
+            );
+        }
+        assert!(!SECOND.is_up());
+
+        let (healthy, body) = report(&[FIRST.status(), ("database", false)]);
+        assert!(!healthy);
+        assert_eq!(
+            body,
+            r#"{"checks":{"database":false,"first":true},"healthy":false}"#
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Operational endpoints shared by the daemons.
+//!
+//! Metrics are served on `/metrics` in the Prometheus text format, liveness
+//! and readiness on `/healthz` and `/readyz`. Any other request is given to
+//! the daemon, which may serve its own API on the same listener.
+pub mod health;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use tracing::{error, info};
+
+/// How a daemon describes itself on the operational endpoints
+#[derive(Clone, Copy)]
+pub struct Probes {
+    /// Metrics of the daemon, in the Prometheus text format
+    pub metrics: fn() -> String,
+    /// Whether the daemon is able to make progress at all, along with a JSON
+    /// description of its checks
+    pub liveness: fn() -> (bool, String),
+    /// Whether the daemon is able to serve requests, along with a JSON
+    /// description of its checks
+    pub readiness: fn() -> (bool, String),
+}
+
+/// Serve the operational endpoints on `listener`, other requests are
+/// answered by `fallback`
+pub async fn serve<F, R>(listener: SocketAddr, probes: Probes, fallback: F)
+where
+    F: Fn(Request<Body>) -> R + Clone + Send + Sync + 'static,
+    R: Future<Output = hyper::http::Result<Response<Body>>> + Send + 'static,
+{
+    let service = make_service_fn(move |_| {
+        let fallback = fallback.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle(request, probes, fallback.clone())
+            }))
+        }
+    });
+    let server = match Server::try_bind(&listener) {
+        Ok(builder) => builder.serve(service),
+        Err(e) => {
+            error!("Could not listen on {}, reason: {}", listener, e);
+            return;
+        }
+    };
+
+    info!("HTTP endpoints listening on {}", listener);
+
+    if let Err(e) = server.await {
+        error!("{}", e);
+    }
+}
+
+/// Fallback of the daemons serving nothing but the operational endpoints
+pub async fn not_found(_: Request<Body>) -> hyper::http::Result<Response<Body>> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(Body::empty())
+}
+
+async fn handle<F, R>(
+    request: Request<Body>,
+    probes: Probes,
+    fallback: F,
+) -> Result<Response<Body>, Infallible>
+where
+    F: Fn(Request<Body>) -> R,
+    R: Future<Output = hyper::http::Result<Response<Body>>>,
+{
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from((probes.metrics)())),
+        (&Method::GET, "/healthz") => health_response((probes.liveness)()),
+        (&Method::GET, "/readyz") => health_response((probes.readiness)()),
+        _ => fallback(request).await,
+    };
+    Ok(response.unwrap())
+}
+
+fn health_response((healthy, body): (bool, String)) -> hyper::http::Result<Response<Body>> {
+    let status = match healthy {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBES: Probes = Probes {
+        metrics: || "rik_up 1\n".to_string(),
+        liveness: || (true, "{}".to_string()),
+        readiness: || (false, "{}".to_string()),
+    };
+
+    async fn status(method: Method, path: &str) -> StatusCode {
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        handle(request, PROBES, not_found).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        assert_eq!(status(Method::GET, "/metrics").await, StatusCode::OK);
+        assert_eq!(status(Method::GET, "/healthz").await, StatusCode::OK);
+        assert_eq!(
+            status(Method::GET, "/readyz").await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(Method::POST, "/metrics").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(status(Method::GET, "/other").await, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,205 @@
+//! Tracing setup shared by the daemons.
+//!
+//! Spans are exported over OTLP when a collector is configured, and the trace
+//! context is propagated between daemons following W3C Trace Context, either
+//! in the metadata of a gRPC request or in the messages of a stream.
+use opentelemetry::global;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::sdk::propagation::TraceContextPropagator;
+use opentelemetry::sdk::{trace, Resource};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use std::collections::HashMap;
+use thiserror::Error;
+use tonic::metadata::{KeyRef, MetadataKey, MetadataMap, MetadataValue};
+use tracing::metadata::LevelFilter;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Environment variable giving the address of the OTLP collector, e.g.
+/// `http://localhost:4317`. Traces are not exported when it is not set.
+pub const OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Trace context carried by the messages of a stream
+pub type TraceContext = HashMap<String, String>;
+
+#[derive(Debug, Error)]
+pub enum TelemetryError {
+    #[error("Could not install the OTLP exporter: {0}")]
+    Exporter(opentelemetry::trace::TraceError),
+}
+
+/// Flush the spans not exported yet when dropped
+pub struct TelemetryGuard {
+    exporting: bool,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if self.exporting {
+            global::shutdown_tracer_provider();
+        }
+    }
+}
+
+/// Install the subscriber of a daemon. Logs are written on stdout and
+/// filtered with `RUST_LOG`, spans are exported to the collector given by
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` under the name of the service.
+///
+/// Must be called from a Tokio runtime, which exports spans in the background.
+pub fn init(service: &'static str) -> Result<TelemetryGuard, TelemetryError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let registry = tracing_subscriber::registry().with(fmt::layer()).with(
+        EnvFilter::builder()
+            .with_default_directive(LevelFilter::INFO.into())
+            .from_env_lossy(),
+    );
+
+    let endpoint = match std::env::var(OTLP_ENDPOINT) {
+        Ok(endpoint) => endpoint,
+        Err(_) => {
+            registry.init();
+            return Ok(TelemetryGuard { exporting: false });
+        }
+    };
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_resource(Resource::new(vec![KeyValue::new("service.name", service)])),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)
+        .map_err(TelemetryError::Exporter)?;
+    registry
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .init();
+    Ok(TelemetryGuard { exporting: true })
+}
+
+/// Trace context of the current span, to be sent along a message
+pub fn current_context() -> TraceContext {
+    let mut context = TraceContext::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&Span::current().context(), &mut context)
+    });
+    context
+}
+
+/// Attach the span to the trace a message was sent from. The span stays a
+/// root span when the message carries no context.
+pub fn set_parent(span: &Span, context: &TraceContext) {
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(context));
+    span.set_parent(parent);
+}
+
+/// Add the trace context of the current span to the metadata of a request
+pub fn inject_metadata(metadata: &mut MetadataMap) {
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&Span::current().context(), &mut MetadataInjector(metadata))
+    });
+}
+
+/// Attach the span to the trace a request was sent from
+pub fn set_parent_from_metadata(span: &Span, metadata: &MetadataMap) {
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&MetadataExtractor(metadata))
+    });
+    span.set_parent(parent);
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (
+            MetadataKey::from_bytes(key.as_bytes()),
+            MetadataValue::try_from(&value),
+        ) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+struct MetadataExtractor<'a>(&'a MetadataMap);
+
+impl Extractor for MetadataExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .keys()
+            .filter_map(|key| match key {
+                KeyRef::Ascii(key) => Some(key.as_str()),
+                KeyRef::Binary(_) => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{TraceContextExt, TracerProvider};
+    use tracing::info_span;
+    use tracing_subscriber::Registry;
+
+    fn with_tracer<T>(f: impl FnOnce() -> T) -> T {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        // The tracer does not keep its provider alive
+        let provider = trace::TracerProvider::builder().build();
+        let tracer = provider.tracer("test");
+        let subscriber =
+            Registry::default().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        tracing::subscriber::with_default(subscriber, f)
+    }
+
+    fn trace_id(span: &Span) -> opentelemetry::trace::TraceId {
+        span.context().span().span_context().trace_id()
+    }
+
+    #[test]
+    fn test_propagate_stream_context() {
+        with_tracer(|| {
+            let parent = info_span!("parent");
+            let context = parent.in_scope(current_context);
+            assert!(context.contains_key("traceparent"));
+
+            let child = info_span!("child");
+            set_parent(&child, &context);
+            assert_eq!(trace_id(&child), trace_id(&parent));
+        });
+    }
+
+    #[test]
+    fn test_propagate_metadata_context() {
+        with_tracer(|| {
+            let parent = info_span!("parent");
+            let mut metadata = MetadataMap::new();
+            parent.in_scope(|| inject_metadata(&mut metadata));
+            assert!(metadata.get("traceparent").is_some());
+
+            let child = info_span!("child");
+            set_parent_from_metadata(&child, &metadata);
+            assert_eq!(trace_id(&child), trace_id(&parent));
+        });
+    }
+
+    #[test]
+    fn test_no_context() {
+        with_tracer(|| {
+            let span = info_span!("root");
+            set_parent(&span, &TraceContext::new());
+            assert_ne!(trace_id(&span), opentelemetry::trace::TraceId::INVALID);
+        });
+    }
+}
@@ -0,0 +1,630 @@
+//! Conversions between the protobuf messages and the Rust types they mirror
+use crate::common::{Protocol, WorkloadKind};
+use crate::{metrics, workload};
+use definition::workload as def;
+use node_metrics::metrics::{CpuMetrics, DiskMetrics, MemoryMetrics, Metrics};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ConversionError {
+    #[error("Missing field: {0}")]
+    MissingField(&'static str),
+
+    #[error("Field {0} is out of range")]
+    OutOfRange(&'static str),
+
+    #[error("Invalid value for field {0}: {1}")]
+    InvalidValue(&'static str, String),
+}
+
+type Result<T> = std::result::Result<T, ConversionError>;
+
+fn to_u16(value: u32, field: &'static str) -> Result<u16> {
+    u16::try_from(value).map_err(|_| ConversionError::OutOfRange(field))
+}
+
+/// Protobuf cannot tell an empty list from a missing one
+fn non_empty<T>(values: Vec<T>) -> Option<Vec<T>> {
+    match values.is_empty() {
+        true => None,
+        false => Some(values),
+    }
+}
+
+/// Only the value is sent, secret references are resolved into it by the
+/// controller before
+impl From<def::EnvConfig> for workload::EnvConfig {
+    fn from(value: def::EnvConfig) -> Self {
+        Self {
+            name: value.name,
+            value: value.value,
+            secret_key_ref: value.value_from.map(|source| workload::SecretKeyRef {
+                name: source.secret_key_ref.name,
+                key: source.secret_key_ref.key,
+                value: source.value.0,
+            }),
+        }
+    }
+}
+
+impl From<workload::EnvConfig> for def::EnvConfig {
+    fn from(value: workload::EnvConfig) -> Self {
+        Self {
+            name: value.name,
+            value: value.value,
+            value_from: value.secret_key_ref.map(|reference| def::EnvSource {
+                secret_key_ref: def::SecretKeyRef {
+                    name: reference.name,
+                    key: reference.key,
+                },
+                value: def::SecretValue(reference.value),
+            }),
+        }
+    }
+}
+
+impl From<def::Protocol> for Protocol {
+    fn from(value: def::Protocol) -> Self {
+        match value {
+            def::Protocol::Tcp => Protocol::Tcp,
+            def::Protocol::Udp => Protocol::Udp,
+            def::Protocol::Sctp => Protocol::Sctp,
+        }
+    }
+}
+
+impl From<Protocol> for def::Protocol {
+    fn from(value: Protocol) -> Self {
+        match value {
+            Protocol::Tcp => def::Protocol::Tcp,
+            Protocol::Udp => def::Protocol::Udp,
+            Protocol::Sctp => def::Protocol::Sctp,
+        }
+    }
+}
+
+impl From<def::PortConfig> for workload::PortConfig {
+    fn from(value: def::PortConfig) -> Self {
+        Self {
+            port: value.port.into(),
+            target_port: value.target_port.into(),
+            protocol: value.protocol.map(|protocol| protocol.to_string()),
+            r#type: value.r#type,
+        }
+    }
+}
+
+impl TryFrom<workload::PortConfig> for def::PortConfig {
+    type Error = ConversionError;
+
+    fn try_from(value: workload::PortConfig) -> Result<Self> {
+        Ok(Self {
+            port: to_u16(value.port, "ports.port")?,
+            target_port: to_u16(value.target_port, "ports.target_port")?,
+            protocol: value
+                .protocol
+                .map(|protocol| {
+                    protocol
+                        .parse()
+                        .map_err(|_| ConversionError::InvalidValue("ports.protocol", protocol))
+                })
+                .transpose()?,
+            r#type: value.r#type,
+        })
+    }
+}
+
+impl From<def::VolumeMount> for workload::VolumeMount {
+    fn from(value: def::VolumeMount) -> Self {
+        Self {
+            name: value.name,
+            mount_path: value.mount_path,
+            read_only: value.read_only,
+        }
+    }
+}
+
+impl From<workload::VolumeMount> for def::VolumeMount {
+    fn from(value: workload::VolumeMount) -> Self {
+        Self {
+            name: value.name,
+            mount_path: value.mount_path,
+            read_only: value.read_only,
+        }
+    }
+}
+
+impl From<def::Volume> for workload::Volume {
+    fn from(value: def::Volume) -> Self {
+        let source = match value.source {
+            def::VolumeSource::EmptyDir {} => {
+                workload::volume::Source::EmptyDir(workload::EmptyDirVolume {})
+            }
+            def::VolumeSource::HostPath { path } => {
+                workload::volume::Source::HostPath(workload::HostPathVolume { path })
+            }
+            def::VolumeSource::Secret { secret_name, items } => {
+                workload::volume::Source::Secret(workload::SecretVolume {
+                    secret_name,
+                    items: items.0.into_iter().collect(),
+                })
+            }
+        };
+        Self {
+            name: value.name,
+            source: Some(source),
+        }
+    }
+}
+
+impl TryFrom<workload::Volume> for def::Volume {
+    type Error = ConversionError;
+
+    fn try_from(value: workload::Volume) -> Result<Self> {
+        let source = match value.source {
+            Some(workload::volume::Source::EmptyDir(_)) => def::VolumeSource::EmptyDir {},
+            Some(workload::volume::Source::HostPath(host_path)) => def::VolumeSource::HostPath {
+                path: host_path.path,
+            },
+            Some(workload::volume::Source::Secret(secret)) => def::VolumeSource::Secret {
+                secret_name: secret.secret_name,
+                items: def::SecretItems(secret.items.into_iter().collect()),
+            },
+            None => return Err(ConversionError::MissingField("volumes.source")),
+        };
+        Ok(Self {
+            name: value.name,
+            source,
+        })
+    }
+}
+
+impl From<def::Container> for workload::Container {
+    fn from(value: def::Container) -> Self {
+        Self {
+            name: value.name,
+            image: value.image,
+            env: value
+                .env
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            ports: value.ports.map(Into::into),
+            command: value.command.unwrap_or_default(),
+            args: value.args.unwrap_or_default(),
+            working_dir: value.working_dir,
+            user: value.user.map(|user| workload::ContainerUser {
+                uid: user.uid,
+                gid: user.gid,
+            }),
+            volume_mounts: value.volume_mounts.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<workload::Container> for def::Container {
+    type Error = ConversionError;
+
+    fn try_from(value: workload::Container) -> Result<Self> {
+        Ok(Self {
+            name: value.name,
+            image: value.image,
+            env: non_empty(value.env.into_iter().map(Into::into).collect()),
+            ports: value.ports.map(TryInto::try_into).transpose()?,
+            command: non_empty(value.command),
+            args: non_empty(value.args),
+            working_dir: value.working_dir,
+            user: value.user.map(|user| def::ContainerUser {
+                uid: user.uid,
+                gid: user.gid,
+            }),
+            volume_mounts: value.volume_mounts.into_iter().map(Into::into).collect(),
+        })
+    }
+}
+
+impl From<def::FunctionPort> for workload::FunctionPort {
+    fn from(value: def::FunctionPort) -> Self {
+        let port_type = match value.port_type {
+            def::NetworkPortExposureType::NodePort => workload::NetworkPortExposureType::NodePort,
+        };
+        Self {
+            port: value.port.into(),
+            target_port: value.target_port.into(),
+            r#type: port_type.into(),
+            protocol: Protocol::from(value.protocol).into(),
+        }
+    }
+}
+
+impl TryFrom<workload::FunctionPort> for def::FunctionPort {
+    type Error = ConversionError;
+
+    fn try_from(value: workload::FunctionPort) -> Result<Self> {
+        let port_type = match workload::NetworkPortExposureType::from_i32(value.r#type) {
+            Some(workload::NetworkPortExposureType::NodePort) => {
+                def::NetworkPortExposureType::NodePort
+            }
+            None => {
+                return Err(ConversionError::InvalidValue(
+                    "exposure.type",
+                    value.r#type.to_string(),
+                ))
+            }
+        };
+        let protocol = Protocol::from_i32(value.protocol).ok_or_else(|| {
+            ConversionError::InvalidValue("exposure.protocol", value.protocol.to_string())
+        })?;
+        Ok(Self {
+            port: to_u16(value.port, "exposure.port")?,
+            target_port: to_u16(value.target_port, "exposure.target_port")?,
+            port_type,
+            protocol: protocol.into(),
+        })
+    }
+}
+
+impl From<def::Function> for workload::Function {
+    fn from(value: def::Function) -> Self {
+        Self {
+            execution: Some(workload::FunctionExecution {
+                rootfs: value.execution.rootfs.to_string(),
+            }),
+            exposure: value.exposure.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<workload::Function> for def::Function {
+    type Error = ConversionError;
+
+    fn try_from(value: workload::Function) -> Result<Self> {
+        let execution = value
+            .execution
+            .ok_or(ConversionError::MissingField("function.execution"))?;
+        let rootfs = url::Url::parse(&execution.rootfs).map_err(|e| {
+            ConversionError::InvalidValue("function.execution.rootfs", e.to_string())
+        })?;
+        Ok(Self {
+            execution: def::FunctionExecution { rootfs },
+            exposure: value.exposure.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+impl From<def::RestartPolicy> for workload::RestartPolicy {
+    fn from(value: def::RestartPolicy) -> Self {
+        match value {
+            def::RestartPolicy::Always => workload::RestartPolicy::Always,
+            def::RestartPolicy::OnFailure => workload::RestartPolicy::OnFailure,
+            def::RestartPolicy::Never => workload::RestartPolicy::Never,
+        }
+    }
+}
+
+impl From<workload::RestartPolicy> for def::RestartPolicy {
+    fn from(value: workload::RestartPolicy) -> Self {
+        match value {
+            workload::RestartPolicy::Always => def::RestartPolicy::Always,
+            workload::RestartPolicy::OnFailure => def::RestartPolicy::OnFailure,
+            workload::RestartPolicy::Never => def::RestartPolicy::Never,
+        }
+    }
+}
+
+impl From<def::Spec> for workload::Spec {
+    fn from(value: def::Spec) -> Self {
+        Self {
+            containers: value.containers.into_iter().map(Into::into).collect(),
+            function: value.function.map(Into::into),
+            termination_grace_period_seconds: value.termination_grace_period_seconds,
+            restart_policy: workload::RestartPolicy::from(value.restart_policy).into(),
+            share_process_namespace: value.share_process_namespace,
+            volumes: value.volumes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<workload::Spec> for def::Spec {
+    type Error = ConversionError;
+
+    fn try_from(value: workload::Spec) -> Result<Self> {
+        let restart_policy =
+            workload::RestartPolicy::from_i32(value.restart_policy).ok_or_else(|| {
+                ConversionError::InvalidValue("restart_policy", value.restart_policy.to_string())
+            })?;
+        Ok(Self {
+            containers: value
+                .containers
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<Vec<_>>>()?,
+            function: value.function.map(TryInto::try_into).transpose()?,
+            termination_grace_period_seconds: value.termination_grace_period_seconds,
+            restart_policy: restart_policy.into(),
+            share_process_namespace: value.share_process_namespace,
+            volumes: value
+                .volumes
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+}
+
+impl From<def::WorkloadDefinition> for workload::WorkloadDefinition {
+    fn from(value: def::WorkloadDefinition) -> Self {
+        Self {
+            api_version: value.api_version,
+            kind: WorkloadKind::from(value.kind).into(),
+            name: value.name,
+            spec: Some(value.spec.into()),
+            replicas: value.replicas.map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<workload::WorkloadDefinition> for def::WorkloadDefinition {
+    type Error = ConversionError;
+
+    fn try_from(value: workload::WorkloadDefinition) -> Result<Self> {
+        let kind = WorkloadKind::from_i32(value.kind)
+            .ok_or_else(|| ConversionError::InvalidValue("kind", value.kind.to_string()))?;
+        Ok(Self {
+            api_version: value.api_version,
+            kind: kind.into(),
+            name: value.name,
+            spec: value
+                .spec
+                .ok_or(ConversionError::MissingField("spec"))?
+                .try_into()?,
+            replicas: value
+                .replicas
+                .map(|replicas| to_u16(replicas, "replicas"))
+                .transpose()?,
+        })
+    }
+}
+
+impl From<&Metrics> for metrics::NodeMetrics {
+    fn from(value: &Metrics) -> Self {
+        Self {
+            cpu: Some(metrics::CpuMetrics {
+                total: value.cpu.total.into(),
+                free: value.cpu.free,
+            }),
+            memory: Some(metrics::MemoryMetrics {
+                total: value.memory.total,
+                free: value.memory.free,
+            }),
+            disks: value
+                .disks
+                .iter()
+                .map(|disk| metrics::DiskMetrics {
+                    disk_name: disk.disk_name.clone(),
+                    total: disk.total,
+                    free: disk.free,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<metrics::NodeMetrics> for Metrics {
+    type Error = ConversionError;
+
+    fn try_from(value: metrics::NodeMetrics) -> Result<Self> {
+        let cpu = value.cpu.ok_or(ConversionError::MissingField("cpu"))?;
+        let memory = value
+            .memory
+            .ok_or(ConversionError::MissingField("memory"))?;
+        Ok(Self {
+            cpu: CpuMetrics {
+                total: u8::try_from(cpu.total)
+                    .map_err(|_| ConversionError::OutOfRange("cpu.total"))?,
+                free: cpu.free,
+            },
+            memory: MemoryMetrics {
+                total: memory.total,
+                free: memory.free,
+            },
+            disks: value
+                .disks
+                .into_iter()
+                .map(|disk| DiskMetrics {
+                    disk_name: disk.disk_name,
+                    total: disk.total,
+                    free: disk.free,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function_definition() -> def::WorkloadDefinition {
+        def::WorkloadDefinition {
+            api_version: "v0".to_string(),
+            kind: def::WorkloadKind::Function,
+            name: "fn-test".to_string(),
+            spec: def::Spec {
+                containers: vec![],
+                function: Some(def::Function {
+                    execution: def::FunctionExecution {
+                        rootfs: url::Url::parse("https://example.com/rootfs.ext4").unwrap(),
+                    },
+                    exposure: Some(def::FunctionPort {
+                        protocol: def::Protocol::Udp,
+                        ..def::FunctionPort::new(45000)
+                    }),
+                }),
+                termination_grace_period_seconds: None,
+                restart_policy: def::RestartPolicy::Always,
+                share_process_namespace: false,
+                volumes: vec![],
+            },
+            replicas: Some(2),
+        }
+    }
+
+    fn pod_definition() -> def::WorkloadDefinition {
+        def::WorkloadDefinition {
+            api_version: "v0".to_string(),
+            kind: def::WorkloadKind::Pod,
+            name: "pod-test".to_string(),
+            spec: def::Spec {
+                containers: vec![def::Container {
+                    name: "debian".to_string(),
+                    image: "debian:latest".to_string(),
+                    env: Some(vec![
+                        def::EnvConfig {
+                            name: "KEY".to_string(),
+                            value: "value".to_string(),
+                            value_from: None,
+                        },
+                        def::EnvConfig {
+                            name: "PASSWORD".to_string(),
+                            value: String::new(),
+                            value_from: Some(def::EnvSource {
+                                secret_key_ref: def::SecretKeyRef {
+                                    name: "database".to_string(),
+                                    key: "password".to_string(),
+                                },
+                                value: def::SecretValue("hunter2".to_string()),
+                            }),
+                        },
+                    ]),
+                    ports: Some(def::PortConfig {
+                        port: 80,
+                        target_port: 8080,
+                        protocol: Some(def::Protocol::Sctp),
+                        r#type: "NodePort".to_string(),
+                    }),
+                    command: Some(vec!["/bin/sh".to_string(), "-c".to_string()]),
+                    args: Some(vec!["sleep infinity".to_string()]),
+                    working_dir: Some("/srv".to_string()),
+                    user: Some(def::ContainerUser {
+                        uid: 1000,
+                        gid: None,
+                    }),
+                    volume_mounts: vec![
+                        def::VolumeMount {
+                            name: "cache".to_string(),
+                            mount_path: "/var/cache".to_string(),
+                            read_only: false,
+                        },
+                        def::VolumeMount {
+                            name: "config".to_string(),
+                            mount_path: "/etc/app".to_string(),
+                            read_only: true,
+                        },
+                    ],
+                }],
+                function: None,
+                termination_grace_period_seconds: Some(10),
+                restart_policy: def::RestartPolicy::OnFailure,
+                share_process_namespace: true,
+                volumes: vec![
+                    def::Volume {
+                        name: "cache".to_string(),
+                        source: def::VolumeSource::EmptyDir {},
+                    },
+                    def::Volume {
+                        name: "config".to_string(),
+                        source: def::VolumeSource::HostPath {
+                            path: "/etc/app".to_string(),
+                        },
+                    },
+                    def::Volume {
+                        name: "credentials".to_string(),
+                        source: def::VolumeSource::Secret {
+                            secret_name: "database".to_string(),
+                            items: def::SecretItems(
+                                [("password".to_string(), "hunter2".to_string())].into(),
+                            ),
+                        },
+                    },
+                ],
+            },
+            replicas: None,
+        }
+    }
+
+    #[test]
+    fn test_workload_definition_round_trip() {
+        for definition in [function_definition(), pod_definition()] {
+            let message: workload::WorkloadDefinition = definition.clone().into();
+            let parsed = def::WorkloadDefinition::try_from(message).unwrap();
+            assert_eq!(parsed, definition);
+        }
+    }
+
+    #[test]
+    fn test_workload_definition_missing_spec() {
+        let mut message: workload::WorkloadDefinition = pod_definition().into();
+        message.spec = None;
+        assert_eq!(
+            def::WorkloadDefinition::try_from(message),
+            Err(ConversionError::MissingField("spec"))
+        );
+    }
+
+    #[test]
+    fn test_workload_definition_port_out_of_range() {
+        let mut message: workload::WorkloadDefinition = pod_definition().into();
+        message.spec.as_mut().unwrap().containers[0]
+            .ports
+            .as_mut()
+            .unwrap()
+            .port = 70000;
+        assert_eq!(
+            def::WorkloadDefinition::try_from(message),
+            Err(ConversionError::OutOfRange("ports.port"))
+        );
+    }
+
+    #[test]
+    fn test_workload_definition_invalid_protocol() {
+        let mut message: workload::WorkloadDefinition = pod_definition().into();
+        message.spec.as_mut().unwrap().containers[0]
+            .ports
+            .as_mut()
+            .unwrap()
+            .protocol = Some("ICMP".to_string());
+        assert_eq!(
+            def::WorkloadDefinition::try_from(message),
+            Err(ConversionError::InvalidValue(
+                "ports.protocol",
+                "ICMP".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_node_metrics_round_trip() {
+        let node_metrics = Metrics {
+            cpu: CpuMetrics {
+                total: 4,
+                free: 75.0,
+            },
+            memory: MemoryMetrics {
+                total: 2048,
+                free: 1024,
+            },
+            disks: vec![DiskMetrics {
+                disk_name: "sda".to_string(),
+                total: 4096,
+                free: 512,
+            }],
+        };
+        let message = metrics::NodeMetrics::from(&node_metrics);
+        let parsed = Metrics::try_from(message).unwrap();
+        assert_eq!(parsed.cpu.total, 4);
+        assert_eq!(parsed.memory.free, 1024);
+        assert_eq!(parsed.disks[0].disk_name, "sda");
+    }
+}
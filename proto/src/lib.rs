@@ -1,4 +1,8 @@
-use common::{worker_status::Status, InstanceMetric, ResourceStatus, WorkloadRequestKind};
+use common::{
+    worker_status::Status, ClusterEvent, InstanceMetric, ResourceStatus, WorkloadKind,
+    WorkloadRequestKind,
+};
+use definition::workload::WorkloadKind as DefinitionWorkloadKind;
 use definition::InstanceStatus;
 use std::ops::Deref;
 pub mod common {
@@ -13,6 +17,21 @@ pub mod controller {
     tonic::include_proto!("controller");
 }
 
+pub mod workload {
+    tonic::include_proto!("workload");
+}
+
+pub mod metrics {
+    tonic::include_proto!("metrics");
+}
+
+pub mod admin {
+    tonic::include_proto!("admin");
+}
+
+mod conversion;
+pub use conversion::ConversionError;
+
 impl From<i32> for WorkloadRequestKind {
     fn from(w: i32) -> Self {
         match w {
@@ -25,6 +44,7 @@ impl From<i32> for WorkloadRequestKind {
 impl From<i32> for ResourceStatus {
     fn from(w: i32) -> Self {
         match w {
+            7 => ResourceStatus::Succeeded,
             6 => ResourceStatus::Destroying,
             5 => ResourceStatus::Creating,
             4 => ResourceStatus::Terminated,
@@ -36,6 +56,24 @@ impl From<i32> for ResourceStatus {
     }
 }
 
+impl From<DefinitionWorkloadKind> for WorkloadKind {
+    fn from(value: DefinitionWorkloadKind) -> Self {
+        match value {
+            DefinitionWorkloadKind::Pod => WorkloadKind::Pod,
+            DefinitionWorkloadKind::Function => WorkloadKind::Function,
+        }
+    }
+}
+
+impl From<WorkloadKind> for DefinitionWorkloadKind {
+    fn from(value: WorkloadKind) -> Self {
+        match value {
+            WorkloadKind::Pod => DefinitionWorkloadKind::Pod,
+            WorkloadKind::Function => DefinitionWorkloadKind::Function,
+        }
+    }
+}
+
 impl From<ResourceStatus> for InstanceStatus {
     fn from(value: ResourceStatus) -> Self {
         match value {
@@ -46,6 +84,7 @@ impl From<ResourceStatus> for InstanceStatus {
             ResourceStatus::Terminated => InstanceStatus::Terminated,
             ResourceStatus::Creating => InstanceStatus::Creating,
             ResourceStatus::Destroying => InstanceStatus::Destroying,
+            ResourceStatus::Succeeded => InstanceStatus::Succeeded,
         }
     }
 }
@@ -66,10 +105,36 @@ impl WorkerStatus {
             status: Some(Status::Instance(InstanceMetric {
                 instance_id,
                 status: status.into(),
-                metrics: "".to_string(),
+                details: None,
+                trace_context: Default::default(),
+                timestamp: definition::now_millis(),
+                placement: None,
             })),
         })
     }
+
+    /// Report an event, forwarded as is up to the controller
+    pub fn event(identifier: String, event: ClusterEvent) -> Self {
+        Self(common::WorkerStatus {
+            identifier,
+            host_address: None,
+            status: Some(Status::Event(event)),
+        })
+    }
+}
+
+impl ClusterEvent {
+    /// Event about an instance, happening now
+    pub fn instance(instance_id: &str, reason: &str, message: String, source: String) -> Self {
+        Self {
+            object_kind: "instance".to_string(),
+            object_id: instance_id.to_string(),
+            reason: reason.to_string(),
+            message,
+            source,
+            timestamp: definition::now_millis(),
+        }
+    }
 }
 
 impl Deref for WorkerStatus {
@@ -1,4 +1,4 @@
-use crate::cli::resource::{CreateResource, GetMultipleResource};
+use crate::cli::resource::{AdminResource, CreateResource, GetMultipleResource};
 use crate::cli::Handler;
 use clap::Args;
 
@@ -29,6 +29,24 @@ impl GetMultipleCommand {
         match self.resource {
             GetMultipleResource::Instances(handler) => Box::new(handler),
             GetMultipleResource::Workloads(handler) => Box::new(handler),
+            GetMultipleResource::Nodes(handler) => Box::new(handler),
+        }
+    }
+}
+
+/// Inspect the state of the scheduler.
+#[derive(Debug, Args)]
+pub struct AdminCommand {
+    #[clap(subcommand)]
+    resource: AdminResource,
+}
+
+impl AdminCommand {
+    pub fn command(self) -> Box<dyn Handler> {
+        match self.resource {
+            AdminResource::Workers(handler) => Box::new(handler),
+            AdminResource::Placements(handler) => Box::new(handler),
+            AdminResource::State(handler) => Box::new(handler),
         }
     }
 }
@@ -1,7 +1,8 @@
 pub mod command;
 mod resource;
 
-use crate::cli::command::{CreateCommand, GetMultipleCommand};
+use crate::cli::command::{AdminCommand, CreateCommand, GetMultipleCommand};
+use crate::cli::resource::{InstanceAttach, InstanceExec, InstanceLogs};
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::{Parser, Subcommand};
@@ -28,6 +29,14 @@ pub enum Command {
     Create(CreateCommand),
     /// Fetch a resource from a cluster
     Get(GetMultipleCommand),
+    /// Inspect the scheduler of a cluster
+    Admin(AdminCommand),
+    /// Print the logs of an instance
+    Logs(InstanceLogs),
+    /// Run a command in a container of an instance
+    Exec(InstanceExec),
+    /// Attach to the main process of a container of an instance
+    Attach(InstanceAttach),
 }
 
 /// Command line interface to interact with a RIK Cluster
@@ -43,6 +52,10 @@ impl CommandLineInterface {
         match self.command {
             Command::Create(subcommand) => subcommand.command(),
             Command::Get(subcommand) => subcommand.command(),
+            Command::Admin(subcommand) => subcommand.command(),
+            Command::Logs(handler) => Box::new(handler),
+            Command::Exec(handler) => Box::new(handler),
+            Command::Attach(handler) => Box::new(handler),
         }
     }
 }
@@ -0,0 +1,221 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Args;
+use definition::now_millis;
+use prettytable::row;
+
+use crate::cli::Handler;
+use crate::core::admin::{Placement, SchedulerWorker};
+use crate::core::client::{AdminClient, Client};
+use crate::core::config::Configuration;
+
+use super::{last_seen, DisplayResource, MEBIBYTE};
+
+#[derive(Debug, Args)]
+pub struct GetSchedulerWorkers {}
+
+#[async_trait]
+impl Handler for GetSchedulerWorkers {
+    #[tracing::instrument(name = "GetSchedulerWorkers::handler", skip(self))]
+    async fn handler(&self) -> Result<()> {
+        let config = Configuration::load()?;
+        let workers = Client::init(config.cluster).get_scheduler_workers().await?;
+
+        workers.into_table().printstd();
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct GetPlacements {}
+
+#[async_trait]
+impl Handler for GetPlacements {
+    #[tracing::instrument(name = "GetPlacements::handler", skip(self))]
+    async fn handler(&self) -> Result<()> {
+        let config = Configuration::load()?;
+        let placements = Client::init(config.cluster).get_placements().await?;
+
+        placements.into_table().printstd();
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct GetSchedulerState {}
+
+#[async_trait]
+impl Handler for GetSchedulerState {
+    #[tracing::instrument(name = "GetSchedulerState::handler", skip(self))]
+    async fn handler(&self) -> Result<()> {
+        let config = Configuration::load()?;
+        let state = Client::init(config.cluster).get_scheduler_state().await?;
+
+        println!("{}", serde_json::to_string_pretty(&state)?);
+        Ok(())
+    }
+}
+
+fn workers_table(workers: &[SchedulerWorker], now: u64) -> prettytable::Table {
+    let mut table = Vec::<SchedulerWorker>::new_table();
+    table.set_titles(row![
+        "ID",
+        "ADDRESS",
+        "STATE",
+        "KINDS",
+        "CPU",
+        "MEMORY",
+        "LAST SEEN"
+    ]);
+    if workers.is_empty() {
+        table.add_row(row!["", "", "", "", "", "", ""]);
+    }
+    for worker in workers {
+        let kinds = worker
+            .capabilities
+            .as_ref()
+            .map(|capabilities| capabilities.kinds.join(","))
+            .unwrap_or_else(|| "*".to_string());
+        let (cpu, memory) = match &worker.metrics {
+            Some(metrics) => (
+                format!("{} ({:.1}% free)", metrics.cpu.total, metrics.cpu.free),
+                format!(
+                    "{}/{} MiB",
+                    metrics.memory.free / MEBIBYTE,
+                    metrics.memory.total / MEBIBYTE
+                ),
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        table.add_row(row![
+            worker.id,
+            worker.address,
+            worker.state,
+            kinds,
+            cpu,
+            memory,
+            last_seen(worker.last_heartbeat, now)
+        ]);
+    }
+    table
+}
+
+impl DisplayResource for Vec<SchedulerWorker> {
+    #[tracing::instrument(name = "DisplayResource::scheduler_worker::into_table", skip(self))]
+    fn into_table(&self) -> prettytable::Table {
+        workers_table(self, now_millis())
+    }
+}
+
+impl DisplayResource for Vec<Placement> {
+    #[tracing::instrument(name = "DisplayResource::placement::into_table", skip(self))]
+    fn into_table(&self) -> prettytable::Table {
+        let mut table = Self::new_table();
+        table.set_titles(row!["WORKLOAD", "INSTANCE", "WORKER", "STATUS"]);
+        if self.is_empty() {
+            table.add_row(row!["", "", "", ""]);
+        }
+        for placement in self {
+            if placement.instances.is_empty() {
+                table.add_row(row![placement.workload_id, "", "", placement.status]);
+            }
+            for instance in &placement.instances {
+                table.add_row(row![
+                    placement.workload_id,
+                    instance.instance_id,
+                    instance.worker_id.as_deref().unwrap_or("-"),
+                    instance.status
+                ]);
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::admin::{InstanceAssignment, WorkerCapabilities};
+    use crate::core::node::{CpuMetrics, MemoryMetrics, NodeMetrics};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn display_scheduler_workers_table() {
+        let workers = vec![
+            SchedulerWorker {
+                id: "worker-1".to_string(),
+                address: "10.0.0.2:42190".to_string(),
+                state: "READY".to_string(),
+                last_heartbeat: Some(10_000),
+                metrics: Some(NodeMetrics {
+                    cpu: CpuMetrics {
+                        total: 4,
+                        free: 75.0,
+                    },
+                    memory: MemoryMetrics {
+                        total: 2048 * MEBIBYTE,
+                        free: 1024 * MEBIBYTE,
+                    },
+                    disks: vec![],
+                }),
+                capabilities: Some(WorkerCapabilities {
+                    kinds: vec!["POD".to_string(), "FUNCTION".to_string()],
+                    arch: "x86_64".to_string(),
+                    runtimes: vec![],
+                }),
+            },
+            SchedulerWorker {
+                id: "worker-2".to_string(),
+                address: "10.0.0.3:42190".to_string(),
+                state: "NOT_READY".to_string(),
+                last_heartbeat: None,
+                metrics: None,
+                capabilities: None,
+            },
+        ];
+
+        let table = workers_table(&workers, 15_000);
+        let expected_output = r#" ID        ADDRESS         STATE      KINDS         CPU             MEMORY         LAST SEEN 
+ worker-1  10.0.0.2:42190  READY      POD,FUNCTION  4 (75.0% free)  1024/2048 MiB  5s ago 
+ worker-2  10.0.0.3:42190  NOT_READY  *             -               -              never 
+"#;
+        assert_eq!(table.to_string(), expected_output);
+    }
+
+    #[test]
+    fn display_placements_table() {
+        let placements = vec![
+            Placement {
+                workload_id: "workload-1".to_string(),
+                replicas: 2,
+                status: "RUNNING".to_string(),
+                instances: vec![
+                    InstanceAssignment {
+                        instance_id: "instance-1".to_string(),
+                        worker_id: Some("worker-1".to_string()),
+                        status: "RUNNING".to_string(),
+                    },
+                    InstanceAssignment {
+                        instance_id: "instance-2".to_string(),
+                        worker_id: None,
+                        status: "PENDING".to_string(),
+                    },
+                ],
+            },
+            Placement {
+                workload_id: "workload-2".to_string(),
+                replicas: 0,
+                status: "DESTROYING".to_string(),
+                instances: vec![],
+            },
+        ];
+
+        let table = placements.into_table();
+        let expected_output = r#" WORKLOAD    INSTANCE    WORKER    STATUS 
+ workload-1  instance-1  worker-1  RUNNING 
+ workload-1  instance-2  -         PENDING 
+ workload-2                        DESTROYING 
+"#;
+        assert_eq!(table.to_string(), expected_output);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::core::client::{Client, ResponseEntity};
+use crate::core::client::{Client, ResponseEntity, Session};
 use crate::core::instance::Instance;
 use crate::{
     cli::Handler,
@@ -7,9 +7,15 @@ use crate::{
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Args;
+use definition::now_millis;
+use nix::sys::termios::{self, SetArg, Termios};
 use prettytable::row;
+use reqwest::Upgraded;
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use tokio::io::AsyncWriteExt;
 
-use super::DisplayResource;
+use super::{last_seen, DisplayResource};
 #[derive(Debug, Args)]
 pub struct CreateInstance {
     #[clap(short, long)]
@@ -37,6 +43,171 @@ impl Handler for CreateInstance {
     }
 }
 
+#[derive(Debug, Args)]
+pub struct InstanceLogs {
+    /// Id of the instance
+    pub instance: String,
+
+    /// Container to print the output of, needed when the instance has several
+    #[clap(short, long)]
+    pub container: Option<String>,
+
+    /// Keep printing the output as it is written
+    #[clap(short, long)]
+    pub follow: bool,
+
+    /// Only print the last lines
+    #[clap(long)]
+    pub tail: Option<usize>,
+}
+
+#[async_trait]
+impl Handler for InstanceLogs {
+    async fn handler(&self) -> Result<()> {
+        let config = Configuration::load()?;
+        let mut response = Client::init(config.cluster)
+            .get_logs(&self.instance, &self.container, self.tail, self.follow)
+            .await?;
+
+        let mut stdout = std::io::stdout();
+        while let Some(chunk) = response.chunk().await? {
+            stdout.write_all(&chunk)?;
+            stdout.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct InstanceExec {
+    /// Id of the instance
+    pub instance: String,
+
+    /// Container to run the command in, needed when the instance has several
+    #[clap(short, long)]
+    pub container: Option<String>,
+
+    /// Pass the standard input to the command
+    #[clap(short = 'i', long)]
+    pub stdin: bool,
+
+    /// Run the command in a TTY
+    #[clap(short, long)]
+    pub tty: bool,
+
+    /// Command to run, given after `--`
+    #[clap(last = true, required = true)]
+    pub command: Vec<String>,
+}
+
+#[async_trait]
+impl Handler for InstanceExec {
+    async fn handler(&self) -> Result<()> {
+        let config = Configuration::load()?;
+        let client = Client::init(config.cluster);
+        let session = client
+            .exec(&self.instance, &self.container, self.tty, &self.command)
+            .await?;
+        run_session(&client, session, self.stdin, self.tty).await
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct InstanceAttach {
+    /// Id of the instance
+    pub instance: String,
+
+    /// Container to attach to, needed when the instance has several
+    #[clap(short, long)]
+    pub container: Option<String>,
+}
+
+#[async_trait]
+impl Handler for InstanceAttach {
+    async fn handler(&self) -> Result<()> {
+        let config = Configuration::load()?;
+        let client = Client::init(config.cluster);
+        let session = client.attach(&self.instance, &self.container).await?;
+        run_session(&client, session, true, true).await
+    }
+}
+
+/// Print the output of a session until it ends, sending it the standard
+/// input when `stdin` is set
+async fn run_session(client: &Client, mut session: Session, stdin: bool, tty: bool) -> Result<()> {
+    let mut input = client.open_input(&session.id).await?;
+    let forward = match stdin {
+        true => Some(tokio::spawn(forward_stdin(input))),
+        // The command sees the end of its input right away
+        false => {
+            input.shutdown().await?;
+            None
+        }
+    };
+    let _terminal = match stdin && tty {
+        true => RawTerminal::enable(),
+        false => None,
+    };
+
+    let mut stdout = std::io::stdout();
+    while let Some(chunk) = session.output.chunk().await? {
+        stdout.write_all(&chunk)?;
+        stdout.flush()?;
+    }
+    if let Some(forward) = forward {
+        forward.abort();
+    }
+    Ok(())
+}
+
+/// Write the standard input to a session until its end. It is read on a
+/// thread of its own, which does not hold back the exit of rikctl.
+async fn forward_stdin(mut input: Upgraded) {
+    let (sender, mut receiver) = tokio::sync::mpsc::channel::<Vec<u8>>(16);
+    std::thread::spawn(move || {
+        let mut stdin = std::io::stdin();
+        let mut buffer = [0u8; 1024];
+        loop {
+            match stdin.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => {
+                    if sender.blocking_send(buffer[..read].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    while let Some(data) = receiver.recv().await {
+        if input.write_all(&data).await.is_err() || input.flush().await.is_err() {
+            return;
+        }
+    }
+    let _ = input.shutdown().await;
+}
+
+/// Terminal in raw mode while a session with a TTY is open, so keys reach
+/// the command as they are typed. Its settings are restored once dropped.
+struct RawTerminal(Termios);
+
+impl RawTerminal {
+    /// Switch the terminal to raw mode, unless stdin is not a terminal
+    fn enable() -> Option<Self> {
+        let fd = std::io::stdin().as_raw_fd();
+        let original = termios::tcgetattr(fd).ok()?;
+        let mut raw = original.clone();
+        termios::cfmakeraw(&mut raw);
+        termios::tcsetattr(fd, SetArg::TCSANOW, &raw).ok()?;
+        Some(Self(original))
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = termios::tcsetattr(std::io::stdin().as_raw_fd(), SetArg::TCSANOW, &self.0);
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct GetMultipleInstance {}
 
@@ -53,28 +224,81 @@ impl Handler for GetMultipleInstance {
     }
 }
 
+fn instances_table(instances: &[ResponseEntity<Instance>], now: u64) -> prettytable::Table {
+    let mut table = Vec::<ResponseEntity<Instance>>::new_table();
+    table.set_titles(row![
+        "ID",
+        "NAME",
+        "STATUS",
+        "SINCE",
+        "ENDPOINTS",
+        "REASON",
+        "EXIT CODE",
+        "MESSAGE"
+    ]);
+    if instances.is_empty() {
+        table.add_row(row!["", "", "", "", "", "", "", ""]);
+    }
+    for instance in instances {
+        let value = &instance.value;
+        let since = value.phases.get(&value.status).copied();
+        let endpoints = match &value.placement {
+            Some(placement) if !placement.ports.is_empty() => placement.endpoints().join(","),
+            _ => "-".to_string(),
+        };
+        table.add_row(row![
+            instance.id,
+            instance.name,
+            value.status,
+            last_seen(since, now),
+            endpoints,
+            value.reason.as_deref().unwrap_or("-"),
+            value
+                .exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            value.message.as_deref().unwrap_or("-")
+        ]);
+    }
+    table
+}
+
 impl DisplayResource for Vec<ResponseEntity<Instance>> {
     #[tracing::instrument(name = "DisplayResource::instance::into_table", skip(self))]
     fn into_table(&self) -> prettytable::Table {
-        let mut table = Self::new_table();
-        table.set_titles(row!["ID", "NAME", "STATUS"]);
-        if self.is_empty() {
-            table.add_row(row!["", "", ""]);
-        }
-        for instance in self {
-            table.add_row(row![instance.id, instance.name, instance.value.status]);
-        }
-        table
+        instances_table(self, now_millis())
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::instance::{Placement, PlacementPort};
     use pretty_assertions::assert_eq;
+    use std::collections::BTreeMap;
 
     fn create_instance() -> Instance {
         Instance {
             status: "Running".to_string(),
+            reason: None,
+            message: None,
+            exit_code: None,
+            phases: BTreeMap::from([("Running".to_string(), 55_000)]),
+            placement: Some(Placement {
+                host_ip: "10.0.0.2".to_string(),
+                guest_ip: Some("192.168.1.2".to_string()),
+                ports: vec![
+                    PlacementPort {
+                        port: 45001,
+                        target_port: 8080,
+                        protocol: Some("TCP".to_string()),
+                    },
+                    PlacementPort {
+                        port: 30053,
+                        target_port: 53,
+                        protocol: Some("UDP".to_string()),
+                    },
+                ],
+            }),
         }
     }
 
@@ -89,14 +313,21 @@ mod tests {
             ResponseEntity {
                 id: "abcd".to_string(),
                 name: "instance-2".to_string(),
-                value: create_instance(),
+                value: Instance {
+                    status: "Failed".to_string(),
+                    reason: Some("ContainerError".to_string()),
+                    message: Some("Runc command failed".to_string()),
+                    exit_code: Some(1),
+                    phases: BTreeMap::new(),
+                    placement: None,
+                },
             },
         ];
 
-        let table = instances.into_table();
-        let expected_output = r#" ID    NAME        STATUS 
- abde  instance-1  Running 
- abcd  instance-2  Running 
+        let table = instances_table(&instances, 60_000);
+        let expected_output = r#" ID    NAME        STATUS   SINCE   ENDPOINTS                          REASON          EXIT CODE  MESSAGE 
+ abde  instance-1  Running  5s ago  10.0.0.2:45001,10.0.0.2:30053/UDP  -               -          - 
+ abcd  instance-2  Failed   never   -                                  ContainerError  1          Runc command failed 
 "#;
         assert_eq!(table.to_string(), expected_output);
     }
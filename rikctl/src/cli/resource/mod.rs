@@ -1,11 +1,18 @@
+mod admin;
 mod instance;
+mod node;
 mod workload;
 
+use crate::cli::resource::admin::{GetPlacements, GetSchedulerState, GetSchedulerWorkers};
 use crate::cli::resource::instance::{CreateInstance, GetMultipleInstance};
+pub use crate::cli::resource::instance::{InstanceAttach, InstanceExec, InstanceLogs};
+use crate::cli::resource::node::GetMultipleNode;
 use crate::cli::resource::workload::{CreateWorkload, GetMultipleWorkload};
 use clap::Subcommand;
 use prettytable::{format, Table};
 
+const MEBIBYTE: u64 = 1024 * 1024;
+
 #[derive(Debug, Subcommand)]
 pub enum CreateResource {
     /// Create a workload
@@ -18,6 +25,17 @@ pub enum CreateResource {
 pub enum GetMultipleResource {
     Instances(GetMultipleInstance),
     Workloads(GetMultipleWorkload),
+    Nodes(GetMultipleNode),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AdminResource {
+    /// List workers registered on the scheduler
+    Workers(GetSchedulerWorkers),
+    /// List the worker assigned to each instance
+    Placements(GetPlacements),
+    /// Dump the whole scheduler state as JSON
+    State(GetSchedulerState),
 }
 
 /// Trait which defines how resources should be displayed
@@ -33,3 +51,11 @@ where
     /// Prints the list of resources in form of table
     fn into_table(&self) -> Table;
 }
+
+/// Time elapsed since `timestamp`, both given in milliseconds
+fn last_seen(timestamp: Option<u64>, now: u64) -> String {
+    match timestamp {
+        Some(timestamp) => format!("{}s ago", now.saturating_sub(timestamp) / 1000),
+        None => "never".to_string(),
+    }
+}
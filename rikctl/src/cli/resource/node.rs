@@ -0,0 +1,137 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::Args;
+use definition::now_millis;
+use prettytable::row;
+
+use crate::cli::Handler;
+use crate::core::client::{Client, NodeClient};
+use crate::core::config::Configuration;
+use crate::core::node::Node;
+
+use super::{last_seen, DisplayResource, MEBIBYTE};
+
+#[derive(Debug, Args)]
+pub struct GetMultipleNode {}
+
+#[async_trait]
+impl Handler for GetMultipleNode {
+    #[tracing::instrument(name = "GetMultipleNode::handler", skip(self))]
+    async fn handler(&self) -> Result<()> {
+        let config = Configuration::load()?;
+        let nodes = Client::init(config.cluster).get_nodes().await?;
+
+        nodes.into_table().printstd();
+        Ok(())
+    }
+}
+
+fn nodes_table(nodes: &[Node], now: u64) -> prettytable::Table {
+    let mut table = Vec::<Node>::new_table();
+    table.set_titles(row![
+        "NAME",
+        "STATE",
+        "ADDRESS",
+        "CPU",
+        "MEMORY",
+        "DISK",
+        "INSTANCES",
+        "LAST SEEN"
+    ]);
+    if nodes.is_empty() {
+        table.add_row(row!["", "", "", "", "", "", "", ""]);
+    }
+    for node in nodes {
+        let (cpu, memory, disk) = match &node.metrics {
+            Some(metrics) => (
+                format!("{} ({:.1}% free)", metrics.cpu.total, metrics.cpu.free),
+                format!(
+                    "{}/{} MiB",
+                    metrics.memory.free / MEBIBYTE,
+                    metrics.memory.total / MEBIBYTE
+                ),
+                format!(
+                    "{}/{} MiB",
+                    metrics.disks.iter().map(|disk| disk.free).sum::<u64>() / MEBIBYTE,
+                    metrics.disks.iter().map(|disk| disk.total).sum::<u64>() / MEBIBYTE
+                ),
+            ),
+            None => ("-".to_string(), "-".to_string(), "-".to_string()),
+        };
+        table.add_row(row![
+            node.id,
+            node.state,
+            node.address,
+            cpu,
+            memory,
+            disk,
+            node.instances.len(),
+            last_seen(Some(node.last_seen), now)
+        ]);
+    }
+    table
+}
+
+impl DisplayResource for Vec<Node> {
+    #[tracing::instrument(name = "DisplayResource::node::into_table", skip(self))]
+    fn into_table(&self) -> prettytable::Table {
+        nodes_table(self, now_millis())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::node::{CpuMetrics, DiskMetrics, MemoryMetrics, NodeMetrics};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn display_nodes_table() {
+        let nodes = vec![
+            Node {
+                id: "worker-1".to_string(),
+                address: "10.0.0.2:42190".to_string(),
+                state: "Ready".to_string(),
+                last_seen: 58_000,
+                metrics: Some(NodeMetrics {
+                    cpu: CpuMetrics {
+                        total: 2,
+                        free: 50.0,
+                    },
+                    memory: MemoryMetrics {
+                        total: 4096 * MEBIBYTE,
+                        free: 512 * MEBIBYTE,
+                    },
+                    disks: vec![
+                        DiskMetrics {
+                            disk_name: "sda".to_string(),
+                            total: 1024 * MEBIBYTE,
+                            free: 256 * MEBIBYTE,
+                        },
+                        DiskMetrics {
+                            disk_name: "sdb".to_string(),
+                            total: 1024 * MEBIBYTE,
+                            free: 256 * MEBIBYTE,
+                        },
+                    ],
+                }),
+                instances: vec!["instance-1".to_string(), "instance-2".to_string()],
+            },
+            Node {
+                id: "worker-2".to_string(),
+                address: "10.0.0.3:42190".to_string(),
+                state: "NotReady".to_string(),
+                last_seen: 0,
+                metrics: None,
+                instances: vec![],
+            },
+        ];
+
+        let table = nodes_table(&nodes, 60_000);
+        let expected_output = r#" NAME      STATE     ADDRESS         CPU             MEMORY        DISK          INSTANCES  LAST SEEN 
+ worker-1  Ready     10.0.0.2:42190  2 (50.0% free)  512/4096 MiB  512/2048 MiB  2          2s ago 
+ worker-2  NotReady  10.0.0.3:42190  -               -             -             0          60s ago 
+"#;
+        assert_eq!(table.to_string(), expected_output);
+    }
+}
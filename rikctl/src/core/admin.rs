@@ -0,0 +1,37 @@
+use crate::core::node::NodeMetrics;
+use serde::{Deserialize, Serialize};
+
+/// A worker registered on the scheduler
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SchedulerWorker {
+    pub id: String,
+    pub address: String,
+    pub state: String,
+    /// Unix timestamp in milliseconds of the last message from the worker
+    pub last_heartbeat: Option<u64>,
+    pub metrics: Option<NodeMetrics>,
+    pub capabilities: Option<WorkerCapabilities>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WorkerCapabilities {
+    pub kinds: Vec<String>,
+    pub arch: String,
+    pub runtimes: Vec<String>,
+}
+
+/// Instances of a workload with the worker they are placed on
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Placement {
+    pub workload_id: String,
+    pub replicas: u32,
+    pub status: String,
+    pub instances: Vec<InstanceAssignment>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InstanceAssignment {
+    pub instance_id: String,
+    pub worker_id: Option<String>,
+    pub status: String,
+}
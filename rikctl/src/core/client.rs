@@ -1,13 +1,16 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
-use reqwest::Client as HttpClient;
+use reqwest::header::{CONNECTION, UPGRADE};
+use reqwest::{Client as HttpClient, Response, Upgraded};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use crate::core::admin::{Placement, SchedulerWorker};
 use crate::core::config;
 use crate::core::workload::Workload;
 
 use super::instance::Instance;
+use super::node::Node;
 
 /// `ResponseEntity` holds data about an entity
 /// returned by the API.
@@ -18,6 +21,16 @@ pub struct ResponseEntity<T> {
     pub value: T,
 }
 
+/// Header giving the id of a session opened in a container
+const SESSION_HEADER: &str = "Rik-Session";
+
+/// A session opened in a container, whose output is the body of `output`.
+/// Its input is sent over the connection given by `open_input`.
+pub struct Session {
+    pub id: String,
+    pub output: Response,
+}
+
 #[async_trait]
 pub trait WorkloadClient {
     async fn get_workloads(&self) -> Result<Vec<ResponseEntity<Workload>>>;
@@ -30,6 +43,34 @@ pub trait InstanceClient {
     async fn get_instances(&self) -> Result<Vec<ResponseEntity<Instance>>>;
     async fn create_instance(&self, workload_id: &str, replicas: &Option<usize>) -> Result<()>;
     async fn delete_instance(&self, workload_id: &str) -> Result<String>;
+    async fn get_logs(
+        &self,
+        instance_id: &str,
+        container: &Option<String>,
+        tail: Option<usize>,
+        follow: bool,
+    ) -> Result<Response>;
+    async fn exec(
+        &self,
+        instance_id: &str,
+        container: &Option<String>,
+        tty: bool,
+        command: &[String],
+    ) -> Result<Session>;
+    async fn attach(&self, instance_id: &str, container: &Option<String>) -> Result<Session>;
+    async fn open_input(&self, session_id: &str) -> Result<Upgraded>;
+}
+
+#[async_trait]
+pub trait NodeClient {
+    async fn get_nodes(&self) -> Result<Vec<Node>>;
+}
+
+#[async_trait]
+pub trait AdminClient {
+    async fn get_scheduler_workers(&self) -> Result<Vec<SchedulerWorker>>;
+    async fn get_placements(&self) -> Result<Vec<Placement>>;
+    async fn get_scheduler_state(&self) -> Result<Value>;
 }
 
 /// `Client` provides the ability to interact
@@ -57,6 +98,40 @@ impl Client {
     pub fn endpoint(&self, path: &str) -> String {
         format!("{}/{}", self.endpoint, path)
     }
+
+    /// Fetch an endpoint, failing with the body of the response
+    /// when the controller answers with an error.
+    async fn get_text(&self, path: &str) -> Result<String> {
+        let response = self.http_client.get(self.endpoint(path)).send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            bail!("{} ({})", text, status);
+        }
+        Ok(text)
+    }
+
+    /// Open a session `kind` in a container of an instance
+    async fn open_session(&self, kind: &str, query: &[(&str, String)]) -> Result<Session> {
+        let response = self
+            .http_client
+            .post(self.endpoint(&format!("api/v0/instances.{}", kind)))
+            .query(query)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("{} ({})", response.text().await?, status);
+        }
+        let id = match response.headers().get(SESSION_HEADER) {
+            Some(id) => id.to_str()?.to_string(),
+            None => bail!("The controller did not give the id of the session"),
+        };
+        Ok(Session {
+            id,
+            output: response,
+        })
+    }
 }
 
 #[async_trait]
@@ -134,4 +209,109 @@ impl InstanceClient for Client {
         let json: Value = serde_json::from_str(&response.text().await?)?;
         Ok(json.to_string())
     }
+
+    /// Request the logs of an instance, whose body is streamed as the
+    /// riklet sends it when following them
+    async fn get_logs(
+        &self,
+        instance_id: &str,
+        container: &Option<String>,
+        tail: Option<usize>,
+        follow: bool,
+    ) -> Result<Response> {
+        let mut query = vec![
+            ("instance", instance_id.to_string()),
+            ("follow", follow.to_string()),
+        ];
+        if let Some(container) = container {
+            query.push(("container", container.clone()));
+        }
+        if let Some(tail) = tail {
+            query.push(("tail", tail.to_string()));
+        }
+
+        let response = self
+            .http_client
+            .get(self.endpoint("api/v0/instances.logs"))
+            .query(&query)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("{} ({})", response.text().await?, status);
+        }
+        Ok(response)
+    }
+
+    /// Run a command in a container of an instance, with a TTY when `tty`
+    /// is set
+    async fn exec(
+        &self,
+        instance_id: &str,
+        container: &Option<String>,
+        tty: bool,
+        command: &[String],
+    ) -> Result<Session> {
+        let mut query = vec![
+            ("instance", instance_id.to_string()),
+            ("tty", tty.to_string()),
+        ];
+        if let Some(container) = container {
+            query.push(("container", container.clone()));
+        }
+        query.extend(command.iter().map(|arg| ("command", arg.clone())));
+        self.open_session("exec", &query).await
+    }
+
+    /// Attach to the main process of a container of an instance
+    async fn attach(&self, instance_id: &str, container: &Option<String>) -> Result<Session> {
+        let mut query = vec![("instance", instance_id.to_string())];
+        if let Some(container) = container {
+            query.push(("container", container.clone()));
+        }
+        self.open_session("attach", &query).await
+    }
+
+    /// Connection the input of a session is written to
+    async fn open_input(&self, session_id: &str) -> Result<Upgraded> {
+        let response = self
+            .http_client
+            .post(self.endpoint("api/v0/instances.input"))
+            .query(&[("session", session_id)])
+            .header(CONNECTION, "Upgrade")
+            .header(UPGRADE, "tcp")
+            .send()
+            .await?;
+        let status = response.status();
+        if status != reqwest::StatusCode::SWITCHING_PROTOCOLS {
+            bail!("{} ({})", response.text().await?, status);
+        }
+        Ok(response.upgrade().await?)
+    }
+}
+
+#[async_trait]
+impl NodeClient for Client {
+    async fn get_nodes(&self) -> Result<Vec<Node>> {
+        let text = self.get_text("api/v0/workers.list").await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+#[async_trait]
+impl AdminClient for Client {
+    async fn get_scheduler_workers(&self) -> Result<Vec<SchedulerWorker>> {
+        let text = self.get_text("api/v0/admin.workers").await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    async fn get_placements(&self) -> Result<Vec<Placement>> {
+        let text = self.get_text("api/v0/admin.placements").await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    async fn get_scheduler_state(&self) -> Result<Value> {
+        let text = self.get_text("api/v0/admin.state").await?;
+        Ok(serde_json::from_str(&text)?)
+    }
 }
@@ -1,6 +1,51 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlacementPort {
+    pub port: u32,
+    pub target_port: u32,
+    /// TCP when not given
+    #[serde(default)]
+    pub protocol: Option<String>,
+}
+
+/// Where an instance runs and how to reach it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Placement {
+    pub host_ip: String,
+    pub guest_ip: Option<String>,
+    pub ports: Vec<PlacementPort>,
+}
+
+impl Placement {
+    /// Addresses the instance can be reached at from outside its worker
+    pub fn endpoints(&self) -> Vec<String> {
+        self.ports
+            .iter()
+            .map(|port| match port.protocol.as_deref() {
+                Some(protocol) if !protocol.eq_ignore_ascii_case("TCP") => {
+                    format!("{}:{}/{}", self.host_ip, port.port, protocol)
+                }
+                _ => format!("{}:{}", self.host_ip, port.port),
+            })
+            .collect()
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Instance {
     pub status: String,
+    /// Short cause of the current status
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Unix timestamp in milliseconds at which each status was entered
+    #[serde(default)]
+    pub phases: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub placement: Option<Placement>,
 }
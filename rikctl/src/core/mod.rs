@@ -1,4 +1,6 @@
+pub mod admin;
 pub mod client;
 pub mod config;
 pub mod instance;
+pub mod node;
 pub mod workload;
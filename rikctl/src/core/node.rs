@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// A worker of the cluster
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Node {
+    pub id: String,
+    pub address: String,
+    pub state: String,
+    /// Unix timestamp in milliseconds of the last update received
+    pub last_seen: u64,
+    pub metrics: Option<NodeMetrics>,
+    /// Identifiers of the instances running on the node
+    pub instances: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NodeMetrics {
+    pub cpu: CpuMetrics,
+    pub memory: MemoryMetrics,
+    #[serde(default)]
+    pub disks: Vec<DiskMetrics>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CpuMetrics {
+    /// Number of CPU
+    pub total: u8,
+    pub free: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MemoryMetrics {
+    /// Total memory (bytes)
+    pub total: u64,
+    /// Free memory (bytes)
+    pub free: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DiskMetrics {
+    pub disk_name: String,
+    /// Total disk (bytes)
+    pub total: u64,
+    /// Free disk (bytes)
+    pub free: u64,
+}
@@ -1,5 +1,10 @@
 use crate::*;
+use nix::sys::socket::{recvmsg, ControlMessageOwned, MsgFlags};
+use std::fs::File;
+use std::io::IoSliceMut;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 use std::path::{Path, PathBuf};
+use tokio::io::Interest;
 use tokio::net::UnixListener;
 
 /// An implementation of a PTY socket
@@ -25,6 +30,42 @@ impl ConsoleSocket {
     pub fn get_listener(&self) -> &Option<UnixListener> {
         &self.listener
     }
+
+    /// Wait for runc to connect and hand over the master side of the
+    /// container PTY, from which the container output is read.
+    pub async fn receive_pty_master(&self) -> Result<File> {
+        let listener = self.listener.as_ref().ok_or_else(|| {
+            Error::ConsoleSocketError(std::io::Error::from(std::io::ErrorKind::NotConnected))
+        })?;
+        let (stream, _) = listener.accept().await.map_err(Error::ConsoleSocketError)?;
+        let fd = stream
+            .async_io(Interest::READABLE, || receive_fd(stream.as_raw_fd()))
+            .await
+            .map_err(Error::ConsoleSocketError)?;
+        // SAFETY: the descriptor was just received, nothing else owns it
+        Ok(unsafe { File::from_raw_fd(fd) })
+    }
+}
+
+/// Read a message carrying a file descriptor, as sent with SCM_RIGHTS
+fn receive_fd(socket: RawFd) -> std::io::Result<RawFd> {
+    // runc sends the name of the PTY along with its descriptor
+    let mut name = [0u8; 4096];
+    let mut iov = [IoSliceMut::new(&mut name)];
+    let mut control = nix::cmsg_space!(RawFd);
+    let message = recvmsg::<()>(socket, &mut iov, Some(&mut control), MsgFlags::MSG_DONTWAIT)?;
+    message
+        .cmsgs()
+        .find_map(|cmsg| match cmsg {
+            ControlMessageOwned::ScmRights(fds) => fds.first().copied(),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "no file descriptor received on the console socket",
+            )
+        })
 }
 
 /// Implement Drop trait.
@@ -4,7 +4,7 @@ use shared::utils::find_binary;
 use std::path::Path;
 use std::process::Stdio;
 use std::time::Duration;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct RuncConfiguration {
@@ -104,6 +104,34 @@ impl Runc {
         serde_json::from_str(&output).map_err(Error::JsonDeserializationError)
     }
 
+    /// Run a new process in a running container.
+    ///
+    /// The standard streams of runc are piped, and are the ones of the
+    /// process unless a TTY is asked for. The process is not waited for, and
+    /// may last longer than the timeout of the other commands.
+    pub fn exec_process(
+        &self,
+        id: &str,
+        command: &[String],
+        opts: Option<&ExecArgs>,
+    ) -> Result<Child> {
+        event!(Level::DEBUG, "Executing {:?} in container {}", command, id);
+        let mut args = vec![String::from("exec")];
+        Self::append_opts(&mut args, opts.map(|opts| opts as &dyn Args))?;
+        args.push(String::from(id));
+        args.extend(command.iter().cloned());
+
+        let args = self.concat_args(&args)?;
+        Command::new(&self.command)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(Error::ProcessSpawnError)
+    }
+
     /// Delete a container
     pub async fn delete(&self, id: &str, opts: Option<&DeleteArgs>) -> Result<()> {
         event!(Level::DEBUG, "Deleting container {}", id);
@@ -174,7 +202,11 @@ impl Executable for Runc {
         }
 
         if !result.status.success() {
-            return Err(Error::RuncCommandFailedError(stdout, stderr));
+            return Err(Error::RuncCommandFailedError(
+                stdout,
+                stderr,
+                result.status.code(),
+            ));
         }
 
         Ok(stdout)
@@ -228,6 +260,50 @@ impl Args for CreateArgs {
     }
 }
 
+/// runc exec arguments
+#[derive(Debug, Clone, Default)]
+pub struct ExecArgs {
+    /// Allocate a pseudo-TTY, whose master side is sent on the console socket
+    pub tty: bool,
+    pub console_socket: Option<PathBuf>,
+    /// File the pid of the process is written to
+    pub pid_file: Option<PathBuf>,
+    pub detach: bool,
+}
+
+impl Args for ExecArgs {
+    fn args(&self) -> Result<Vec<String>> {
+        let mut args: Vec<String> = Vec::new();
+
+        if self.tty {
+            args.push(String::from("--tty"))
+        }
+
+        if let Some(pid_file) = self.pid_file.clone() {
+            args.push(String::from("--pid-file"));
+            args.push(pid_file.to_string_lossy().parse().unwrap())
+        }
+
+        if let Some(console_socket) = self.console_socket.clone() {
+            args.push(String::from("--console-socket"));
+            args.push(
+                console_socket
+                    .canonicalize()
+                    .map_err(Error::InvalidPathError)?
+                    .to_string_lossy()
+                    .parse()
+                    .unwrap(),
+            )
+        }
+
+        if self.detach {
+            args.push(String::from("--detach"))
+        }
+
+        Ok(args)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct KillArgs {
     /// Send the specified signal to all processes inside the container
@@ -294,7 +370,7 @@ mod tests {
     use std::path::PathBuf;
 
     use crate::console::ConsoleSocket;
-    use crate::container::{CreateArgs, DeleteArgs, Runc, RuncConfiguration};
+    use crate::container::{CreateArgs, DeleteArgs, ExecArgs, Runc, RuncConfiguration};
     use shared::utils::unpack;
     use std::time::Duration;
     use tokio::time::sleep;
@@ -516,4 +592,29 @@ mod tests {
 
         assert_eq!(container_state.status, Some(String::from("stopped")))
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_it_exec_in_a_container() {
+        let (runc_path, runc_root) = setup_test_sequence();
+
+        let container = TestContainer::new(&runc_path, &runc_root, &PathBuf::from(BUSYBOX_ARCHIVE))
+            .await
+            .expect("Unable to create the container");
+
+        let runc = container.runc.unwrap();
+
+        sleep(Duration::from_millis(500)).await;
+
+        let command = vec![String::from("echo"), String::from("hello")];
+        let output = runc
+            .exec_process(&container.id, &command, Some(&ExecArgs::default()))
+            .expect("Unable to exec in the container")
+            .wait_with_output()
+            .await
+            .expect("Unable to wait for the process");
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello\n")
+    }
 }
@@ -19,14 +19,17 @@ pub enum Error {
     ProcessSpawnError(std::io::Error),
     #[error("Runc command timeout {0}")]
     RuncCommandTimeoutError(tokio::time::error::Elapsed),
+    /// Holds the exit code of runc, unless it was killed by a signal
     #[error("Runc command failed, stdout: \"{0}\", stderr: \"{1}\"")]
-    RuncCommandFailedError(String, String),
+    RuncCommandFailedError(String, String, Option<i32>),
     #[error("Runc command error: {0}")]
     RuncCommandError(std::io::Error),
     #[error("Invalid path: {0}")]
     InvalidPathError(std::io::Error),
     #[error("Unable to bind to unix socket: {0}")]
     UnixSocketOpenError(std::io::Error),
+    #[error("Unable to receive the container console: {0}")]
+    ConsoleSocketError(std::io::Error),
     #[error("Json deserialization error: {0}")]
     JsonDeserializationError(serde_json::error::Error),
 }
@@ -0,0 +1,249 @@
+use crate::{Error, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tracing::{event, Level};
+
+/// Name of the runtime configuration in a bundle
+const CONFIG_FILE: &str = "config.json";
+
+/// Runtime configuration of an OCI bundle, its `config.json`.
+///
+/// Only the parts riklet changes are known, the rest of the configuration is
+/// kept as it was read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleConfig(Value);
+
+impl BundleConfig {
+    /// Read the configuration of the bundle at `bundle`
+    pub fn read(bundle: &Path) -> Result<Self> {
+        let path = bundle.join(CONFIG_FILE);
+        event!(
+            Level::DEBUG,
+            "Reading bundle configuration {}",
+            path.display()
+        );
+        let content = std::fs::read_to_string(&path).map_err(Error::BundleConfigError)?;
+        serde_json::from_str(&content)
+            .map(BundleConfig)
+            .map_err(Error::BundleConfigParseError)
+    }
+
+    /// Write the configuration in the bundle at `bundle`, created if needed
+    pub fn write(&self, bundle: &Path) -> Result<()> {
+        std::fs::create_dir_all(bundle).map_err(Error::BundleConfigError)?;
+        let content =
+            serde_json::to_string_pretty(&self.0).map_err(Error::BundleConfigParseError)?;
+        std::fs::write(bundle.join(CONFIG_FILE), content).map_err(Error::BundleConfigError)
+    }
+
+    /// Root filesystem of the container, as written in the configuration
+    pub fn root(&self) -> Option<PathBuf> {
+        self.0["root"]["path"].as_str().map(PathBuf::from)
+    }
+
+    /// Use the root filesystem at `path`
+    pub fn set_root(&mut self, path: &Path) {
+        self.0["root"]["path"] = json!(path);
+    }
+
+    /// Make the root filesystem absolute, resolving it from `bundle`, so the
+    /// configuration can be used from another bundle
+    pub fn resolve_root(&mut self, bundle: &Path) {
+        if let Some(root) = self.root().filter(|root| root.is_relative()) {
+            self.set_root(&bundle.join(root));
+        }
+    }
+
+    /// Run `args` as the process of the container, the executable first
+    pub fn set_args(&mut self, args: &[String]) {
+        self.0["process"]["args"] = json!(args);
+    }
+
+    /// Set the variables of `env` in the environment of the process, in
+    /// place of the ones of the image with the same names
+    pub fn merge_env(&mut self, env: &[(String, String)]) {
+        let variables = array_mut(&mut self.0["process"]["env"]);
+        for (name, value) in env {
+            let prefix = format!("{}=", name);
+            variables.retain(|variable| match variable.as_str() {
+                Some(variable) => !variable.starts_with(&prefix),
+                None => true,
+            });
+            variables.push(json!(format!("{}{}", prefix, value)));
+        }
+    }
+
+    /// Run the process in `cwd`, an absolute path of the container
+    pub fn set_cwd(&mut self, cwd: &str) {
+        self.0["process"]["cwd"] = json!(cwd);
+    }
+
+    /// Run the process as `uid`, and `gid` when given instead of the group
+    /// of the image
+    pub fn set_user(&mut self, uid: u32, gid: Option<u32>) {
+        self.0["process"]["user"]["uid"] = json!(uid);
+        if let Some(gid) = gid {
+            self.0["process"]["user"]["gid"] = json!(gid);
+        }
+    }
+
+    /// Name the container `hostname`
+    pub fn set_hostname(&mut self, hostname: &str) {
+        self.0["hostname"] = json!(hostname);
+    }
+
+    /// Join the namespace of `kind`, e.g. `network`, at `path` instead of
+    /// creating a new one
+    pub fn set_namespace_path(&mut self, kind: &str, path: &Path) {
+        let namespaces = array_mut(&mut self.0["linux"]["namespaces"]);
+        namespaces.retain(|namespace| namespace["type"] != kind);
+        namespaces.push(json!({ "type": kind, "path": path }));
+    }
+
+    /// Bind mount `source` of the host at `destination` in the container
+    pub fn add_bind_mount(&mut self, source: &Path, destination: &str, read_only: bool) {
+        let mut options = vec!["rbind", "rprivate"];
+        if read_only {
+            options.push("ro");
+        }
+        let mounts = array_mut(&mut self.0["mounts"]);
+        mounts.retain(|mount| mount["destination"] != destination);
+        mounts.push(json!({
+            "destination": destination,
+            "type": "bind",
+            "source": source,
+            "options": options,
+        }));
+    }
+}
+
+/// Array at `value`, which is replaced by an empty one if it is not an array
+fn array_mut(value: &mut Value) -> &mut Vec<Value> {
+    if !value.is_array() {
+        *value = json!([]);
+    }
+    match value {
+        Value::Array(array) => array,
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BundleConfig {
+        BundleConfig(json!({
+            "ociVersion": "1.0.2",
+            "process": {
+                "user": { "uid": 0, "gid": 0 },
+                "args": ["/bin/sh"],
+                "env": ["PATH=/usr/bin:/bin", "TERM=xterm"],
+                "cwd": "/"
+            },
+            "root": { "path": "rootfs" },
+            "mounts": [
+                { "destination": "/proc", "type": "proc", "source": "proc" },
+                { "destination": "/etc/resolv.conf", "type": "bind", "source": "/tmp/resolv.conf" }
+            ],
+            "linux": {
+                "namespaces": [{ "type": "pid" }, { "type": "network" }]
+            }
+        }))
+    }
+
+    #[test]
+    fn test_resolve_root() {
+        let mut config = config();
+        config.resolve_root(Path::new("/var/lib/riklet/bundles/alpine"));
+        assert_eq!(
+            config.root(),
+            Some(PathBuf::from("/var/lib/riklet/bundles/alpine/rootfs"))
+        );
+
+        config.resolve_root(Path::new("/elsewhere"));
+        assert_eq!(
+            config.root(),
+            Some(PathBuf::from("/var/lib/riklet/bundles/alpine/rootfs"))
+        );
+    }
+
+    #[test]
+    fn test_process() {
+        let mut config = config();
+        config.set_args(&["nginx".to_string(), "-g".to_string()]);
+        config.merge_env(&[
+            ("PATH".to_string(), "/usr/local/bin".to_string()),
+            ("KEY".to_string(), "a=b".to_string()),
+        ]);
+        config.set_cwd("/srv");
+        config.set_user(1000, None);
+        config.set_hostname("instance-1");
+        assert_eq!(
+            config.0["process"],
+            json!({
+                "user": { "uid": 1000, "gid": 0 },
+                "args": ["nginx", "-g"],
+                "env": ["TERM=xterm", "PATH=/usr/local/bin", "KEY=a=b"],
+                "cwd": "/srv"
+            })
+        );
+        assert_eq!(config.0["hostname"], "instance-1");
+
+        config.set_user(1000, Some(100));
+        assert_eq!(
+            config.0["process"]["user"],
+            json!({ "uid": 1000, "gid": 100 })
+        );
+
+        let mut config = BundleConfig(json!({}));
+        config.merge_env(&[("KEY".to_string(), "value".to_string())]);
+        assert_eq!(config.0["process"]["env"], json!(["KEY=value"]));
+    }
+
+    #[test]
+    fn test_set_namespace_path() {
+        let mut config = config();
+        config.set_namespace_path("network", Path::new("/var/run/netns/pod"));
+        assert_eq!(
+            config.0["linux"]["namespaces"],
+            json!([{ "type": "pid" }, { "type": "network", "path": "/var/run/netns/pod" }])
+        );
+
+        let mut config = BundleConfig(json!({}));
+        config.set_namespace_path("network", Path::new("/var/run/netns/pod"));
+        assert_eq!(
+            config.0["linux"]["namespaces"],
+            json!([{ "type": "network", "path": "/var/run/netns/pod" }])
+        );
+    }
+
+    #[test]
+    fn test_add_bind_mount() {
+        let mut config = config();
+        config.add_bind_mount(Path::new("/etc/resolv.conf"), "/etc/resolv.conf", true);
+        assert_eq!(
+            config.0["mounts"],
+            json!([
+                { "destination": "/proc", "type": "proc", "source": "proc" },
+                {
+                    "destination": "/etc/resolv.conf",
+                    "type": "bind",
+                    "source": "/etc/resolv.conf",
+                    "options": ["rbind", "rprivate", "ro"]
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_write_read() {
+        let bundle = std::env::temp_dir().join(format!("bundle-{}", std::process::id()));
+        let config = config();
+        config.write(&bundle).unwrap();
+        assert_eq!(BundleConfig::read(&bundle).unwrap(), config);
+        std::fs::remove_dir_all(&bundle).unwrap();
+
+        assert!(BundleConfig::read(&bundle).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::image::Image;
+use crate::layout;
 use crate::skopeo::{Skopeo, SkopeoConfiguration};
 use crate::umoci::{Umoci, UmociConfiguration, UnpackArgs};
 use crate::*;
@@ -97,4 +98,11 @@ impl ImageManager {
 
         Ok(image)
     }
+
+    /// Entrypoint of a pulled image, the process of its bundle being both
+    /// its entrypoint and its command
+    pub fn entrypoint(&self, image_str: &str) -> Result<Vec<String>> {
+        let image = Image::from(image_str);
+        layout::entrypoint(&self.skopeo.layout_path(&image.get_uuid()), &image.tag)
+    }
 }
@@ -0,0 +1,99 @@
+use crate::{Error, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tracing::{event, Level};
+
+/// Annotation of the index naming the tag of a manifest
+const REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+
+fn read_json(path: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(path).map_err(Error::ImageLayoutError)?;
+    serde_json::from_str(&content).map_err(Error::ImageLayoutParseError)
+}
+
+/// Path of the blob of `digest`, e.g. `sha256:<hex>`, in the layout
+fn blob_path(layout: &Path, digest: &Value) -> Result<PathBuf> {
+    digest
+        .as_str()
+        .and_then(|digest| digest.split_once(':'))
+        .map(|(algorithm, hex)| layout.join("blobs").join(algorithm).join(hex))
+        .ok_or_else(|| Error::InvalidImageLayout(format!("invalid digest {}", digest)))
+}
+
+/// Entrypoint of the image tagged `tag` in the OCI image layout at `layout`,
+/// as skopeo copies it. It is empty when the image has none.
+pub fn entrypoint(layout: &Path, tag: &str) -> Result<Vec<String>> {
+    event!(
+        Level::DEBUG,
+        "Reading entrypoint of {}:{}",
+        layout.display(),
+        tag
+    );
+    let index = read_json(&layout.join("index.json"))?;
+    let manifest = index["manifests"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|manifest| manifest["annotations"][REF_NAME_ANNOTATION] == tag)
+        .ok_or_else(|| Error::InvalidImageLayout(format!("no image tagged {}", tag)))?;
+    let manifest = read_json(&blob_path(layout, &manifest["digest"])?)?;
+    let config = read_json(&blob_path(layout, &manifest["config"]["digest"])?)?;
+
+    Ok(config["config"]["Entrypoint"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|arg| arg.as_str().map(String::from))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_blob(layout: &Path, hex: &str, content: Value) {
+        let path = layout.join("blobs").join("sha256");
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join(hex), content.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_entrypoint() {
+        let layout = std::env::temp_dir().join(format!("layout-{}", std::process::id()));
+        std::fs::create_dir_all(&layout).unwrap();
+        std::fs::write(
+            layout.join("index.json"),
+            json!({
+                "schemaVersion": 2,
+                "manifests": [{
+                    "digest": "sha256:aaaa",
+                    "annotations": { REF_NAME_ANNOTATION: "latest" }
+                }]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        write_blob(
+            &layout,
+            "aaaa",
+            json!({ "config": { "digest": "sha256:bbbb" } }),
+        );
+        write_blob(
+            &layout,
+            "bbbb",
+            json!({ "config": { "Entrypoint": ["/docker-entrypoint.sh"], "Cmd": ["nginx"] } }),
+        );
+
+        assert_eq!(
+            entrypoint(&layout, "latest").unwrap(),
+            vec!["/docker-entrypoint.sh".to_string()]
+        );
+        assert!(entrypoint(&layout, "1.25").is_err());
+
+        write_blob(&layout, "bbbb", json!({ "config": { "Cmd": ["sh"] } }));
+        assert!(entrypoint(&layout, "latest").unwrap().is_empty());
+
+        std::fs::remove_dir_all(&layout).unwrap();
+    }
+}
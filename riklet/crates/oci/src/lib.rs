@@ -1,7 +1,9 @@
 use async_trait::async_trait;
 
+pub mod bundle;
 pub mod image;
 pub mod image_manager;
+pub mod layout;
 pub mod skopeo;
 pub mod umoci;
 use thiserror::Error;
@@ -30,6 +32,16 @@ pub enum Error {
     SkopeoCommandError(std::io::Error),
     #[error("Invalid path: {0}")]
     InvalidPathError(std::io::Error),
+    #[error("Bundle configuration error: {0}")]
+    BundleConfigError(std::io::Error),
+    #[error("Invalid bundle configuration: {0}")]
+    BundleConfigParseError(serde_json::Error),
+    #[error("Image layout error: {0}")]
+    ImageLayoutError(std::io::Error),
+    #[error("Invalid image layout: {0}")]
+    ImageLayoutParseError(serde_json::Error),
+    #[error("Invalid image layout: {0}")]
+    InvalidImageLayout(String),
 }
 
 trait Args {
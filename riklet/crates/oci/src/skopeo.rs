@@ -73,6 +73,11 @@ impl Skopeo {
         })
     }
 
+    /// Layout an image is copied to, by its uuid
+    pub fn layout_path(&self, uuid: &str) -> PathBuf {
+        self.images_directory.join(uuid)
+    }
+
     fn get_pull_path(&self, directory: &str) -> String {
         format!(
             "oci:{}/{}",
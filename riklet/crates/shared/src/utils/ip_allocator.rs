@@ -8,15 +8,32 @@ pub struct IpAllocator {
 
 impl IpAllocator {
     pub fn new() -> Result<IpAllocator, IpNetworkError> {
+        let network = Ipv4Network::new(Ipv4Addr::new(192, 168, 1, 0), 24)?;
+        IpAllocator::with_subnets(network, 30)
+    }
+
+    /// Allocator of the subnets of `network` with the given `prefix`, e.g.
+    /// single addresses with a prefix of 32
+    pub fn with_subnets(network: Ipv4Network, prefix: u8) -> Result<IpAllocator, IpNetworkError> {
+        if prefix < network.prefix() || prefix > 32 {
+            return Err(IpNetworkError::InvalidPrefix);
+        }
         let mut subnet_pool: HashMap<Ipv4Network, bool> = HashMap::new();
-        let network = Ipv4Network::new(Ipv4Addr::new(192, 168, 1, 0), 24).unwrap();
-        for ip in network.iter().step_by(4) {
-            let subnet = Ipv4Network::new(ip, 30)?;
+        let step = 1usize << (32 - prefix);
+        for ip in network.iter().step_by(step) {
+            let subnet = Ipv4Network::new(ip, prefix)?;
             subnet_pool.insert(subnet, true);
         }
         Ok(IpAllocator { subnet_pool })
     }
 
+    /// Prevent a subnet from being allocated, e.g. one used by the host
+    pub fn reserve(&mut self, subnet: Ipv4Network) {
+        if let Some(available) = self.subnet_pool.get_mut(&subnet) {
+            *available = false;
+        }
+    }
+
     pub fn allocate_subnet(&mut self) -> Option<Ipv4Network> {
         for (subnet, available) in self.subnet_pool.iter_mut() {
             if *available {
@@ -37,3 +54,33 @@ impl IpAllocator {
         self.subnet_pool.iter().filter(|subnet| *subnet.1).count()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_subnets() {
+        let network = Ipv4Network::new(Ipv4Addr::new(10, 42, 0, 0), 30).unwrap();
+        let mut allocator = IpAllocator::with_subnets(network, 32).unwrap();
+        assert_eq!(allocator.available(), 4);
+
+        allocator.reserve(Ipv4Network::new(Ipv4Addr::new(10, 42, 0, 0), 32).unwrap());
+        allocator.reserve(Ipv4Network::new(Ipv4Addr::new(10, 42, 0, 1), 32).unwrap());
+        allocator.reserve(Ipv4Network::new(Ipv4Addr::new(10, 42, 0, 3), 32).unwrap());
+        let subnet = allocator.allocate_subnet().unwrap();
+        assert_eq!(subnet.ip(), Ipv4Addr::new(10, 42, 0, 2));
+        assert_eq!(subnet.prefix(), 32);
+        assert_eq!(allocator.allocate_subnet(), None);
+
+        allocator.free_subnet(subnet);
+        assert_eq!(allocator.available(), 1);
+    }
+
+    #[test]
+    fn test_with_subnets_larger_prefix() {
+        let network = Ipv4Network::new(Ipv4Addr::new(10, 42, 0, 0), 24).unwrap();
+        assert!(IpAllocator::with_subnets(network, 16).is_err());
+        assert_eq!(IpAllocator::new().unwrap().available(), 64);
+    }
+}
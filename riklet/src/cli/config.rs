@@ -1,5 +1,5 @@
-use clap::Parser;
 use cri::container::RuncConfiguration;
+use ipnetwork::Ipv4Network;
 use oci::image_manager::ImageManagerConfiguration;
 use oci::skopeo::SkopeoConfiguration;
 use oci::umoci::UmociConfiguration;
@@ -11,7 +11,7 @@ use std::time::Duration;
 use thiserror::Error;
 
 use super::CliConfiguration;
-use crate::constants::DEFAULT_COMMAND_TIMEOUT;
+use crate::constants::{DEFAULT_COMMAND_TIMEOUT, DEFAULT_POD_BRIDGE, DEFAULT_POD_CIDR};
 use tracing::{event, Level};
 
 #[derive(Debug, Error)]
@@ -38,13 +38,31 @@ pub struct Configuration {
     pub log_level: String,
     pub runner: RuncConfiguration,
     pub manager: ImageManagerConfiguration,
+    #[serde(default)]
+    pub pod_network: PodNetworkConfiguration,
 }
 
-impl Configuration {
-    fn get_cli_args() -> CliConfiguration {
-        CliConfiguration::parse()
+/// Network the pods of the node are connected to
+#[derive(Deserialize, Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct PodNetworkConfiguration {
+    /// Addresses of the pods, the first one being given to the bridge
+    pub cidr: Ipv4Network,
+    /// Name of the bridge the pods are connected to
+    pub bridge: String,
+}
+
+impl Default for PodNetworkConfiguration {
+    fn default() -> Self {
+        Self {
+            cidr: DEFAULT_POD_CIDR
+                .parse()
+                .expect("Default pod CIDR should be valid"),
+            bridge: String::from(DEFAULT_POD_BRIDGE),
+        }
     }
+}
 
+impl Configuration {
     /// Create the configuration file and store the default config into it
     fn create(path: &Path, configuration: &Configuration) -> Result<()> {
         event!(Level::INFO, "No configuration file found at {}. Creating a new configuration file with the default configuration.", path.display());
@@ -73,21 +91,20 @@ impl Configuration {
 
     /// Load the configuration file
     /// If not exists, create it and return the default configuration
-    pub fn load() -> Result<Self> {
+    pub fn load(opts: &CliConfiguration) -> Result<Self> {
         event!(Level::DEBUG, "Loading configuration");
-        let opts = Configuration::get_cli_args();
 
         let path = PathBuf::from(&opts.config_file);
 
         let mut configuration = Configuration::default();
 
         if !path.exists() {
-            configuration.override_config(&opts);
+            configuration.override_config(opts);
             Configuration::create(&path, &configuration)?;
         } else {
             configuration = Configuration::read(&path)?;
             if opts.override_config {
-                configuration.override_config(&opts);
+                configuration.override_config(opts);
             }
         };
 
@@ -155,6 +172,7 @@ impl Default for Configuration {
                     ..Default::default()
                 },
             },
+            pod_network: PodNetworkConfiguration::default(),
         }
     }
 }
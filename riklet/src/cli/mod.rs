@@ -2,7 +2,10 @@ pub mod config;
 pub mod function_config;
 
 use clap::{value_parser, Parser};
-use std::{net::Ipv4Addr, path::PathBuf};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
 
 /// The configuration of the riklet.
 #[derive(Debug, Clone, Parser)]
@@ -39,4 +42,24 @@ pub struct CliConfiguration {
         value_parser = value_parser!(Ipv4Addr)
     )]
     pub iface_ip: Option<Ipv4Addr>,
+    /// Address on which the metrics and health endpoints listen
+    #[arg(
+        long,
+        value_name = "METRICS_ADDRESS",
+        env = "METRICS_ADDRESS",
+        default_value = "0.0.0.0:4998"
+    )]
+    pub metrics_address: SocketAddr,
+    /// Address on which the logs and sessions of the instances are served, the one the controller dials. By default, the
+    /// address the riklet reaches the scheduler from, on port 4999.
+    #[arg(long, value_name = "SESSIONS_ADDRESS", env = "SESSIONS_ADDRESS")]
+    pub sessions_address: Option<SocketAddr>,
+    /// Token the controller presents to read logs and open sessions, they are disabled when unset
+    #[arg(
+        long,
+        value_name = "SESSIONS_TOKEN",
+        env = "SESSIONS_TOKEN",
+        hide_env_values = true
+    )]
+    pub sessions_token: Option<String>,
 }
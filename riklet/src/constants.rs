@@ -1,7 +1,28 @@
 pub const DEFAULT_COMMAND_TIMEOUT: u64 = 30000;
 
+/// Seconds the containers of a pod are given to stop before being killed,
+/// unless its workload sets `terminationGracePeriodSeconds`
+pub const DEFAULT_TERMINATION_GRACE_PERIOD: u64 = 30;
+
 /// A path to a directory which will contain the firecracker VMs
 pub const DEFAULT_FIRECRACKER_WORKSPACE: &str = "/var/lib/riklet/vm";
 
+/// A path to a directory which will contain the emptyDir volumes of the pods
+pub const DEFAULT_VOLUMES_DIRECTORY: &str = "/var/lib/riklet/volumes";
+
 /// IPv4 adresse mask that is used to configure IP address for the guest VM and host interface
 pub const DEFAULT_FIRECRACKER_NETWORK_MASK: u8 = 30;
+
+/// Addresses given to the pods of a node, the first one being the one of the bridge
+pub const DEFAULT_POD_CIDR: &str = "10.42.0.0/24";
+
+/// Port the logs and sessions of the instances are served on, the one the
+/// controller dials unless told otherwise
+pub const DEFAULT_SESSIONS_PORT: u16 = 4999;
+
+/// Name of the bridge the pods of a node are connected to
+pub const DEFAULT_POD_BRIDGE: &str = "rik0";
+
+/// Ports of the node given to the container ports of type NodePort, the
+/// first free one when they don't ask for one
+pub const DEFAULT_NODE_PORT_RANGE: std::ops::Range<u16> = definition::workload::NODE_PORT_RANGE;
@@ -1,27 +1,50 @@
 use crate::banner;
 use crate::cli::config::{Configuration, ConfigurationError};
 use crate::cli::function_config::FnConfiguration;
+use crate::cli::CliConfiguration;
+use crate::constants::DEFAULT_SESSIONS_PORT;
 use crate::emitters::metrics_emitter::MetricsEmitter;
+use crate::exec;
+use crate::health;
+use crate::http;
+use crate::logs;
+use crate::metrics::RUNTIMES;
+use crate::net_utils;
+use crate::runtime::capabilities;
 use crate::runtime::network::{GlobalRuntimeNetwork, NetworkError, RuntimeNetwork};
+use crate::runtime::orphans;
+use crate::runtime::supervisor::{self, describe_exit, Report};
 use crate::runtime::{DynamicRuntimeManager, Runtime, RuntimeConfigurator, RuntimeError};
-use crate::structs::{EventEmitter, WorkloadDefinition};
+use crate::structs::{EventEmitter, PortMapping, WorkloadDefinition};
+use definition::workload::{is_dns_label, WorkloadKind};
 use definition::InstanceStatus;
-use proto::common::WorkerRegistration;
+use proto::common::worker_status::Status;
+use proto::common::{
+    ClusterEvent, InstanceDetails, InstancePlacement, InstancePort, ResourceStatus,
+    WorkerCapabilities, WorkerInstance, WorkerRegistration,
+};
 use proto::worker::worker_client::WorkerClient;
 use proto::worker::InstanceScheduling;
+use proto::ConversionError;
 use proto::{WorkerStatus, WorkloadAction};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::time::Duration;
 
 use thiserror::Error;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use tonic::{transport::Channel, Request, Streaming};
-use tracing::{debug, error, event, info, Level};
+use tracing::{debug, error, event, info, warn, Level, Span};
 
 const METRICS_UPDATER_INTERVAL: u64 = 15 * 1000;
+/// Delay between two registration attempts when the scheduler is unreachable
+const REGISTRATION_RETRY_INTERVAL: u64 = 5 * 1000;
 
 #[derive(Error, Debug)]
 pub enum RikletError {
     #[error("Failed to parse workload definition: {0}")]
-    WorkloadParseError(serde_json::Error),
+    WorkloadParseError(ConversionError),
 
     #[error("Message status error: {0}")]
     MessageStatusError(tonic::Status),
@@ -43,6 +66,15 @@ pub enum RikletError {
 }
 type Result<T> = std::result::Result<T, RikletError>;
 
+/// Outcome of the teardown of an instance, done apart from the main loop
+/// since it may wait for the grace period of its containers
+struct Deletion {
+    instance_id: String,
+    /// Kept so the deletion can be tried again when it failed
+    runtime: Box<dyn Runtime>,
+    result: std::result::Result<(), RuntimeError>,
+}
+
 pub struct Riklet {
     config: Configuration,
     hostname: String,
@@ -51,6 +83,19 @@ pub struct Riklet {
     // Can be pod or function runtimes
     // The key is the instance id
     runtimes: HashMap<String, Box<dyn Runtime>>,
+    /// Instances currently running on this node, reported to the scheduler
+    /// on each registration so it can reconcile its state.
+    /// The key is the instance id
+    inventory: HashMap<String, WorkerInstance>,
+    /// What this node is able to run, probed at startup
+    capabilities: WorkerCapabilities,
+    /// What happens to the containers of the pods, told by their supervisors
+    reports: UnboundedReceiver<Report>,
+    /// Instances being torn down, whose runtime is out of `runtimes` until
+    /// their deletion is over
+    deleting: HashSet<String>,
+    deletions_sender: UnboundedSender<Deletion>,
+    deletions: UnboundedReceiver<Deletion>,
     /// Holds the global network configuration which includes basic iptables
     /// rules and chains used by all workloads
     ///
@@ -61,23 +106,26 @@ pub struct Riklet {
 }
 
 impl Riklet {
+    #[tracing::instrument(skip_all, fields(instance_id = %workload.instance_id))]
     async fn handle_workload(&mut self, workload: &InstanceScheduling) -> Result<()> {
+        telemetry::set_parent(&Span::current(), &workload.trace_context);
         info!(
             "Instance scheduling received for instance: {}",
             &workload.instance_id
         );
-        let workload_definition: WorkloadDefinition =
-            serde_json::from_str(workload.definition.as_str())
-                .map_err(RikletError::WorkloadParseError)?;
-
-        let dynamic_runtime_manager: DynamicRuntimeManager =
-            RuntimeConfigurator::create(&workload_definition);
-
         match &workload.action.into() {
             WorkloadAction::CREATE => {
-                self.create_workload(workload, dynamic_runtime_manager)
+                let workload_definition = WorkloadDefinition::try_from(workload)
+                    .map_err(RikletError::WorkloadParseError)?;
+
+                let dynamic_runtime_manager: DynamicRuntimeManager =
+                    RuntimeConfigurator::create(&workload_definition);
+
+                self.create_workload(workload, &workload_definition, dynamic_runtime_manager)
                     .await?
             }
+            // Destroy requests may come without definition, e.g. when the
+            // scheduler asks to remove an orphan instance after reconciliation
             WorkloadAction::DELETE => self.delete_workload(workload).await?,
         };
 
@@ -87,9 +135,17 @@ impl Riklet {
     async fn create_workload(
         &mut self,
         workload: &InstanceScheduling,
+        workload_definition: &WorkloadDefinition,
         dynamic_runtime_manager: DynamicRuntimeManager<'_>,
     ) -> Result<()> {
         let instance_id: &String = &workload.instance_id;
+        Self::check_instance_id(instance_id)?;
+        if self.deleting.contains(instance_id) || self.runtimes.contains_key(instance_id) {
+            return Err(RikletError::InvalidInput(format!(
+                "instance {} already exists",
+                instance_id
+            )));
+        }
         self.send_status(InstanceStatus::Creating, instance_id)
             .await?;
 
@@ -98,18 +154,31 @@ impl Riklet {
             .await
         {
             Err(e) => {
-                self.send_status(InstanceStatus::Failed, instance_id)
+                self.send_failure(instance_id, &e)
                     .await
                     .unwrap_or_else(|e| {
                         error!("Error while sending status: {}", e);
                     });
+                self.send_event(instance_id, "Failed", e.to_string()).await;
                 return Err(RikletError::RuntimeManagerError(e));
             }
             Ok(runtime) => {
+                let entry =
+                    Self::inventory_entry(instance_id, workload_definition, runtime.port_mapping());
+                RUNTIMES
+                    .with_label_values(&[&Self::runtime_kind(&entry)])
+                    .inc();
+                let placement = InstancePlacement {
+                    host_ip: String::new(),
+                    guest_ip: runtime.guest_ip().map(|ip| ip.to_string()),
+                    ports: entry.ports.clone(),
+                };
                 self.runtimes.insert(instance_id.clone(), runtime);
+                self.inventory.insert(instance_id.clone(), entry);
 
-                self.send_status(InstanceStatus::Running, instance_id)
-                    .await?;
+                self.send_running(instance_id, placement).await?;
+                self.send_event(instance_id, "Started", "Instance is running".to_string())
+                    .await;
             }
         }
         Ok(())
@@ -118,35 +187,298 @@ impl Riklet {
     /// Deletes an instance and its runtime
     ///
     /// Expected lifecycle is:
-    /// Receive delete request -> Send destroying status
-    /// -> Destroy instance & Unregister runtime -> Send terminated status
+    /// Receive delete request -> Destroy instance in its own task
+    /// -> Unregister runtime & Send terminated status once it is over
     #[tracing::instrument(skip_all, fields(instance_id = %workload.instance_id))]
     async fn delete_workload(&mut self, workload: &InstanceScheduling) -> Result<()> {
         debug!("Delete workload");
         let instance_id: &String = &workload.instance_id;
+        Self::check_instance_id(instance_id)?;
 
-        let instance = self
-            .runtimes
-            .get_mut(instance_id)
-            .ok_or_else(|| RikletError::InvalidInput(instance_id.clone()))?;
+        if self.deleting.contains(instance_id) {
+            debug!("Instance {} is already being deleted", instance_id);
+            return Ok(());
+        }
+        let mut runtime = match self.runtimes.remove(instance_id) {
+            Some(runtime) => runtime,
+            None => {
+                // Nothing runs anymore, e.g. the instance failed to start
+                logs::remove(instance_id);
+                exec::unregister(instance_id);
+                return Err(RikletError::InvalidInput(instance_id.clone()));
+            }
+        };
 
-        instance
-            .down()
-            .await
-            .map_err(RikletError::RuntimeManagerError)?;
+        self.deleting.insert(instance_id.clone());
+        let sender = self.deletions_sender.clone();
+        let instance_id = instance_id.clone();
+        tokio::spawn(async move {
+            let result = runtime.down().await;
+            let _ = sender.send(Deletion {
+                instance_id,
+                runtime,
+                result,
+            });
+        });
+        Ok(())
+    }
+
+    /// Unregister an instance once its runtime is torn down, or keep it
+    /// around so its deletion can be asked again
+    async fn handle_deletion(&mut self, deletion: Deletion) {
+        let instance_id = &deletion.instance_id;
+        self.deleting.remove(instance_id);
+
+        if let Err(e) = deletion.result {
+            error!("Could not delete instance {}: {}", instance_id, e);
+            self.send_event(instance_id, "FailedStop", e.to_string())
+                .await;
+            self.runtimes.insert(instance_id.clone(), deletion.runtime);
+            return;
+        }
 
         self.send_status(InstanceStatus::Terminated, instance_id)
-            .await?;
+            .await
+            .unwrap_or_else(|e| error!("Error while sending status: {}", e));
+        self.send_event(instance_id, "Stopped", "Instance is stopped".to_string())
+            .await;
+
+        logs::remove(instance_id);
+        exec::unregister(instance_id);
+        if let Some(entry) = self.inventory.remove(instance_id) {
+            RUNTIMES
+                .with_label_values(&[&Self::runtime_kind(&entry)])
+                .dec();
+        }
+    }
 
-        self.runtimes.remove(instance_id);
+    /// Serve the logs and sessions of the instances to the controller sharing
+    /// a token with the riklet. They are served on the address the riklet
+    /// reaches the scheduler from unless told otherwise, since it is the
+    /// address the scheduler reports to the controller.
+    fn serve_sessions(cli: &CliConfiguration, master_url: &str) {
+        let token = match cli.sessions_token.clone().filter(|token| !token.is_empty()) {
+            Some(token) => token,
+            None => {
+                warn!("No sessions token given, logs and sessions of the instances are disabled.");
+                return;
+            }
+        };
+        let address = match cli.sessions_address {
+            Some(address) => address,
+            None => match net_utils::source_ip(master_url) {
+                Ok(ip) => SocketAddr::new(ip, DEFAULT_SESSIONS_PORT),
+                Err(e) => {
+                    error!(
+                        "Logs and sessions of the instances are disabled, no address to serve them on: {}",
+                        e
+                    );
+                    return;
+                }
+            },
+        };
+        tokio::spawn(http::serve_sessions(address, token));
+    }
+
+    /// Refuse the ids which can't name the files and directories of an
+    /// instance, before any path is built from them
+    fn check_instance_id(instance_id: &str) -> Result<()> {
+        if !is_dns_label(instance_id) {
+            return Err(RikletError::InvalidInput(format!(
+                "invalid instance id {}",
+                instance_id
+            )));
+        }
         Ok(())
     }
 
-    #[tracing::instrument(skip(self), fields(instance_id = %instance_id, status = %status))]
+    /// Label of the kind of runtime backing an instance
+    fn runtime_kind(entry: &WorkerInstance) -> String {
+        entry.kind().as_str_name().to_lowercase()
+    }
+
+    /// Describe a running instance so it can be reported to the scheduler
+    fn inventory_entry(
+        instance_id: &str,
+        workload_definition: &WorkloadDefinition,
+        port_mapping: Vec<PortMapping>,
+    ) -> WorkerInstance {
+        let kind: proto::common::WorkloadKind =
+            WorkloadKind::from(workload_definition.kind.clone()).into();
+        WorkerInstance {
+            instance_id: instance_id.to_string(),
+            kind: kind.into(),
+            status: ResourceStatus::Running.into(),
+            ports: port_mapping
+                .into_iter()
+                .map(|mapping| InstancePort {
+                    port: mapping.host_port.into(),
+                    target_port: mapping.target_port.into(),
+                    protocol: proto::common::Protocol::from(mapping.protocol).into(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Registration message of this node, with the instances it is already
+    /// running and what it is able to run
+    fn registration(&self) -> WorkerRegistration {
+        WorkerRegistration {
+            hostname: self.hostname.clone(),
+            instances: self.inventory.values().cloned().collect(),
+            capabilities: Some(self.capabilities.clone()),
+        }
+    }
+
+    /// Register this node to the scheduler and return the stream of
+    /// scheduling requests
+    async fn register(
+        client: &mut WorkerClient<Channel>,
+        registration: WorkerRegistration,
+    ) -> Result<Streaming<InstanceScheduling>> {
+        event!(Level::DEBUG, "Node's registration to the master");
+        let request = Request::new(registration);
+        let stream = client
+            .register(request)
+            .await
+            .map_err(RikletError::MessageStatusError)?
+            .into_inner();
+        Ok(stream)
+    }
+
+    /// Register again until the scheduler accepts the node
+    async fn reconnect(&mut self) {
+        loop {
+            tokio::time::sleep(Duration::from_millis(REGISTRATION_RETRY_INTERVAL)).await;
+            let registration = self.registration();
+            match Self::register(&mut self.client, registration).await {
+                Ok(stream) => {
+                    info!(
+                        "Registered again to the scheduler with {} instance(s)",
+                        self.inventory.len()
+                    );
+                    self.stream = stream;
+                    health::REGISTRATION.set(true);
+                    return;
+                }
+                Err(e) => warn!("Failed to register to the scheduler: {}", e),
+            }
+        }
+    }
+
+    /// Tell the scheduler and users what happened to the containers of an
+    /// instance
+    async fn handle_report(&mut self, report: Report) {
+        match report {
+            Report::Exited {
+                instance_id,
+                container,
+                code,
+                restarting,
+            } => {
+                let mut message = format!("Container {} exited {}", container, describe_exit(code));
+                if restarting {
+                    message.push_str(", it will be restarted");
+                }
+                self.send_event(&instance_id, "ContainerExited", message)
+                    .await;
+            }
+            Report::Restarted {
+                instance_id,
+                container,
+                restarts,
+            } => {
+                let message = format!("Container {} restarted {} time(s)", container, restarts);
+                self.send_event(&instance_id, "ContainerRestarted", message)
+                    .await;
+            }
+            Report::Finished {
+                instance_id,
+                failure,
+            } => {
+                let (status, details) = match failure {
+                    Some((container, code)) => (
+                        InstanceStatus::Failed,
+                        InstanceDetails {
+                            workload_id: String::new(),
+                            reason: Some("ContainerFailed".to_string()),
+                            message: Some(format!(
+                                "Container {} exited {}",
+                                container,
+                                describe_exit(code)
+                            )),
+                            exit_code: code,
+                        },
+                    ),
+                    None => (
+                        InstanceStatus::Succeeded,
+                        InstanceDetails {
+                            workload_id: String::new(),
+                            reason: Some("Completed".to_string()),
+                            message: Some("Every container exited successfully".to_string()),
+                            exit_code: Some(0),
+                        },
+                    ),
+                };
+                // The instance is being deleted
+                if self.deleting.contains(&instance_id) {
+                    return;
+                }
+                match self.inventory.get_mut(&instance_id) {
+                    Some(entry) => entry.status = status.clone().into(),
+                    None => return,
+                }
+                self.send_instance_metric(status, &instance_id, Some(details), None)
+                    .await
+                    .unwrap_or_else(|e| error!("Error while sending status: {}", e));
+            }
+        }
+    }
+
     async fn send_status(&self, status: InstanceStatus, instance_id: &str) -> Result<()> {
+        self.send_instance_metric(status, instance_id, None, None)
+            .await
+    }
+
+    /// Report the instance as running along with how to reach it
+    async fn send_running(&self, instance_id: &str, placement: InstancePlacement) -> Result<()> {
+        self.send_instance_metric(InstanceStatus::Running, instance_id, None, Some(placement))
+            .await
+    }
+
+    /// Report the instance as failed along with what made it fail. The exit
+    /// code is left unset, the one of a failed runc command is not the code
+    /// of a container and those are reported once they exited
+    async fn send_failure(&self, instance_id: &str, error: &RuntimeError) -> Result<()> {
+        let details = InstanceDetails {
+            workload_id: String::new(),
+            reason: Some(error.reason().to_string()),
+            message: Some(error.to_string()),
+            exit_code: None,
+        };
+        self.send_instance_metric(InstanceStatus::Failed, instance_id, Some(details), None)
+            .await
+    }
+
+    #[tracing::instrument(
+        skip(self, details, placement),
+        fields(instance_id = %instance_id, status = %status)
+    )]
+    async fn send_instance_metric(
+        &self,
+        status: InstanceStatus,
+        instance_id: &str,
+        details: Option<InstanceDetails>,
+        placement: Option<InstancePlacement>,
+    ) -> Result<()> {
         info!("Update instance status");
 
-        let status = WorkerStatus::new(self.hostname.clone(), instance_id.to_string(), status);
+        let mut status = WorkerStatus::new(self.hostname.clone(), instance_id.to_string(), status);
+        if let Some(Status::Instance(metric)) = status.0.status.as_mut() {
+            metric.trace_context = telemetry::current_context();
+            metric.details = details;
+            metric.placement = placement;
+        }
 
         MetricsEmitter::emit_event(self.client.clone(), vec![status.0])
             .await
@@ -154,21 +486,46 @@ impl Riklet {
         Ok(())
     }
 
+    /// Report an event about an instance, so users can see what happened to it
+    async fn send_event(&self, instance_id: &str, reason: &str, message: String) {
+        let event = ClusterEvent::instance(
+            instance_id,
+            reason,
+            message,
+            format!("riklet/{}", self.hostname),
+        );
+        let status = WorkerStatus::event(self.hostname.clone(), event);
+        MetricsEmitter::emit_event(self.client.clone(), vec![status.0])
+            .await
+            .unwrap_or_else(|err| event!(Level::ERROR, "Error while sending event : {:?}", err));
+    }
+
     pub async fn run(&mut self) -> Result<()> {
+        let _alive = health::RIKLET.guard();
         self.start_metrics_updater();
         info!("Riklet is running");
 
-        while let Some(workload) = self
-            .stream
-            .message()
-            .await
-            .map_err(RikletError::MessageStatusError)?
-        {
-            self.handle_workload(&workload).await.unwrap_or_else(|e| {
-                error!("Error while handling workload: {}", e);
-            })
+        loop {
+            tokio::select! {
+                message = self.stream.message() => match message {
+                    Ok(Some(workload)) => self.handle_workload(&workload).await.unwrap_or_else(|e| {
+                        error!("Error while handling workload: {}", e);
+                    }),
+                    Ok(None) => {
+                        warn!("Scheduler closed the stream, registering again");
+                        health::REGISTRATION.set(false);
+                        self.reconnect().await;
+                    }
+                    Err(e) => {
+                        warn!("Lost connection with the scheduler: {}", e);
+                        health::REGISTRATION.set(false);
+                        self.reconnect().await;
+                    }
+                },
+                Some(report) = self.reports.recv() => self.handle_report(report).await,
+                Some(deletion) = self.deletions.recv() => self.handle_deletion(deletion).await,
+            }
         }
-        Ok(())
     }
 
     fn start_metrics_updater(&self) {
@@ -184,39 +541,67 @@ impl Riklet {
         });
     }
 
-    pub async fn new() -> Result<Self> {
+    pub async fn new(cli: &CliConfiguration) -> Result<Self> {
         event!(Level::DEBUG, "Riklet bootstraping process started.");
         banner();
         let hostname = gethostname::gethostname().into_string().unwrap();
 
-        let config = Configuration::load().map_err(RikletError::ConfigurationError)?;
+        let config = Configuration::load(cli).map_err(RikletError::ConfigurationError)?;
 
         let mut client = WorkerClient::connect(config.master_ip.clone())
             .await
             .map_err(RikletError::ConnectionError)?;
         event!(Level::DEBUG, "gRPC WorkerClient connected.");
-
-        event!(Level::DEBUG, "Node's registration to the master");
-        let request = Request::new(WorkerRegistration {
-            hostname: hostname.clone(),
-        });
-        let stream = client.register(request).await.unwrap().into_inner();
+        Self::serve_sessions(cli, &config.master_ip);
 
         let fn_configuration =
             FnConfiguration::load().map_err(|e| RikletError::InvalidInput(e.to_string()))?;
 
-        let mut global_runtime_network = GlobalRuntimeNetwork::new(fn_configuration.iface)
-            .map_err(|e| RikletError::NetworkError(NetworkError::IptablesError(e)))?;
+        // Containers left by runc become children of the riklet, so their
+        // exit codes can be known
+        if let Err(e) = supervisor::become_subreaper() {
+            warn!("Exit codes of the containers won't be known: {}", e);
+        }
+
+        let capabilities = capabilities::probe(&config, &fn_configuration);
+        health::RUNTIMES.set(!capabilities.kinds.is_empty());
+
+        // The instances of a previous riklet are not known anymore, they are
+        // rescheduled once it registers without them
+        orphans::tear_down(&config).await;
+
+        let stream = Self::register(
+            &mut client,
+            WorkerRegistration {
+                hostname: hostname.clone(),
+                instances: vec![],
+                capabilities: Some(capabilities.clone()),
+            },
+        )
+        .await?;
+        health::REGISTRATION.set(true);
+
+        let mut global_runtime_network =
+            GlobalRuntimeNetwork::new(fn_configuration.iface, config.pod_network.clone())
+                .map_err(|e| RikletError::NetworkError(NetworkError::IptablesError(e)))?;
         global_runtime_network
             .init()
             .await
             .map_err(RikletError::NetworkError)?;
+        health::NETWORK.set(true);
 
+        let (deletions_sender, deletions) = mpsc::unbounded_channel();
         Ok(Self {
             hostname,
             client,
             stream,
             runtimes: HashMap::<String, Box<dyn Runtime>>::new(),
+            inventory: HashMap::new(),
+            capabilities,
+            reports: supervisor::subscribe(),
+            deleting: HashSet::new(),
+            deletions_sender,
+            deletions,
             config,
             network: global_runtime_network,
         })
@@ -225,6 +610,14 @@ impl Riklet {
     pub async fn shutdown(&mut self) -> Result<()> {
         info!("Shutting down ...");
 
+        // Let the deletions already started finish
+        while !self.deleting.is_empty() {
+            match self.deletions.recv().await {
+                Some(deletion) => self.handle_deletion(deletion).await,
+                None => break,
+            }
+        }
+
         // Stop and clean all runtime
         for (_, runtime) in &mut self.runtimes {
             runtime
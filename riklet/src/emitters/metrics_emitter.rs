@@ -1,3 +1,4 @@
+use crate::metrics;
 use crate::structs::EventEmitter;
 use futures_util::stream;
 use node_metrics::metrics_manager::MetricsManager;
@@ -33,12 +34,13 @@ impl MetricsEmitter {
 
     async fn emit(&mut self) {
         let node_metric = self.manager.fetch();
+        metrics::record_node(&node_metric);
         let worker_status = WorkerStatus {
             host_address: None,
             identifier: self.identifier.clone(),
             status: Some(proto::common::worker_status::Status::Worker(WorkerMetric {
                 status: 2,
-                metrics: node_metric.to_json().unwrap(),
+                metrics: Some((&node_metric).into()),
             })),
         };
         MetricsEmitter::emit_event(self.client.clone(), vec![worker_status])
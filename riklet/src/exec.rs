@@ -0,0 +1,292 @@
+//! Interactive sessions in the containers of the instances, either running a
+//! new process with `runc exec` or attached to the main one. The input and
+//! output of a session are relayed over an upgraded HTTP connection.
+use crate::logs;
+use crate::runtime::{Result, RuntimeError};
+use cri::console::ConsoleSocket;
+use cri::container::{ExecArgs, Runc};
+use nix::sys::wait::waitpid;
+use nix::unistd::Pid;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::process::Child;
+use tracing::{debug, error};
+use uuid::Uuid;
+
+/// Delay between two checks for new output of an attached container
+const ATTACH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A container sessions can be opened in
+pub struct Container {
+    pub id: String,
+    pub runtime: Arc<Runc>,
+    /// Master side of the PTY of the main process, once runc handed it over
+    pub console: Option<File>,
+}
+
+/// Containers of the instances running on this node, by instance id then
+/// by container name
+static CONTAINERS: Lazy<Mutex<HashMap<String, HashMap<String, Container>>>> =
+    Lazy::new(Default::default);
+
+/// Make a container of an instance available to sessions
+pub fn register(instance_id: &str, name: &str, id: &str, runtime: Arc<Runc>) {
+    CONTAINERS
+        .lock()
+        .unwrap()
+        .entry(instance_id.to_string())
+        .or_default()
+        .insert(
+            name.to_string(),
+            Container {
+                id: id.to_string(),
+                runtime,
+                console: None,
+            },
+        );
+}
+
+/// Keep the console of a container so sessions can attach to it
+pub fn set_console(instance_id: &str, name: &str, console: File) {
+    if let Some(container) = CONTAINERS
+        .lock()
+        .unwrap()
+        .get_mut(instance_id)
+        .and_then(|containers| containers.get_mut(name))
+    {
+        container.console = Some(console);
+    }
+}
+
+/// Forget the containers of an instance which is gone
+pub fn unregister(instance_id: &str) {
+    CONTAINERS.lock().unwrap().remove(instance_id);
+}
+
+/// Names of the containers of an instance, sorted
+pub fn names(instance_id: &str) -> Vec<String> {
+    let mut names: Vec<String> = CONTAINERS
+        .lock()
+        .unwrap()
+        .get(instance_id)
+        .map(|containers| containers.keys().cloned().collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// A container of an instance, given by `name` unless the instance has a
+/// single one. Its console is duplicated so the session can outlive it.
+pub fn find(instance_id: &str, name: Option<&str>) -> Option<(String, Container)> {
+    let containers = CONTAINERS.lock().unwrap();
+    let containers = containers.get(instance_id)?;
+    let (name, container) = match name {
+        Some(name) => containers.get_key_value(name)?,
+        None if containers.len() == 1 => containers.iter().next()?,
+        None => return None,
+    };
+    let console = container
+        .console
+        .as_ref()
+        .and_then(|console| console.try_clone().ok());
+    Some((
+        name.clone(),
+        Container {
+            id: container.id.clone(),
+            runtime: container.runtime.clone(),
+            console,
+        },
+    ))
+}
+
+/// A process started in a container
+pub enum Process {
+    /// Master side of the TTY of the process, along with its pid
+    Terminal(File, Option<i32>),
+    /// runc itself, whose standard streams are the ones of the process
+    Piped(Child),
+}
+
+/// Start `command` in a container, with a TTY when `tty` is set
+pub async fn start(container: &Container, command: &[String], tty: bool) -> Result<Process> {
+    if !tty {
+        return container
+            .runtime
+            .exec_process(&container.id, command, Some(&ExecArgs::default()))
+            .map(Process::Piped)
+            .map_err(RuntimeError::CriError);
+    }
+
+    // runc only hands the TTY over when detached
+    let session = Uuid::new_v4();
+    let socket_path = std::env::temp_dir().join(format!("exec-{}", session));
+    let pid_file = std::env::temp_dir().join(format!("exec-{}.pid", session));
+    let console_socket = ConsoleSocket::new(&socket_path).map_err(RuntimeError::CriError)?;
+    let child = container
+        .runtime
+        .exec_process(
+            &container.id,
+            command,
+            Some(&ExecArgs {
+                tty: true,
+                console_socket: Some(socket_path),
+                pid_file: Some(pid_file.clone()),
+                detach: true,
+            }),
+        )
+        .map_err(RuntimeError::CriError)?;
+    let pty_master = tokio::spawn(async move { console_socket.receive_pty_master().await });
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(RuntimeError::IoError)?;
+    let pid = std::fs::read_to_string(&pid_file)
+        .ok()
+        .and_then(|pid| pid.trim().parse().ok());
+    let _ = std::fs::remove_file(&pid_file);
+    if !output.status.success() {
+        pty_master.abort();
+        return Err(RuntimeError::CriError(cri::Error::RuncCommandFailedError(
+            String::from_utf8_lossy(&output.stdout).to_string(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+            output.status.code(),
+        )));
+    }
+    pty_master
+        .await
+        .map_err(|e| RuntimeError::Error(e.to_string()))?
+        .map(|pty_master| Process::Terminal(pty_master, pid))
+        .map_err(RuntimeError::CriError)
+}
+
+/// Relay the input and output of a process over `stream` until it exits
+pub async fn relay<S>(stream: S, process: Process)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (mut input, mut output) = io::split(stream);
+    match process {
+        Process::Terminal(pty_master, pid) => {
+            let writer = match pty_master.try_clone() {
+                Ok(writer) => writer,
+                Err(e) => {
+                    error!("Could not duplicate the TTY of the process: {}", e);
+                    return;
+                }
+            };
+            let mut writer = tokio::fs::File::from_std(writer);
+            let forward = tokio::spawn(async move { io::copy(&mut input, &mut writer).await });
+            // The PTY master fails with EIO once the process exited
+            let _ = io::copy(&mut tokio::fs::File::from_std(pty_master), &mut output).await;
+            forward.abort();
+            // runc left the process to the riklet, which is its subreaper
+            if let Some(pid) = pid {
+                let _ =
+                    tokio::task::spawn_blocking(move || waitpid(Pid::from_raw(pid), None)).await;
+            }
+        }
+        Process::Piped(mut child) => {
+            let stdin = child.stdin.take();
+            let forward = tokio::spawn(async move {
+                if let Some(mut stdin) = stdin {
+                    // Closing stdin once the client is done lets the process end
+                    let _ = io::copy(&mut input, &mut stdin).await;
+                }
+            });
+            merge_outputs(&mut child, &mut output).await;
+            match child.wait().await {
+                Ok(status) => debug!("Executed process exited with {}", status),
+                Err(e) => error!("Could not wait for the executed process: {}", e),
+            }
+            forward.abort();
+        }
+    }
+    let _ = output.shutdown().await;
+}
+
+/// Write both the standard output and error of a process to `output`, as
+/// they come, until both are closed
+async fn merge_outputs(child: &mut Child, output: &mut (impl AsyncWrite + Unpin)) {
+    let mut stdout = child.stdout.take();
+    let mut stderr = child.stderr.take();
+    let mut stdout_buffer = [0u8; 8192];
+    let mut stderr_buffer = [0u8; 8192];
+    loop {
+        let written = tokio::select! {
+            read = read_some(&mut stdout, &mut stdout_buffer), if stdout.is_some() => match read {
+                Some(read) => output.write_all(&stdout_buffer[..read]).await,
+                None => {
+                    stdout = None;
+                    Ok(())
+                }
+            },
+            read = read_some(&mut stderr, &mut stderr_buffer), if stderr.is_some() => match read {
+                Some(read) => output.write_all(&stderr_buffer[..read]).await,
+                None => {
+                    stderr = None;
+                    Ok(())
+                }
+            },
+            else => return,
+        };
+        if written.is_err() {
+            return;
+        }
+    }
+}
+
+/// Read what an output of a process has, `None` meaning it is closed
+async fn read_some(
+    output: &mut Option<impl AsyncRead + Unpin>,
+    buffer: &mut [u8],
+) -> Option<usize> {
+    match output.as_mut()?.read(buffer).await {
+        Ok(0) | Err(_) => None,
+        Ok(read) => Some(read),
+    }
+}
+
+/// Attach to the main process of a container: what it writes is sent over
+/// `stream`, and what comes from `stream` is written to its console, until
+/// either side is done
+pub async fn attach<S>(stream: S, instance_id: &str, name: &str, console: File)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (mut input, output) = io::split(stream);
+    let mut console = tokio::fs::File::from_std(console);
+    tokio::select! {
+        _ = logs::follow_into(logs::log_path(instance_id, name), output, ATTACH_INTERVAL) => (),
+        _ = io::copy(&mut input, &mut console) => (),
+    }
+    debug!("Detached from container {} of {}", name, instance_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::process::Command;
+
+    #[tokio::test]
+    async fn test_merge_outputs() {
+        let mut child = Command::new("sh")
+            .args(["-c", "echo out; echo err >&2"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut output = Vec::new();
+        merge_outputs(&mut child, &mut output).await;
+        child.wait().await.unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("out\n"));
+        assert!(output.contains("err\n"));
+    }
+}
@@ -0,0 +1,26 @@
+//! Liveness and readiness of the riklet, served on `/healthz` and `/readyz`
+use probes::health::{report, Check};
+
+/// Loop handling scheduling requests coming from the scheduler
+pub static RIKLET: Check = Check::new("riklet");
+/// Stream of scheduling requests, down while registering again
+pub static REGISTRATION: Check = Check::new("registration");
+/// Chains and rules shared by every workload
+pub static NETWORK: Check = Check::new("network");
+/// Binaries needed to run at least one kind of workload
+pub static RUNTIMES: Check = Check::new("runtimes");
+
+/// Whether the riklet is able to make progress at all
+pub fn liveness() -> (bool, String) {
+    report(&[RIKLET.status()])
+}
+
+/// Whether the riklet is able to run workloads
+pub fn readiness() -> (bool, String) {
+    report(&[
+        RIKLET.status(),
+        REGISTRATION.status(),
+        NETWORK.status(),
+        RUNTIMES.status(),
+    ])
+}
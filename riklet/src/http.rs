@@ -0,0 +1,323 @@
+use crate::{exec, logs};
+use hyper::header::{AUTHORIZATION, CONNECTION, UPGRADE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info};
+
+/// Protocol sessions switch their connection to, carrying raw bytes
+const SESSION_PROTOCOL: &str = "tcp";
+
+/// Serve the logs of the instances and sessions in their containers on
+/// `listener`, to the clients presenting `token`. Sessions run any command in
+/// the containers and logs may tell what they hold, so they are kept apart
+/// from the operational endpoints.
+pub async fn serve_sessions(listener: SocketAddr, token: String) {
+    let token = Arc::new(token);
+    let service = make_service_fn(move |_| {
+        let token = token.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle_session(request, token.clone())
+            }))
+        }
+    });
+    let server = match Server::try_bind(&listener) {
+        Ok(builder) => builder.serve(service),
+        Err(e) => {
+            error!("Could not listen on {}, reason: {}", listener, e);
+            return;
+        }
+    };
+
+    info!("Sessions listening on {}", listener);
+
+    if let Err(e) = server.await {
+        error!("{}", e);
+    }
+}
+
+/// Whether the request carries `token` as its bearer token, compared in
+/// constant time so it can't be guessed a byte at a time
+fn is_authorized(request: &Request<Body>, token: &str) -> bool {
+    let given = match request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        Some(given) => given.as_bytes(),
+        None => return false,
+    };
+    given.len() == token.len()
+        && given
+            .iter()
+            .zip(token.as_bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+async fn handle_session(
+    request: Request<Body>,
+    token: Arc<String>,
+) -> hyper::http::Result<Response<Body>> {
+    if !is_authorized(&request, &token) {
+        return text_response(StatusCode::UNAUTHORIZED, "Invalid sessions token");
+    }
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    match (&method, path.as_str()) {
+        (&Method::GET, path) if path.starts_with("/instances/") => {
+            match path.trim_start_matches("/instances/").split_once('/') {
+                Some((instance_id, "logs")) => logs_response(instance_id, request.uri().query()),
+                _ => Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty()),
+            }
+        }
+        (&Method::POST, path) if path.starts_with("/instances/") => {
+            match path.trim_start_matches("/instances/").split_once('/') {
+                Some((instance_id, "exec")) => exec_response(instance_id, request).await,
+                Some((instance_id, "attach")) => attach_response(instance_id, request),
+                _ => Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty()),
+            }
+        }
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    }
+}
+
+fn text_response(status: StatusCode, body: impl Into<Body>) -> hyper::http::Result<Response<Body>> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .body(body.into())
+}
+
+/// Output of an instance, given by the `container` parameter unless the
+/// instance has a single one or the console of a microVM. Only the last
+/// `tail` lines are sent when given, and new output is streamed as it comes
+/// when `follow` is set.
+fn logs_response(instance_id: &str, query: Option<&str>) -> hyper::http::Result<Response<Body>> {
+    let params: HashMap<String, String> =
+        url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+            .into_owned()
+            .collect();
+    if !logs::is_valid_name(instance_id) {
+        return text_response(StatusCode::BAD_REQUEST, "Invalid instance id");
+    }
+
+    let name = match params.get("container") {
+        Some(name) if logs::is_valid_name(name) => name.clone(),
+        Some(_) => return text_response(StatusCode::BAD_REQUEST, "Invalid container name"),
+        None => match logs::names(&logs::instance_directory(instance_id)) {
+            Ok(mut names) if names.len() == 1 => names.remove(0),
+            Ok(names) if names.iter().any(|name| name == logs::CONSOLE) => {
+                logs::CONSOLE.to_string()
+            }
+            Ok(names) if !names.is_empty() => {
+                return text_response(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Instance {} has several logs, choose one of: {}",
+                        instance_id,
+                        names.join(", ")
+                    ),
+                )
+            }
+            _ => {
+                return text_response(
+                    StatusCode::NOT_FOUND,
+                    format!("No logs found for instance {}", instance_id),
+                )
+            }
+        },
+    };
+    let tail = match params.get("tail").map(|tail| tail.parse::<usize>()) {
+        None => None,
+        Some(Ok(tail)) => Some(tail),
+        Some(Err(_)) => return text_response(StatusCode::BAD_REQUEST, "Invalid tail"),
+    };
+    let follow = matches!(
+        params.get("follow").map(String::as_str),
+        Some("true") | Some("1")
+    );
+
+    let path = logs::log_path(instance_id, &name);
+    let (content, offset) = match logs::read_tail(&path, tail) {
+        Ok(read) => read,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return text_response(
+                StatusCode::NOT_FOUND,
+                format!("No logs named {} for instance {}", name, instance_id),
+            )
+        }
+        Err(e) => {
+            error!("Could not read {}: {}", path.display(), e);
+            return text_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+        }
+    };
+    if !follow {
+        return text_response(StatusCode::OK, content);
+    }
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        if sender.send_data(content.into()).await.is_ok() {
+            logs::follow(path, offset, sender).await;
+        }
+    });
+    text_response(StatusCode::OK, body)
+}
+
+/// Container of an instance a session is opened in, given by the
+/// `container` parameter unless the instance has a single one, or the
+/// status and message telling why there is none
+fn session_container(
+    instance_id: &str,
+    request: &Request<Body>,
+    container: Option<&str>,
+) -> Result<(String, exec::Container), (StatusCode, String)> {
+    if request.headers().get(UPGRADE).is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Sessions need the connection upgraded to {}",
+                SESSION_PROTOCOL
+            ),
+        ));
+    }
+    if let Some(found) = exec::find(instance_id, container) {
+        return Ok(found);
+    }
+    let names = exec::names(instance_id);
+    Err(match container {
+        None if names.len() > 1 => (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Instance {} has several containers, choose one of: {}",
+                instance_id,
+                names.join(", ")
+            ),
+        ),
+        Some(name) if !names.is_empty() => (
+            StatusCode::NOT_FOUND,
+            format!("No container {} in instance {}", name, instance_id),
+        ),
+        _ => (
+            StatusCode::NOT_FOUND,
+            format!("No container found for instance {}", instance_id),
+        ),
+    })
+}
+
+/// Switch the connection of a session to raw bytes
+fn switching_response() -> hyper::http::Result<Response<Body>> {
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(CONNECTION, "Upgrade")
+        .header(UPGRADE, SESSION_PROTOCOL)
+        .body(Body::empty())
+}
+
+/// Run the `command` parameters in a container, with a TTY when `tty` is
+/// set, relaying its input and output over the upgraded connection until it
+/// exits
+async fn exec_response(
+    instance_id: &str,
+    request: Request<Body>,
+) -> hyper::http::Result<Response<Body>> {
+    let params: Vec<(String, String)> =
+        url::form_urlencoded::parse(request.uri().query().unwrap_or_default().as_bytes())
+            .into_owned()
+            .collect();
+    let param = |key: &str| {
+        params
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    };
+    let command: Vec<String> = params
+        .iter()
+        .filter(|(name, _)| name == "command")
+        .map(|(_, value)| value.clone())
+        .collect();
+    if command.is_empty() {
+        return text_response(StatusCode::BAD_REQUEST, "Missing command");
+    }
+    let tty = matches!(param("tty"), Some("true") | Some("1"));
+
+    let (name, container) = match session_container(instance_id, &request, param("container")) {
+        Ok(found) => found,
+        Err((status, message)) => return text_response(status, message),
+    };
+    let instance_id = instance_id.to_string();
+    tokio::spawn(async move {
+        let mut upgraded = match hyper::upgrade::on(request).await {
+            Ok(upgraded) => upgraded,
+            Err(e) => {
+                error!("Could not upgrade the connection of a session: {}", e);
+                return;
+            }
+        };
+        // Only started once there is a client, so it is never left running
+        // without one
+        match exec::start(&container, &command, tty).await {
+            Ok(process) => exec::relay(upgraded, process).await,
+            Err(e) => {
+                error!(
+                    "Could not exec in container {} of {}: {}",
+                    name, instance_id, e
+                );
+                let _ = upgraded.write_all(format!("{}\n", e).as_bytes()).await;
+                let _ = upgraded.shutdown().await;
+            }
+        }
+    });
+    switching_response()
+}
+
+/// Attach to the main process of a container, until the client leaves
+fn attach_response(
+    instance_id: &str,
+    request: Request<Body>,
+) -> hyper::http::Result<Response<Body>> {
+    let params: HashMap<String, String> =
+        url::form_urlencoded::parse(request.uri().query().unwrap_or_default().as_bytes())
+            .into_owned()
+            .collect();
+    let (name, container) = match session_container(
+        instance_id,
+        &request,
+        params.get("container").map(String::as_str),
+    ) {
+        Ok(found) => found,
+        Err((status, message)) => return text_response(status, message),
+    };
+    let console = match container.console {
+        Some(console) => console,
+        None => {
+            return text_response(
+                StatusCode::CONFLICT,
+                format!("Container {} of {} has no console yet", name, instance_id),
+            )
+        }
+    };
+
+    let instance_id = instance_id.to_string();
+    tokio::spawn(async move {
+        match hyper::upgrade::on(request).await {
+            Ok(upgraded) => exec::attach(upgraded, &instance_id, &name, console).await,
+            Err(e) => error!("Could not upgrade the connection of a session: {}", e),
+        }
+    });
+    switching_response()
+}
@@ -0,0 +1,366 @@
+//! Output of the instances, written to rotated files under [LOGS_DIRECTORY]
+//! and served on `/instances/<id>/logs` along with the sessions
+use futures_util::future::poll_fn;
+use futures_util::FutureExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+/// Directory holding a sub-directory of log files per instance
+pub const LOGS_DIRECTORY: &str = "/var/log/riklet";
+/// Name of the logs of the serial console of a microVM
+pub const CONSOLE: &str = "console";
+/// Name of the logs of the Firecracker process running a microVM
+pub const FIRECRACKER: &str = "firecracker";
+/// Size from which a log file is rotated
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+/// Number of rotated files kept along with the current one
+const MAX_ROTATED_FILES: usize = 3;
+/// Delay between two checks for new output when following a log file
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Directory of the log files of an instance
+pub fn instance_directory(instance_id: &str) -> PathBuf {
+    Path::new(LOGS_DIRECTORY).join(instance_id)
+}
+
+/// Log file of a container, or of any other output of an instance
+pub fn log_path(instance_id: &str, name: &str) -> PathBuf {
+    instance_directory(instance_id).join(format!("{}.log", name))
+}
+
+/// Whether a name given through the API can be used as a file name
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains('/')
+}
+
+/// Names of the outputs an instance has logs of, sorted
+pub fn names(directory: &Path) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let file_name = entry?.file_name();
+        if let Some(name) = file_name
+            .to_str()
+            .and_then(|name| name.strip_suffix(".log"))
+        {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Remove the logs of an instance which is gone
+pub fn remove(instance_id: &str) {
+    let directory = instance_directory(instance_id);
+    match fs::remove_dir_all(&directory) {
+        Ok(()) => debug!("Removed logs of instance {}", instance_id),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => error!("Could not remove {}: {}", directory.display(), e),
+    }
+}
+
+/// Path of the n-th rotated file, the first one being the most recent
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", index));
+    PathBuf::from(rotated)
+}
+
+/// A log file moved aside once it reaches a given size, only the most
+/// recent rotated files being kept
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::with_max_size(path, MAX_LOG_SIZE)
+    }
+
+    fn with_max_size(path: &Path, max_size: u64) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_size,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                fs::rename(from, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Copy an output to its log file until it is closed
+pub fn capture(mut output: impl Read + Send + 'static, path: PathBuf) {
+    tokio::task::spawn_blocking(move || {
+        let mut file = match RotatingFile::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Could not open log file {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let mut buffer = [0u8; 8192];
+        loop {
+            match output.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => {
+                    if let Err(e) = file.write_all(&buffer[..read]) {
+                        error!("Could not write log file {}: {}", path.display(), e);
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                // The PTY master fails with EIO once the container exited
+                Err(_) => break,
+            }
+        }
+        debug!("Output captured in {} is closed", path.display());
+    });
+}
+
+/// Copy what another process appends to `source` to a log file, until the
+/// returned task is aborted. The source may not exist yet.
+pub fn mirror(source: PathBuf, path: PathBuf) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut file = match RotatingFile::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Could not open log file {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let mut offset = 0;
+        loop {
+            tokio::time::sleep(FOLLOW_INTERVAL).await;
+            match read_from(&source, &mut offset) {
+                Ok(content) if !content.is_empty() => {
+                    if let Err(e) = file.write_all(&content) {
+                        error!("Could not write log file {}: {}", path.display(), e);
+                        return;
+                    }
+                }
+                Ok(_) => (),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+                Err(e) => {
+                    error!("Could not read {}: {}", source.display(), e);
+                    return;
+                }
+            }
+        }
+    })
+}
+
+/// What was written to a file from `offset`, which is moved to its end.
+/// The file is read from its start again when it was truncated.
+fn read_from(path: &Path, offset: &mut u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    if size < *offset {
+        *offset = 0;
+    }
+    let mut content = Vec::new();
+    if size > *offset {
+        file.seek(SeekFrom::Start(*offset))?;
+        *offset += file.read_to_end(&mut content)? as u64;
+    }
+    Ok(content)
+}
+
+/// Content of a log file along with its rotated files, limited to the last
+/// `lines` lines when given. Also gives the size of the current file, from
+/// which new output is followed.
+pub fn read_tail(path: &Path, lines: Option<usize>) -> io::Result<(Vec<u8>, u64)> {
+    let mut content = Vec::new();
+    for index in (1..=MAX_ROTATED_FILES).rev() {
+        match File::open(rotated_path(path, index)) {
+            Ok(mut file) => {
+                file.read_to_end(&mut content)?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+    }
+    let mut file = File::open(path)?;
+    let offset = file.read_to_end(&mut content)? as u64;
+
+    if let Some(lines) = lines {
+        // A trailing newline ends the last line rather than starting a new one
+        let end = match content.last() {
+            Some(b'\n') => content.len() - 1,
+            _ => content.len(),
+        };
+        let start = content[..end]
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, byte)| **byte == b'\n')
+            .nth(lines.saturating_sub(1))
+            .map(|(index, _)| index + 1)
+            .unwrap_or(0);
+        content = match lines {
+            0 => Vec::new(),
+            _ => content.split_off(start),
+        };
+    }
+    Ok((content, offset))
+}
+
+/// Send what is written to a log file from `offset`, until the file is
+/// removed or the client is gone
+pub async fn follow(path: PathBuf, mut offset: u64, mut sender: hyper::body::Sender) {
+    loop {
+        tokio::time::sleep(FOLLOW_INTERVAL).await;
+        if let Some(Err(_)) = poll_fn(|cx| sender.poll_ready(cx)).now_or_never() {
+            return;
+        }
+        let content = match read_from(&path, &mut offset) {
+            Ok(content) => content,
+            Err(_) => return,
+        };
+        if content.is_empty() {
+            continue;
+        }
+        if sender.send_data(content.into()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Write what is appended to a log file from its current end to `output`,
+/// checking for it every `interval`, until the file is removed or `output`
+/// is closed
+pub async fn follow_into(path: PathBuf, mut output: impl AsyncWrite + Unpin, interval: Duration) {
+    let mut offset = match fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return,
+    };
+    loop {
+        tokio::time::sleep(interval).await;
+        let content = match read_from(&path, &mut offset) {
+            Ok(content) => content,
+            Err(_) => return,
+        };
+        if content.is_empty() {
+            continue;
+        }
+        if output.write_all(&content).await.is_err() || output.flush().await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("riklet-logs-{}", uuid::Uuid::new_v4()))
+            .join("container.log")
+    }
+
+    #[test]
+    fn test_rotate_and_tail() {
+        let path = temp_log();
+        let mut file = RotatingFile::with_max_size(&path, 8).unwrap();
+        for line in ["one\n", "two\n", "three\n", "four\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        assert!(rotated_path(&path, 1).exists());
+
+        let (content, offset) = read_tail(&path, None).unwrap();
+        assert_eq!(content, b"one\ntwo\nthree\nfour\n");
+        assert_eq!(offset, 5);
+
+        let (content, _) = read_tail(&path, Some(2)).unwrap();
+        assert_eq!(content, b"three\nfour\n");
+        let (content, _) = read_tail(&path, Some(0)).unwrap();
+        assert!(content.is_empty());
+
+        assert_eq!(names(path.parent().unwrap()).unwrap(), vec!["container"]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_keep_rotated_files() {
+        let path = temp_log();
+        let mut file = RotatingFile::with_max_size(&path, 1).unwrap();
+        for line in ["1", "2", "3", "4", "5", "6"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        assert!(rotated_path(&path, MAX_ROTATED_FILES).exists());
+        assert!(!rotated_path(&path, MAX_ROTATED_FILES + 1).exists());
+
+        let (content, _) = read_tail(&path, None).unwrap();
+        assert_eq!(content, b"3456");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_read_appended() {
+        let path = temp_log();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut offset = 0;
+        fs::write(&path, "boot\n").unwrap();
+        assert_eq!(read_from(&path, &mut offset).unwrap(), b"boot\n");
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"panic\n").unwrap();
+        assert_eq!(read_from(&path, &mut offset).unwrap(), b"panic\n");
+        assert!(read_from(&path, &mut offset).unwrap().is_empty());
+
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(read_from(&path, &mut offset).unwrap(), b"new\n");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_valid_names() {
+        assert!(is_valid_name("web"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("../etc"));
+        assert!(!is_valid_name("a/b"));
+    }
+}
@@ -2,20 +2,25 @@ mod cli;
 mod constants;
 mod core;
 mod emitters;
+mod exec;
+mod health;
+mod http;
 mod iptables;
+mod logs;
+mod metrics;
 mod net_utils;
 mod runtime;
 mod structs;
 
+use crate::cli::CliConfiguration;
 use crate::core::Riklet;
 use anyhow::{Context, Result};
+use clap::Parser;
+use probes::Probes;
 
 use tokio::signal::ctrl_c;
 use tokio::signal::unix::{signal, SignalKind};
-use tracing::{error, info, metadata::LevelFilter};
-use tracing_subscriber::{
-    fmt, prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt, EnvFilter,
-};
+use tracing::{error, info};
 
 pub fn banner() {
     println!(
@@ -30,20 +35,8 @@ pub fn banner() {
     );
 }
 
-pub fn init_logger() -> Result<()> {
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(
-            EnvFilter::builder()
-                .with_default_directive(LevelFilter::INFO.into())
-                .from_env_lossy(),
-        )
-        .init();
-    Ok(())
-}
-
-async fn serve() -> Result<()> {
-    let mut riklet = Riklet::new().await.unwrap_or_else(|e| {
+async fn serve(cli: CliConfiguration) -> Result<()> {
+    let mut riklet = Riklet::new(&cli).await.unwrap_or_else(|e| {
         error!(
             "An error occured during the bootstraping process of the Riklet. {}",
             e
@@ -72,7 +65,7 @@ async fn serve() -> Result<()> {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    init_logger()?;
+    let _telemetry = telemetry::init("riklet")?;
 
     // If the process doesn't have root privileges, exit and display error.
     if !nix::unistd::Uid::effective().is_root() {
@@ -80,7 +73,18 @@ async fn main() -> Result<()> {
         std::process::exit(1);
     }
 
-    serve().await?;
+    let cli = CliConfiguration::parse();
+    let endpoints = Probes {
+        metrics: metrics::render,
+        liveness: health::liveness,
+        readiness: health::readiness,
+    };
+    tokio::spawn(probes::serve(
+        cli.metrics_address,
+        endpoints,
+        probes::not_found,
+    ));
+    serve(cli).await?;
 
     info!("Riklet stopped");
 
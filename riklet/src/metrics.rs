@@ -0,0 +1,94 @@
+//! Operational metrics of the riklet, exposed in Prometheus format on `/metrics`
+use node_metrics::metrics::Metrics;
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_gauge, register_histogram, register_int_gauge, register_int_gauge_vec, Gauge,
+    Histogram, IntGauge, IntGaugeVec, TextEncoder,
+};
+
+pub static NODE_CPU_COUNT: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("rik_riklet_node_cpu_count", "Number of CPU of the node").unwrap()
+});
+
+pub static NODE_CPU_USAGE: Lazy<Gauge> = Lazy::new(|| {
+    register_gauge!(
+        "rik_riklet_node_cpu_usage_percent",
+        "Percentage of total CPU usage of the node"
+    )
+    .unwrap()
+});
+
+pub static NODE_MEMORY: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "rik_riklet_node_memory_bytes",
+        "Memory of the node",
+        &["kind"]
+    )
+    .unwrap()
+});
+
+pub static NODE_DISK: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "rik_riklet_node_disk_bytes",
+        "Disk space of the node",
+        &["disk", "kind"]
+    )
+    .unwrap()
+});
+
+pub static RUNTIMES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "rik_riklet_runtimes",
+        "Runtimes currently running on the node",
+        &["kind"]
+    )
+    .unwrap()
+});
+
+pub static IMAGE_PULL_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "rik_riklet_image_pull_duration_seconds",
+        "Time spent pulling and unpacking container images",
+        vec![0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]
+    )
+    .unwrap()
+});
+
+pub static VM_BOOT_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "rik_riklet_vm_boot_duration_seconds",
+        "Time spent creating and starting microVMs"
+    )
+    .unwrap()
+});
+
+/// Publish the latest metrics of the node
+pub fn record_node(metrics: &Metrics) {
+    NODE_CPU_COUNT.set(metrics.cpu.total.into());
+    NODE_CPU_USAGE.set(100.0 - f64::from(metrics.cpu.free));
+    NODE_MEMORY
+        .with_label_values(&["total"])
+        .set(metrics.memory.total as i64);
+    NODE_MEMORY
+        .with_label_values(&["free"])
+        .set(metrics.memory.free as i64);
+    for disk in &metrics.disks {
+        NODE_DISK
+            .with_label_values(&[&disk.disk_name, "total"])
+            .set(disk.total as i64);
+        NODE_DISK
+            .with_label_values(&[&disk.disk_name, "free"])
+            .set(disk.free as i64);
+    }
+}
+
+/// Encode every registered metric in Prometheus text format
+pub fn render() -> String {
+    // Metrics are registered lazily, make sure they show up before being used
+    Lazy::force(&RUNTIMES);
+    Lazy::force(&IMAGE_PULL_DURATION);
+    Lazy::force(&VM_BOOT_DURATION);
+    TextEncoder::new()
+        .encode_to_string(&prometheus::gather())
+        .unwrap_or_default()
+}
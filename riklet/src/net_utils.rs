@@ -1,8 +1,14 @@
-use std::net::{IpAddr, Ipv4Addr};
+use std::fs::File;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
 
+use anyhow::{anyhow, Context};
 use futures_util::TryStreamExt;
+use nix::mount::{mount, umount2, MntFlags, MsFlags};
+use nix::sched::{setns, unshare, CloneFlags};
 use rand::Rng;
-use rtnetlink::new_connection;
+use rtnetlink::{new_connection, Handle};
 use tracing::{debug, trace, warn};
 use utils::net::mac::MacAddr;
 
@@ -56,6 +62,219 @@ pub async fn set_link_ipv4(
     return Err(rtnetlink::Error::RequestFailed);
 }
 
+/// Index of the link named `iface_name`, if there is one
+async fn get_link_index(
+    handle: &Handle,
+    iface_name: &str,
+) -> Result<Option<u32>, rtnetlink::Error> {
+    let mut links = handle
+        .link()
+        .get()
+        .match_name(iface_name.to_string())
+        .execute();
+    match links.try_next().await {
+        Ok(link) => Ok(link.map(|link| link.header.index)),
+        // The kernel answers with ENODEV when no link has this name
+        Err(rtnetlink::Error::NetlinkError(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[tracing::instrument()]
+/// Create a bridge with the address ipv4/mask and bring it up, unless it
+/// already exists
+pub async fn create_bridge(name: String, ipv4: Ipv4Addr, mask: u8) -> Result<(), anyhow::Error> {
+    let (connection, handle, _) = new_connection()?;
+    tokio::spawn(connection);
+
+    if get_link_index(&handle, &name).await?.is_some() {
+        debug!("Bridge {} already exists", name);
+        return Ok(());
+    }
+    handle.link().add().bridge(name.clone()).execute().await?;
+    set_link_ipv4(name.clone(), ipv4, mask).await?;
+    set_link_up(name).await?;
+    Ok(())
+}
+
+#[tracing::instrument()]
+/// Create a pair of veth interfaces: `iface_name` is attached to `bridge` and
+/// brought up, while `peer_name` is moved in the network namespace at `netns`
+pub async fn create_veth(
+    iface_name: String,
+    peer_name: String,
+    bridge: String,
+    netns: PathBuf,
+) -> Result<(), anyhow::Error> {
+    let (connection, handle, _) = new_connection()?;
+    tokio::spawn(connection);
+
+    handle
+        .link()
+        .add()
+        .veth(iface_name.clone(), peer_name.clone())
+        .execute()
+        .await?;
+    let bridge_index = get_link_index(&handle, &bridge)
+        .await?
+        .ok_or_else(|| anyhow!("Could not get the bridge {}", bridge))?;
+    let index = get_link_index(&handle, &iface_name)
+        .await?
+        .ok_or_else(|| anyhow!("Could not get the interface {}", iface_name))?;
+    let peer_index = get_link_index(&handle, &peer_name)
+        .await?
+        .ok_or_else(|| anyhow!("Could not get the interface {}", peer_name))?;
+
+    handle
+        .link()
+        .set(index)
+        .master(bridge_index)
+        .execute()
+        .await?;
+    handle.link().set(index).up().execute().await?;
+
+    let netns = File::open(&netns)
+        .with_context(|| format!("Could not open the network namespace {}", netns.display()))?;
+    handle
+        .link()
+        .set(peer_index)
+        .setns_by_fd(netns.as_raw_fd())
+        .execute()
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument()]
+/// Delete the link named `iface_name`, along with its veth peer if it has one.
+/// Nothing is done if there is no such link.
+pub async fn delete_link(iface_name: String) -> Result<(), anyhow::Error> {
+    let (connection, handle, _) = new_connection()?;
+    tokio::spawn(connection);
+
+    if let Some(index) = get_link_index(&handle, &iface_name).await? {
+        handle.link().del(index).execute().await?;
+    }
+    Ok(())
+}
+
+/// Directory where network namespaces are bound so they outlive their
+/// processes, as `ip netns` does
+pub const NETNS_DIRECTORY: &str = "/var/run/netns";
+
+/// Path of the network namespace named `name`
+pub fn netns_path(name: &str) -> PathBuf {
+    Path::new(NETNS_DIRECTORY).join(name)
+}
+
+/// Run `f` in the network namespace at `netns`.
+///
+/// Namespaces belong to threads, so `f` runs in a thread of its own which
+/// is gone once done, and no other task ever runs in the namespace.
+fn in_netns<T, F>(netns: PathBuf, f: F) -> Result<T, anyhow::Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, anyhow::Error> + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let netns = File::open(&netns)
+            .with_context(|| format!("Could not open the network namespace {}", netns.display()))?;
+        setns(netns.as_raw_fd(), CloneFlags::CLONE_NEWNET)?;
+        f()
+    })
+    .join()
+    .map_err(|_| anyhow!("Network namespace thread panicked"))?
+}
+
+#[tracing::instrument()]
+/// Create a new network namespace, bound at [netns_path]
+pub fn create_netns(name: &str) -> Result<PathBuf, anyhow::Error> {
+    let path = netns_path(name);
+    std::fs::create_dir_all(NETNS_DIRECTORY)?;
+    File::create(&path)?;
+
+    let target = path.clone();
+    let created = std::thread::spawn(move || -> Result<(), anyhow::Error> {
+        unshare(CloneFlags::CLONE_NEWNET)?;
+        mount(
+            Some("/proc/thread-self/ns/net"),
+            target.as_path(),
+            None::<&str>,
+            MsFlags::MS_BIND,
+            None::<&str>,
+        )?;
+        Ok(())
+    })
+    .join()
+    .map_err(|_| anyhow!("Network namespace thread panicked"))?;
+
+    if let Err(e) = created {
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
+
+#[tracing::instrument()]
+/// Delete the network namespace at `netns`, its interfaces going along.
+/// Nothing is done if there is no such namespace.
+pub fn delete_netns(netns: &Path) -> Result<(), anyhow::Error> {
+    if !netns.exists() {
+        return Ok(());
+    }
+    if let Err(e) = umount2(netns, MntFlags::MNT_DETACH) {
+        // Not mounted if the namespace was not completely created
+        if e != nix::errno::Errno::EINVAL {
+            return Err(e.into());
+        }
+    }
+    std::fs::remove_file(netns)?;
+    Ok(())
+}
+
+#[tracing::instrument()]
+/// Configure the interface `iface_name` of the network namespace at `netns`:
+/// it is renamed to `new_name`, given the address ipv4/mask and brought up,
+/// with the default route going through `gateway`. The loopback is brought
+/// up too.
+pub async fn configure_netns_link(
+    netns: PathBuf,
+    iface_name: String,
+    new_name: String,
+    ipv4: Ipv4Addr,
+    mask: u8,
+    gateway: Ipv4Addr,
+) -> Result<(), anyhow::Error> {
+    tokio::task::spawn_blocking(move || {
+        in_netns(netns, move || {
+            // The connection has to be opened from the namespace
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            runtime.block_on(async move {
+                let (connection, handle, _) = new_connection()?;
+                tokio::spawn(connection);
+
+                let index = get_link_index(&handle, &iface_name)
+                    .await?
+                    .ok_or_else(|| anyhow!("Could not get the interface {}", iface_name))?;
+                handle.link().set(index).name(new_name).execute().await?;
+                handle
+                    .address()
+                    .add(index, ipv4.into(), mask)
+                    .execute()
+                    .await?;
+                handle.link().set(index).up().execute().await?;
+                if let Some(lo) = get_link_index(&handle, "lo").await? {
+                    handle.link().set(lo).up().execute().await?;
+                }
+                handle.route().add().v4().gateway(gateway).execute().await?;
+                Ok::<(), anyhow::Error>(())
+            })
+        })
+    })
+    .await?
+}
+
 /// Generate a new interface name with based on the id and a randomly generated number
 ///
 /// Random format is expected to be the following: {id}-1234 where 1234 is a random number
@@ -103,6 +322,25 @@ pub fn get_default_iface() -> Result<String, anyhow::Error> {
     Ok(iface_config.name)
 }
 
+/// Local IP the node reaches `url` from, the one it is seen connecting from
+/// there. A UDP socket is only connected to it, so nothing is sent.
+pub fn source_ip(url: &str) -> Result<IpAddr, anyhow::Error> {
+    let url = url::Url::parse(url).with_context(|| format!("Invalid URL {}", url))?;
+    let destination = url
+        .socket_addrs(|| None)
+        .with_context(|| format!("Could not resolve {}", url))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No address for {}", url))?;
+    let unspecified = match destination {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    };
+    let socket = UdpSocket::bind(unspecified)?;
+    socket.connect(destination)?;
+    Ok(socket.local_addr()?.ip())
+}
+
 pub fn get_default_gateway() -> Result<Ipv4Addr, anyhow::Error> {
     let gateway = match default_net::get_default_gateway() {
         Ok(gateway) => gateway,
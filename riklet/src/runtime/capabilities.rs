@@ -0,0 +1,138 @@
+use crate::cli::config::Configuration;
+use crate::cli::function_config::FnConfiguration;
+use proto::common::{WorkerCapabilities, WorkloadKind};
+use shared::utils::find_binary;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Device exposed by the kernel when KVM is available
+const KVM_DEVICE: &str = "/dev/kvm";
+/// Environment variable giving the path to the firecracker binary
+const FIRECRACKER_LOCATION: &str = "FIRECRACKER_LOCATION";
+
+/// Binaries found on the node, `None` when missing
+#[derive(Debug, Default)]
+struct Runtimes {
+    runc: Option<PathBuf>,
+    skopeo: Option<PathBuf>,
+    umoci: Option<PathBuf>,
+    firecracker: Option<PathBuf>,
+}
+
+impl Runtimes {
+    fn probe(config: &Configuration) -> Self {
+        // A binary configured explicitly takes precedence over the one in $PATH
+        let locate = |configured: &Option<PathBuf>, name: &str| match configured {
+            Some(path) if path.is_file() => Some(path.clone()),
+            Some(_) => None,
+            None => find_binary(name),
+        };
+        Runtimes {
+            runc: locate(&config.runner.command, "runc"),
+            skopeo: locate(&config.manager.image_puller.command, "skopeo"),
+            umoci: locate(&config.manager.oci_manager.command, "umoci"),
+            firecracker: Self::locate_firecracker(),
+        }
+    }
+
+    /// Follow the same lookup order as the firecracker executor
+    fn locate_firecracker() -> Option<PathBuf> {
+        std::env::var_os(FIRECRACKER_LOCATION)
+            .map(PathBuf::from)
+            .filter(|path| path.is_file())
+            .or_else(|| find_binary("firecracker"))
+            .or_else(|| Some(PathBuf::from("firecracker")).filter(|path| path.is_file()))
+    }
+
+    fn names(&self) -> Vec<String> {
+        [
+            ("runc", &self.runc),
+            ("skopeo", &self.skopeo),
+            ("umoci", &self.umoci),
+            ("firecracker", &self.firecracker),
+        ]
+        .iter()
+        .filter(|(_, path)| path.is_some())
+        .map(|(name, _)| name.to_string())
+        .collect()
+    }
+
+    fn can_run_pods(&self) -> bool {
+        self.runc.is_some() && self.skopeo.is_some() && self.umoci.is_some()
+    }
+}
+
+/// Workload kinds supported given what was found on the node
+fn supported_kinds(pods: bool, functions: bool) -> Vec<WorkloadKind> {
+    let mut kinds = Vec::new();
+    if pods {
+        kinds.push(WorkloadKind::Pod);
+    }
+    if functions {
+        kinds.push(WorkloadKind::Function);
+    }
+    kinds
+}
+
+/// Probe the node to know which workloads it is able to run.
+///
+/// Pods need runc, skopeo and umoci. Functions need KVM, a kernel at
+/// `kernel_location` and Firecracker.
+pub fn probe(config: &Configuration, fn_config: &FnConfiguration) -> WorkerCapabilities {
+    let runtimes = Runtimes::probe(config);
+
+    let pods = runtimes.can_run_pods();
+    if !pods {
+        warn!("Pods are not supported on this node: {:?}", runtimes);
+    }
+
+    let kvm = Path::new(KVM_DEVICE).exists();
+    let kernel = fn_config.kernel_location.is_file();
+    let functions = kvm && kernel && runtimes.firecracker.is_some();
+    if !functions {
+        warn!(
+            "Functions are not supported on this node, kvm: {}, kernel ({}): {}, firecracker: {:?}",
+            kvm,
+            fn_config.kernel_location.display(),
+            kernel,
+            runtimes.firecracker
+        );
+    }
+
+    let capabilities = WorkerCapabilities {
+        kinds: supported_kinds(pods, functions)
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        arch: std::env::consts::ARCH.to_string(),
+        runtimes: runtimes.names(),
+    };
+    info!("Node capabilities: {:?}", capabilities);
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_kinds() {
+        assert_eq!(
+            supported_kinds(true, true),
+            vec![WorkloadKind::Pod, WorkloadKind::Function]
+        );
+        assert_eq!(supported_kinds(false, true), vec![WorkloadKind::Function]);
+        assert!(supported_kinds(false, false).is_empty());
+    }
+
+    #[test]
+    fn test_runtimes_names() {
+        let runtimes = Runtimes {
+            runc: Some(PathBuf::from("/usr/bin/runc")),
+            firecracker: Some(PathBuf::from("/usr/bin/firecracker")),
+            ..Default::default()
+        };
+        assert_eq!(runtimes.names(), vec!["runc", "firecracker"]);
+        assert!(!runtimes.can_run_pods());
+    }
+}
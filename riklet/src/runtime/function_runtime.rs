@@ -1,14 +1,16 @@
 use crate::cli::config::Configuration as CliConfiguration;
 use crate::constants::DEFAULT_FIRECRACKER_WORKSPACE;
+use crate::logs;
+use crate::metrics::VM_BOOT_DURATION;
 use crate::net_utils::generate_mac_addr;
 use crate::runtime::Result;
 use crate::{
     cli::function_config::FnConfiguration,
     runtime::{network::RuntimeNetwork, RuntimeError},
-    structs::WorkloadDefinition,
+    structs::{PortMapping, WorkloadDefinition},
 };
 use async_trait::async_trait;
-use curl::easy::Easy;
+use curl::easy::{Easy, List};
 use firepilot::builder::drive::DriveBuilder;
 use firepilot::builder::executor::FirecrackerExecutorBuilder;
 use firepilot::builder::kernel::KernelBuilder;
@@ -16,17 +18,26 @@ use firepilot::builder::network_interface::NetworkInterfaceBuilder;
 use firepilot::builder::{Builder, Configuration};
 use firepilot::machine::Machine;
 use proto::worker::InstanceScheduling;
+use serde_json::json;
 use std::{
+    convert::TryFrom,
     fs,
     fs::File,
     io::Write,
+    net::Ipv4Addr,
     path::{Path, PathBuf},
 };
-use tracing::{debug, error, event, trace, Level};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, event, trace, warn, Level};
 
 use super::{network::function_network::FunctionRuntimeNetwork, Runtime, RuntimeManager};
 
 const BOOT_ARGS_STATIC: &str = "console=ttyS0 reboot=k nomodules random.trust_cpu=on panic=1 pci=off tsc=reliable i8042.nokbd i8042.noaux quiet loglevel=0";
+/// API socket of Firecracker, in the directory of the microVM
+const API_SOCKET: &str = "firecracker.socket";
+/// Standard output of Firecracker, where the serial console of the guest is
+/// written, redirected by firepilot in the directory of the microVM
+const VM_STDOUT: &str = "stdout.log";
 
 struct FunctionRuntime {
     id: String,
@@ -38,6 +49,52 @@ struct FunctionRuntime {
     /// microVM instance, expected to be None when nothing is running, and expected to
     /// to be fullfilled when the microVM is running
     machine: Option<Machine>,
+    /// Task copying the serial console of the microVM to the logs of the instance
+    console: Option<JoinHandle<()>>,
+}
+
+/// Write the log of Firecracker to `log_path`, must be done before the
+/// microVM is started
+fn configure_logger(socket: &Path, log_path: &Path) -> Result<()> {
+    // Firecracker only opens existing files
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent).map_err(RuntimeError::IoError)?;
+    }
+    File::create(log_path).map_err(RuntimeError::IoError)?;
+
+    let body = json!({
+        "log_path": log_path,
+        "level": "Info",
+        "show_level": true,
+        "show_log_origin": false,
+    })
+    .to_string();
+    let mut headers = List::new();
+    headers
+        .append("Content-Type: application/json")
+        .map_err(RuntimeError::FetchingError)?;
+
+    let mut easy = Easy::new();
+    easy.unix_socket(&socket.to_string_lossy())
+        .map_err(RuntimeError::FetchingError)?;
+    easy.url("http://localhost/logger")
+        .map_err(RuntimeError::FetchingError)?;
+    easy.custom_request("PUT")
+        .map_err(RuntimeError::FetchingError)?;
+    easy.http_headers(headers)
+        .map_err(RuntimeError::FetchingError)?;
+    easy.post_fields_copy(body.as_bytes())
+        .map_err(RuntimeError::FetchingError)?;
+    easy.perform().map_err(RuntimeError::FetchingError)?;
+
+    let response_code = easy.response_code().map_err(RuntimeError::FetchingError)?;
+    if response_code != 204 {
+        return Err(RuntimeError::Error(format!(
+            "Response code from Firecracker: {}",
+            response_code
+        )));
+    }
+    Ok(())
 }
 
 impl FunctionRuntime {
@@ -94,21 +151,33 @@ impl FunctionRuntime {
 
         Ok(config)
     }
-}
 
-#[async_trait]
-impl Runtime for FunctionRuntime {
-    #[tracing::instrument(skip(self), fields(id = %self.id))]
-    async fn up(&mut self) -> Result<()> {
-        debug!("Pre-boot configuration for microVM");
+    /// Collect the serial console of the guest and the log of Firecracker in
+    /// the logs of the instance, which is not worth failing the boot for
+    async fn capture_logs(&mut self) {
+        let vm_directory = Path::new(DEFAULT_FIRECRACKER_WORKSPACE).join(&self.id);
+        self.console = Some(logs::mirror(
+            vm_directory.join(VM_STDOUT),
+            logs::log_path(&self.id, logs::CONSOLE),
+        ));
 
-        // Define tap name
-        self.network
-            .init()
+        let log_path = logs::log_path(&self.id, logs::FIRECRACKER);
+        let socket = vm_directory.join(API_SOCKET);
+        // The request to the API of Firecracker blocks
+        let configured = tokio::task::spawn_blocking(move || configure_logger(&socket, &log_path))
             .await
-            .map_err(RuntimeError::NetworkError)?;
+            .unwrap_or_else(|e| Err(RuntimeError::Error(e.to_string())));
+        if let Err(e) = configured {
+            warn!(
+                "Could not configure the Firecracker log of {}: {}",
+                self.id, e
+            );
+        }
+    }
 
-        let vm_config = self.generate_microvm_config()?;
+    /// Create the microVM and start it once its network is ready
+    #[tracing::instrument(name = "vm_boot", skip_all, fields(id = %self.id))]
+    async fn boot(&mut self, vm_config: Configuration) -> Result<Machine> {
         let mut machine = Machine::new();
 
         // Copy files and spawn the microVM socket, but it doesn't start the microVM
@@ -116,6 +185,7 @@ impl Runtime for FunctionRuntime {
             .create(vm_config)
             .await
             .map_err(RuntimeError::FirecrackerError)?;
+        self.capture_logs().await;
 
         // Applies IP to TAP and rules
         self.network
@@ -128,6 +198,26 @@ impl Runtime for FunctionRuntime {
             .start()
             .await
             .map_err(RuntimeError::FirecrackerError)?;
+        Ok(machine)
+    }
+}
+
+#[async_trait]
+impl Runtime for FunctionRuntime {
+    #[tracing::instrument(skip(self), fields(id = %self.id))]
+    async fn up(&mut self) -> Result<()> {
+        debug!("Pre-boot configuration for microVM");
+
+        // Define tap name
+        self.network
+            .init()
+            .await
+            .map_err(RuntimeError::NetworkError)?;
+
+        let vm_config = self.generate_microvm_config()?;
+        let boot_timer = VM_BOOT_DURATION.start_timer();
+        let machine = self.boot(vm_config).await?;
+        boot_timer.observe_duration();
         self.machine = Some(machine);
         Ok(())
     }
@@ -151,6 +241,9 @@ impl Runtime for FunctionRuntime {
             .await
             .map_err(RuntimeError::FirecrackerError)?;
         debug!("microVM properly stopped");
+        if let Some(console) = self.console.take() {
+            console.abort();
+        }
 
         debug!("Destroying function runtime network");
         self.network
@@ -158,6 +251,22 @@ impl Runtime for FunctionRuntime {
             .await
             .map_err(RuntimeError::NetworkError)
     }
+
+    fn guest_ip(&self) -> Option<Ipv4Addr> {
+        Some(self.network.guest_ip)
+    }
+
+    fn port_mapping(&self) -> Vec<PortMapping> {
+        self.network.port_mapping.clone()
+    }
+}
+
+impl Drop for FunctionRuntime {
+    fn drop(&mut self) {
+        if let Some(console) = self.console.take() {
+            console.abort();
+        }
+    }
 }
 
 pub struct FunctionRuntimeManager {}
@@ -236,9 +345,8 @@ impl RuntimeManager for FunctionRuntimeManager {
         _config: CliConfiguration,
     ) -> super::Result<Box<dyn Runtime>> {
         event!(Level::DEBUG, "Function workload detected");
-        let workload_definition: WorkloadDefinition =
-            serde_json::from_str(workload.definition.as_str())
-                .map_err(RuntimeError::ParsingError)?;
+        let workload_definition =
+            WorkloadDefinition::try_from(&workload).map_err(RuntimeError::ParsingError)?;
 
         let fn_config = FnConfiguration::load().map_err(|e| RuntimeError::Error(e.to_string()))?;
 
@@ -248,6 +356,7 @@ impl RuntimeManager for FunctionRuntimeManager {
             network: FunctionRuntimeNetwork::new(&workload, fn_config.iface)
                 .map_err(RuntimeError::NetworkError)?,
             machine: None,
+            console: None,
             id: workload.instance_id,
         }))
     }
@@ -0,0 +1,258 @@
+//! Infra process of the pods. It does nothing but hold the IPC namespace of a
+//! pod, and its PID namespace when the containers share one, so they can be
+//! joined by the containers and outlive their restarts.
+//!
+//! When it holds the PID namespace it is its init, and reaps the processes
+//! orphaned in the pod. The main processes of the containers may be among
+//! them, so the exits it reaps are written back to the riklet, which would
+//! not know their codes otherwise.
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::sched::{clone, CloneFlags};
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::{close, pipe2, Pid};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Size of the stack of the infra process, which only waits for its children
+const STACK_SIZE: usize = 64 * 1024;
+/// Descriptors closed one by one when the kernel can't close them at once
+const MAX_INHERITED_FD: libc::c_int = 65536;
+/// Descriptor the infra process writes the exits it reaps to
+const EXITS_FD: libc::c_int = 3;
+/// Size of the record of an exit: the pid of the process in the namespace
+/// of the pod, then its wait status
+const EXIT_RECORD_SIZE: usize = 8;
+/// Name of the infra processes, by which the ones left by a previous riklet
+/// are found
+pub const INFRA_PROCESS_NAME: &str = "rik-infra";
+
+/// Close the files inherited from the riklet, such as its sockets, which
+/// would be kept open as long as the pod otherwise
+fn close_inherited_files() {
+    // SAFETY: closing descriptors is async-signal-safe
+    unsafe {
+        if libc::syscall(libc::SYS_close_range, EXITS_FD + 1, libc::c_uint::MAX, 0) < 0 {
+            for fd in EXITS_FD + 1..MAX_INHERITED_FD {
+                libc::close(fd);
+            }
+        }
+    }
+}
+
+/// Wait for the children of the process forever, writing their exits to
+/// `exits`
+fn reap_forever(exits: RawFd) -> isize {
+    // SAFETY: anything done in a process cloned from a multi-threaded one has
+    // to be async-signal-safe, as dup2, prctl, waitpid, write and sleep are
+    unsafe {
+        if exits != EXITS_FD {
+            libc::dup2(exits, EXITS_FD);
+        }
+        libc::prctl(
+            libc::PR_SET_NAME,
+            b"rik-infra\0".as_ptr() as libc::c_ulong,
+            0,
+            0,
+            0,
+        );
+    }
+    close_inherited_files();
+    loop {
+        let mut status = 0;
+        let pid = unsafe { libc::waitpid(-1, &mut status, 0) };
+        if pid < 0 {
+            unsafe { libc::sleep(1) };
+            continue;
+        }
+        let mut record = [0u8; EXIT_RECORD_SIZE];
+        record[..4].copy_from_slice(&pid.to_ne_bytes());
+        record[4..].copy_from_slice(&status.to_ne_bytes());
+        // Records fit in a pipe write, so they are written whole or not at
+        // all when the riklet lags behind
+        unsafe {
+            libc::write(
+                EXITS_FD,
+                record.as_ptr() as *const libc::c_void,
+                record.len(),
+            )
+        };
+    }
+}
+
+/// Codes of the processes of a pod reaped by its infra process, by pid in
+/// the namespace of the pod. Only the watched processes are kept.
+#[derive(Debug, Clone, Default)]
+pub struct Exits(Arc<Mutex<HashMap<i32, Option<i32>>>>);
+
+impl Exits {
+    /// Keep the code of the process `pid` once it exits
+    pub fn watch(&self, pid: i32) {
+        self.0.lock().unwrap().insert(pid, None);
+    }
+
+    /// Code of the watched process `pid`, once it exited
+    pub fn take(&self, pid: i32) -> Option<i32> {
+        let mut exits = self.0.lock().unwrap();
+        let code = exits.get(&pid).copied().flatten()?;
+        exits.remove(&pid);
+        Some(code)
+    }
+
+    fn record(&self, pid: i32, status: i32) {
+        if let Some(code) = self.0.lock().unwrap().get_mut(&pid) {
+            *code = WaitStatus::from_raw(Pid::from_raw(pid), status)
+                .ok()
+                .and_then(super::supervisor::exit_code_of);
+        }
+    }
+
+    /// Record the exits written by the infra process on `pipe`, until it is
+    /// gone
+    fn read_from(&self, pipe: RawFd) {
+        let exits = self.clone();
+        // SAFETY: the read end of the pipe is only owned by the file
+        let mut pipe = unsafe { File::from_raw_fd(pipe) };
+        std::thread::spawn(move || {
+            let mut record = [0u8; EXIT_RECORD_SIZE];
+            while pipe.read_exact(&mut record).is_ok() {
+                let mut pid = [0u8; 4];
+                let mut status = [0u8; 4];
+                pid.copy_from_slice(&record[..4]);
+                status.copy_from_slice(&record[4..]);
+                exits.record(i32::from_ne_bytes(pid), i32::from_ne_bytes(status));
+            }
+        });
+    }
+}
+
+#[derive(Debug)]
+pub struct InfraProcess {
+    pid: Pid,
+    exits: Exits,
+}
+
+impl InfraProcess {
+    /// Start the infra process in a new IPC namespace, and in a new PID
+    /// namespace too when `share_pid`
+    pub fn spawn(share_pid: bool) -> nix::Result<Self> {
+        let mut flags = CloneFlags::CLONE_NEWIPC;
+        if share_pid {
+            flags |= CloneFlags::CLONE_NEWPID;
+        }
+        let (read, write) = pipe2(OFlag::O_CLOEXEC)?;
+        // The infra process drops the exits the riklet doesn't read rather
+        // than stop reaping
+        if let Err(e) = fcntl(write, FcntlArg::F_SETFL(OFlag::O_NONBLOCK)) {
+            let _ = close(read);
+            let _ = close(write);
+            return Err(e);
+        }
+        let mut stack = vec![0u8; STACK_SIZE];
+        let pid = clone(
+            Box::new(move || reap_forever(write)),
+            &mut stack,
+            flags,
+            Some(libc::SIGCHLD),
+        );
+        // The infra process holds the only write end left, so the pipe ends
+        // along with it
+        if let Err(e) = close(write) {
+            warn!("Could not close the exits pipe of the infra process: {}", e);
+        }
+        let pid = match pid {
+            Ok(pid) => pid,
+            Err(e) => {
+                let _ = close(read);
+                return Err(e);
+            }
+        };
+
+        let exits = Exits::default();
+        exits.read_from(read);
+        Ok(InfraProcess { pid, exits })
+    }
+
+    /// Exits of the processes of the pod reaped by the infra process
+    pub fn exits(&self) -> Exits {
+        self.exits.clone()
+    }
+
+    /// Namespace of `kind`, e.g. `ipc`, held by the process
+    pub fn namespace_path(&self, kind: &str) -> PathBuf {
+        PathBuf::from(format!("/proc/{}/ns/{}", self.pid, kind))
+    }
+
+    /// Kill the process and reap it. Its namespaces go along once no
+    /// container is left in them.
+    pub fn stop(self) -> nix::Result<()> {
+        match kill(self.pid, Signal::SIGKILL) {
+            Ok(()) | Err(Errno::ESRCH) => (),
+            Err(e) => return Err(e),
+        }
+        match waitpid(self.pid, None) {
+            Ok(_) | Err(Errno::ECHILD) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn_stop() {
+        let namespace = |path: PathBuf| std::fs::read_link(path).unwrap();
+
+        let infra = InfraProcess::spawn(true).unwrap();
+        for kind in ["ipc", "pid"] {
+            assert_ne!(
+                namespace(infra.namespace_path(kind)),
+                namespace(PathBuf::from(format!("/proc/self/ns/{}", kind)))
+            );
+        }
+        let path = infra.namespace_path("ipc");
+        infra.stop().unwrap();
+        assert!(!path.exists());
+
+        let infra = InfraProcess::spawn(false).unwrap();
+        assert_eq!(
+            namespace(infra.namespace_path("pid")),
+            namespace(PathBuf::from("/proc/self/ns/pid"))
+        );
+        // Named once it runs
+        let name = || std::fs::read_to_string(format!("/proc/{}/comm", infra.pid)).unwrap();
+        for _ in 0..100 {
+            if name().trim() == INFRA_PROCESS_NAME {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(name().trim(), INFRA_PROCESS_NAME);
+        infra.stop().unwrap();
+    }
+
+    #[test]
+    fn test_exits() {
+        let exits = Exits::default();
+        exits.watch(5);
+        exits.record(5, 3 << 8);
+        exits.record(6, 0);
+
+        assert_eq!(exits.take(6), None);
+        assert_eq!(exits.take(5), Some(3));
+        assert_eq!(exits.take(5), None);
+
+        exits.watch(7);
+        assert_eq!(exits.take(7), None);
+        exits.record(7, libc::SIGKILL);
+        assert_eq!(exits.take(7), Some(128 + libc::SIGKILL));
+    }
+}
@@ -1,16 +1,25 @@
+pub mod capabilities;
 pub mod network;
 
 pub mod function_runtime;
+pub mod infra;
+pub mod orphans;
 pub mod pod_runtime;
+pub mod supervisor;
 
 use self::{
     function_runtime::FunctionRuntimeManager, network::NetworkError, pod_runtime::PodRuntimeManager,
 };
-use crate::{cli::config::Configuration, structs::WorkloadDefinition};
+use crate::{
+    cli::config::Configuration,
+    structs::{PortMapping, WorkloadDefinition},
+};
 use async_trait::async_trait;
 use firepilot::{builder::BuilderError, machine::FirepilotError};
 use proto::worker::InstanceScheduling;
+use proto::ConversionError;
 use std::fmt::Debug;
+use std::net::Ipv4Addr;
 use thiserror::Error;
 use tracing::error;
 
@@ -29,7 +38,7 @@ pub enum RuntimeError {
     IoError(std::io::Error),
 
     #[error("Parsing error: {0}")]
-    ParsingError(serde_json::Error),
+    ParsingError(ConversionError),
 
     #[error("OCI error: {0}")]
     OciError(oci::Error),
@@ -47,12 +56,40 @@ pub enum RuntimeError {
     NotRunning(String),
 }
 
+impl RuntimeError {
+    /// Short cause of the failure, reported along with the instance status
+    pub fn reason(&self) -> &'static str {
+        match self {
+            RuntimeError::Error(_) => "RuntimeError",
+            RuntimeError::NetworkError(_) => "NetworkError",
+            RuntimeError::FetchingError(_) => "FetchError",
+            RuntimeError::IoError(_) => "IoError",
+            RuntimeError::ParsingError(_) => "InvalidDefinition",
+            RuntimeError::OciError(_) => "ImageError",
+            RuntimeError::CriError(_) => "ContainerError",
+            RuntimeError::FirecrackerError(_) => "VmError",
+            RuntimeError::FirepilotConfiguration(_) => "InvalidVmConfiguration",
+            RuntimeError::NotRunning(_) => "NotRunning",
+        }
+    }
+}
+
 type Result<T> = std::result::Result<T, RuntimeError>;
 
 #[async_trait]
 pub trait Runtime: Send + Sync {
     async fn up(&mut self) -> Result<()>;
     async fn down(&mut self) -> Result<()>;
+
+    /// IP address of the guest, for runtimes giving one to each instance
+    fn guest_ip(&self) -> Option<Ipv4Addr> {
+        None
+    }
+
+    /// Ports of the node the instance is exposed on
+    fn port_mapping(&self) -> Vec<PortMapping> {
+        Vec::new()
+    }
 }
 
 #[async_trait]
@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use ipnetwork::Ipv4Network;
 use proto::worker::InstanceScheduling;
+use std::convert::TryFrom;
 use std::net::Ipv4Addr;
 use tracing::{debug, error};
 
@@ -9,7 +10,7 @@ use crate::iptables::Chain;
 use crate::net_utils::{self, get_iptables_riklet_chain};
 use crate::{
     iptables::{rule::Rule, Iptables, MutateIptables, Table},
-    structs::WorkloadDefinition,
+    structs::{PortMapping, WorkloadDefinition},
 };
 
 use super::{NetworkError, Result, RuntimeNetwork, IP_ALLOCATOR};
@@ -25,7 +26,7 @@ pub struct FunctionRuntimeNetwork {
     /// Host tap interface IP
     pub host_ip: Ipv4Addr,
     /// A mapping of exposed port to internal port
-    pub port_mapping: Vec<(u16, u16)>,
+    pub port_mapping: Vec<PortMapping>,
     /// A unique name for the tap interface
     pub tap: Option<String>,
     pub iptables: Iptables,
@@ -48,9 +49,8 @@ impl FunctionRuntimeNetwork {
     pub fn new(workload: &InstanceScheduling, gateway_iface: String) -> Result<Self> {
         let mask_long: &str = "255.255.255.252";
 
-        let workload_definition: WorkloadDefinition =
-            serde_json::from_str(workload.definition.as_str())
-                .map_err(NetworkError::ParsingError)?;
+        let workload_definition =
+            WorkloadDefinition::try_from(workload).map_err(NetworkError::ParsingError)?;
 
         // Alocate ip range for tap interface and firecracker micro VM
         let subnet = IP_ALLOCATOR
@@ -109,11 +109,14 @@ impl FunctionRuntimeNetwork {
         rules.push(filter_conntrack);
         rules.push(rule);
         // port mapping
-        for (exposed_port, internal_port) in self.port_mapping.iter() {
+        for mapping in self.port_mapping.iter() {
             let rule = Rule {
                 rule: format!(
-                    "-p tcp --dport {} -j DNAT --to-destination {}:{}",
-                    exposed_port, self.guest_ip, internal_port
+                    "-p {} --dport {} -j DNAT --to-destination {}:{}",
+                    mapping.protocol.iptables_name(),
+                    mapping.host_port,
+                    self.guest_ip,
+                    mapping.target_port
                 ),
                 chain: get_iptables_riklet_chain(),
                 table: Table::Nat,
@@ -212,6 +215,7 @@ impl RuntimeNetwork for FunctionRuntimeNetwork {
 mod tests {
     use std::{net::Ipv4Addr, process::Command};
 
+    use definition::workload::Protocol;
     use serial_test::serial;
     use tracing::trace;
 
@@ -219,6 +223,7 @@ mod tests {
         iptables::{rule::Rule, Iptables, MutateIptables, Table},
         net_utils::{get_default_iface, get_iptables_riklet_chain},
         runtime::network::{GlobalRuntimeNetwork, RuntimeNetwork},
+        structs::PortMapping,
     };
 
     use super::FunctionRuntimeNetwork;
@@ -263,7 +268,7 @@ mod tests {
 
     fn create_function_network_rt(
         tap_name: &str,
-        port_mapping: &Vec<(u16, u16)>,
+        port_mapping: &Vec<PortMapping>,
     ) -> FunctionRuntimeNetwork {
         FunctionRuntimeNetwork {
             identifier: "test".to_string(),
@@ -290,11 +295,23 @@ mod tests {
     #[tokio::test]
     #[serial]
     async fn apply_exposure_network_routing() {
-        let mut network = GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string()).unwrap();
+        let mut network =
+            GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string(), Default::default()).unwrap();
         let result = network.init().await;
         assert!(result.is_ok());
 
-        let exposed_port = vec![(8080, 8080)];
+        let exposed_port = vec![
+            PortMapping {
+                host_port: 8080,
+                target_port: 8080,
+                protocol: Protocol::Tcp,
+            },
+            PortMapping {
+                host_port: 8053,
+                target_port: 53,
+                protocol: Protocol::Udp,
+            },
+        ];
         let mut fn_rt = create_function_network_rt("riklet010", &exposed_port);
         open_tap_shell(fn_rt.tap_name().unwrap().as_str()).unwrap();
         fn_rt.up_routing().unwrap();
@@ -303,11 +320,14 @@ mod tests {
         let mut rules: Vec<Rule> = vec![];
 
         // Register expected rules
-        for (exposed_port, internal_port) in fn_rt.port_mapping.iter() {
+        for mapping in fn_rt.port_mapping.iter() {
             let rule = Rule {
                 rule: format!(
-                    "-p tcp --dport {} -j DNAT --to-destination {}:{}",
-                    exposed_port, fn_rt.guest_ip, internal_port
+                    "-p {} --dport {} -j DNAT --to-destination {}:{}",
+                    mapping.protocol.iptables_name(),
+                    mapping.host_port,
+                    fn_rt.guest_ip,
+                    mapping.target_port
                 ),
                 chain: get_iptables_riklet_chain(),
                 table: Table::Nat,
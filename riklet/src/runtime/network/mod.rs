@@ -2,14 +2,20 @@ pub mod function_network;
 pub mod pod_network;
 
 use async_trait::async_trait;
-use once_cell::sync::Lazy;
+use definition::workload::Protocol;
+use once_cell::sync::{Lazy, OnceCell};
+use proto::ConversionError;
 use shared::utils::ip_allocator::IpAllocator;
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::sync::Mutex;
 use thiserror::Error;
 
+use crate::cli::config::PodNetworkConfiguration;
+use crate::constants::DEFAULT_NODE_PORT_RANGE;
 use crate::iptables::rule::Rule;
 use crate::iptables::{Chain, Iptables, IptablesError, MutateIptables, Table};
+use crate::net_utils;
 
 // Initialize Singleton for IpAllocator
 static IP_ALLOCATOR: Lazy<Mutex<IpAllocator>> = Lazy::new(|| {
@@ -17,6 +23,66 @@ static IP_ALLOCATOR: Lazy<Mutex<IpAllocator>> = Lazy::new(|| {
     Mutex::new(ip_allocator)
 });
 
+/// Addresses of the pods, taken from the pod CIDR the first time one is
+/// needed. The network address, the bridge and the broadcast address are
+/// never given.
+static POD_IP_ALLOCATOR: OnceCell<Mutex<IpAllocator>> = OnceCell::new();
+
+fn pod_ip_allocator(network: &PodNetworkConfiguration) -> Result<&'static Mutex<IpAllocator>> {
+    POD_IP_ALLOCATOR.get_or_try_init(|| {
+        let mut allocator = IpAllocator::with_subnets(network.cidr, 32)
+            .map_err(|e| NetworkError::Error(format!("Invalid pod CIDR: {}", e)))?;
+        allocator.reserve(network.cidr.network().into());
+        allocator.reserve(pod_gateway(network)?.into());
+        allocator.reserve(network.cidr.broadcast().into());
+        Ok(Mutex::new(allocator))
+    })
+}
+
+/// Ports of the node held by the pods, a port being taken once per protocol
+static NODE_PORTS: Lazy<Mutex<HashSet<(u16, Protocol)>>> = Lazy::new(Default::default);
+
+/// Hold a port of the node for `protocol`: the requested one, which must be
+/// in [DEFAULT_NODE_PORT_RANGE], or given 0 the first free one of it
+fn reserve_node_port(requested: u16, protocol: Protocol) -> Result<u16> {
+    let mut ports = NODE_PORTS.lock().unwrap();
+    let port = match requested {
+        0 => DEFAULT_NODE_PORT_RANGE
+            .find(|port| !ports.contains(&(*port, protocol)))
+            .ok_or_else(|| NetworkError::Error("No more node port available".to_string()))?,
+        port if !DEFAULT_NODE_PORT_RANGE.contains(&port) => {
+            return Err(NetworkError::Error(format!(
+                "Node port {} is out of the range {}-{}",
+                port,
+                DEFAULT_NODE_PORT_RANGE.start,
+                DEFAULT_NODE_PORT_RANGE.end - 1
+            )))
+        }
+        port if ports.contains(&(port, protocol)) => {
+            return Err(NetworkError::Error(format!(
+                "Node port {}/{} is already used",
+                port, protocol
+            )))
+        }
+        port => port,
+    };
+    ports.insert((port, protocol));
+    Ok(port)
+}
+
+/// Give back a port of the node, so it can be held again
+fn release_node_port(port: u16, protocol: Protocol) {
+    NODE_PORTS.lock().unwrap().remove(&(port, protocol));
+}
+
+/// Address of the bridge, through which the pods reach other networks
+fn pod_gateway(network: &PodNetworkConfiguration) -> Result<std::net::Ipv4Addr> {
+    network
+        .cidr
+        .nth(1)
+        .ok_or_else(|| NetworkError::Error(format!("Pod CIDR {} is too small", network.cidr)))
+}
+
 #[derive(Debug, Error)]
 pub enum NetworkError {
     #[error("Network error: {0}")]
@@ -26,7 +92,7 @@ pub enum NetworkError {
     IptablesError(IptablesError),
 
     #[error("Parsing error: {0}")]
-    ParsingError(serde_json::Error),
+    ParsingError(ConversionError),
 
     #[error("Should have been able to apply a valid IP address to the interface, but failed: {0}")]
     InterfaceIPError(String),
@@ -58,17 +124,61 @@ pub struct GlobalRuntimeNetwork {
 
     /// Name of the interface that will be used as the gateway for the network
     gateway_iface: String,
+
+    /// Network the pods are connected to
+    pod_network: PodNetworkConfiguration,
 }
 
 impl GlobalRuntimeNetwork {
-    pub fn new(gateway_iface: String) -> std::result::Result<GlobalRuntimeNetwork, IptablesError> {
+    pub fn new(
+        gateway_iface: String,
+        pod_network: PodNetworkConfiguration,
+    ) -> std::result::Result<GlobalRuntimeNetwork, IptablesError> {
         Ok(GlobalRuntimeNetwork {
             iptables: Iptables::new(true)?,
             gateway_iface,
+            pod_network,
         })
     }
+
+    /// Create the bridge of the pods and let their traffic be forwarded,
+    /// either to the other pods or out of the gateway interface
+    async fn init_pod_network(&mut self) -> Result<()> {
+        let bridge = self.pod_network.bridge.clone();
+        net_utils::create_bridge(
+            bridge.clone(),
+            pod_gateway(&self.pod_network)?,
+            self.pod_network.cidr.prefix(),
+        )
+        .await
+        .map_err(|e| NetworkError::Error(format!("Could not create bridge {}: {}", bridge, e)))?;
+
+        let rules = [
+            format!("-i {} -o {} -j ACCEPT", bridge, self.gateway_iface),
+            format!(
+                "-m conntrack --ctstate RELATED,ESTABLISHED -j ACCEPT -o {}",
+                bridge
+            ),
+            format!("-i {} -o {} -j ACCEPT", bridge, bridge),
+        ];
+        for rule in rules {
+            self.iptables
+                .create(&Rule {
+                    chain: Chain::Forward,
+                    table: Table::Filter,
+                    rule,
+                })
+                .map_err(NetworkError::IptablesError)?;
+        }
+
+        std::fs::write(IP_FORWARD, "1")
+            .map_err(|e| NetworkError::Error(format!("Could not enable IP forwarding: {}", e)))
+    }
 }
 
+/// Whether the host forwards IPv4 packets between its interfaces
+const IP_FORWARD: &str = "/proc/sys/net/ipv4/ip_forward";
+
 #[async_trait]
 impl RuntimeNetwork for GlobalRuntimeNetwork {
     /// Global runtime network init will setup the whole network configuration
@@ -85,6 +195,8 @@ impl RuntimeNetwork for GlobalRuntimeNetwork {
     ///  external network
     /// - Enable conntrack on the Filter table to allow workloads to access the
     ///  external network
+    /// - Create the bridge the pods are connected to, giving it the first
+    ///  address of the pod CIDR, and forward the traffic of the pods
     ///
     /// The usage of the RIKLET chain allows us to prevent the need to repeat
     /// the rule on both PREROUTING and OUTPUT chains.
@@ -122,7 +234,8 @@ impl RuntimeNetwork for GlobalRuntimeNetwork {
         self.iptables
             .create(&nat_masquerade)
             .map_err(NetworkError::IptablesError)?;
-        Ok(())
+
+        self.init_pod_network().await
     }
 
     /// Nothing is needed to be done here, since all the iptable rules and
@@ -135,17 +248,41 @@ impl RuntimeNetwork for GlobalRuntimeNetwork {
 
 #[cfg(test)]
 mod tests {
+    use definition::workload::Protocol;
     use std::net::Ipv4Addr;
 
-    use crate::runtime::network::{GlobalRuntimeNetwork, RuntimeNetwork};
+    use crate::runtime::network::{
+        release_node_port, reserve_node_port, GlobalRuntimeNetwork, RuntimeNetwork,
+    };
     use serial_test::serial;
 
     const GATEWAY_MOCK: &str = "eth0";
 
+    #[test]
+    #[serial]
+    fn test_reserve_node_port() {
+        assert_eq!(reserve_node_port(0, Protocol::Tcp).unwrap(), 30000);
+        assert_eq!(reserve_node_port(30001, Protocol::Tcp).unwrap(), 30001);
+        assert_eq!(reserve_node_port(0, Protocol::Tcp).unwrap(), 30002);
+        assert!(reserve_node_port(30001, Protocol::Tcp).is_err());
+        assert_eq!(reserve_node_port(30001, Protocol::Udp).unwrap(), 30001);
+        assert_eq!(reserve_node_port(0, Protocol::Udp).unwrap(), 30000);
+        assert!(reserve_node_port(22, Protocol::Tcp).is_err());
+        assert!(reserve_node_port(32768, Protocol::Tcp).is_err());
+
+        release_node_port(30000, Protocol::Tcp);
+        assert_eq!(reserve_node_port(0, Protocol::Tcp).unwrap(), 30000);
+        for port in 30000..30003 {
+            release_node_port(port, Protocol::Tcp);
+            release_node_port(port, Protocol::Udp);
+        }
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_network_init_ok() {
-        let mut network = GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string()).unwrap();
+        let mut network =
+            GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string(), Default::default()).unwrap();
         let result = network.init().await;
         assert!(result.is_ok());
         let result = network.destroy().await;
@@ -155,7 +292,8 @@ mod tests {
     #[tokio::test]
     #[serial]
     async fn test_network_init_drop() {
-        let mut network = GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string()).unwrap();
+        let mut network =
+            GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string(), Default::default()).unwrap();
         let result = network.init().await;
         assert!(result.is_ok());
 
@@ -202,11 +340,13 @@ mod tests {
     #[tokio::test]
     #[serial]
     async fn test_multiple_global_network_fails() {
-        let mut network = GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string()).unwrap();
+        let mut network =
+            GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string(), Default::default()).unwrap();
         let result = network.init().await;
         assert!(result.is_ok());
 
-        let mut network2 = GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string()).unwrap();
+        let mut network2 =
+            GlobalRuntimeNetwork::new(GATEWAY_MOCK.to_string(), Default::default()).unwrap();
         let result = network2.init().await;
         assert!(result.is_err());
 
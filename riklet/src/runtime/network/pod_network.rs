@@ -1,26 +1,227 @@
 use async_trait::async_trait;
-use std::fmt::Debug;
+use ipnetwork::Ipv4Network;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
 
-use super::{Result, RuntimeNetwork};
+use crate::cli::config::PodNetworkConfiguration;
+use crate::iptables::{rule::Rule, Chain, Iptables, MutateIptables, Table};
+use crate::net_utils::{self, get_iptables_riklet_chain};
+use crate::structs::PortMapping;
 
-#[derive(Debug)]
-pub struct PodRuntimeNetwork {}
+use super::{
+    pod_gateway, pod_ip_allocator, release_node_port, reserve_node_port, NetworkError, Result,
+    RuntimeNetwork,
+};
+
+/// Name of the interface of the pods
+const POD_IFACE: &str = "eth0";
+/// Prefix of the names of the network namespaces of the pods
+pub const NETNS_PREFIX: &str = "rik-";
+
+/// Network of a pod: its own network namespace, connected to the bridge of
+/// the pods by a veth pair, with an address taken from the pod CIDR. Its node
+/// ports are forwarded to the pod like the ones of functions.
+pub struct PodRuntimeNetwork {
+    /// Unique identifier of the pod instance
+    identifier: String,
+    configuration: PodNetworkConfiguration,
+    /// Address of the pod, once allocated
+    subnet: Option<Ipv4Network>,
+    /// Network namespace of the pod, once created
+    netns: Option<PathBuf>,
+    /// End of the veth pair left on the host, attached to the bridge
+    veth: Option<String>,
+    /// Ports requested on the node, 0 letting the riklet choose, mapped to
+    /// the ports of the pod
+    requested_ports: Vec<PortMapping>,
+    /// Ports of the node held by the pod, mapped to the ports of the pod
+    port_mapping: Vec<PortMapping>,
+    iptables: Iptables,
+    /// Rules created for the node ports, deleted along with the pod
+    rules: Vec<Rule>,
+}
 
 impl PodRuntimeNetwork {
-    pub fn new() -> Self {
-        PodRuntimeNetwork {}
+    /// Creates a new PodRuntimeNetwork, it won't create anything on the system yet
+    ///
+    /// `port_mapping` gives the ports to expose on the node, a host port of
+    /// 0 being chosen by the riklet
+    pub fn new(
+        identifier: String,
+        configuration: PodNetworkConfiguration,
+        port_mapping: Vec<PortMapping>,
+    ) -> Result<Self> {
+        Ok(PodRuntimeNetwork {
+            identifier,
+            configuration,
+            subnet: None,
+            netns: None,
+            veth: None,
+            requested_ports: port_mapping,
+            port_mapping: Vec::new(),
+            iptables: Iptables::new(false).map_err(NetworkError::IptablesError)?,
+            rules: Vec::new(),
+        })
+    }
+
+    /// Address of the pod, once the network is initialized
+    pub fn pod_ip(&self) -> Option<Ipv4Addr> {
+        self.subnet.map(|subnet| subnet.ip())
+    }
+
+    /// Network namespace the containers of the pod join, once created
+    pub fn netns(&self) -> Option<&Path> {
+        self.netns.as_deref()
+    }
+
+    /// Ports of the node held by the pod, once the network is initialized
+    pub fn port_mapping(&self) -> Vec<PortMapping> {
+        self.port_mapping.clone()
+    }
+
+    fn netns_name(&self) -> String {
+        format!("{}{}", NETNS_PREFIX, self.identifier)
+    }
+
+    async fn create(&mut self) -> Result<()> {
+        let gateway = pod_gateway(&self.configuration)?;
+        let subnet = pod_ip_allocator(&self.configuration)?
+            .lock()
+            .unwrap()
+            .allocate_subnet()
+            .ok_or_else(|| NetworkError::Error("No more pod ip available".to_string()))?;
+        self.subnet = Some(subnet);
+
+        let netns = net_utils::create_netns(&self.netns_name())
+            .map_err(|e| NetworkError::Error(format!("Could not create namespace: {}", e)))?;
+        self.netns = Some(netns.clone());
+
+        // The peer is renamed once in the namespace, where it can't conflict
+        let veth = net_utils::new_tap_random_name("veth".to_string());
+        let peer = net_utils::new_tap_random_name("pod".to_string());
+        net_utils::create_veth(
+            veth.clone(),
+            peer.clone(),
+            self.configuration.bridge.clone(),
+            netns.clone(),
+        )
+        .await
+        .map_err(|e| NetworkError::Error(format!("Could not create veth {}: {}", veth, e)))?;
+        self.veth = Some(veth);
+
+        net_utils::configure_netns_link(
+            netns,
+            peer,
+            POD_IFACE.to_string(),
+            subnet.ip(),
+            self.configuration.cidr.prefix(),
+            gateway,
+        )
+        .await
+        .map_err(|e| NetworkError::InterfaceIPError(e.to_string()))?;
+
+        debug!("Pod {} has address {}", self.identifier, subnet.ip());
+
+        for mapping in self.requested_ports.clone() {
+            let host_port = reserve_node_port(mapping.host_port, mapping.protocol)?;
+            self.port_mapping.push(PortMapping {
+                host_port,
+                ..mapping
+            });
+            debug!(
+                "Pod {} exposed on node port {}/{}",
+                self.identifier, host_port, mapping.protocol
+            );
+        }
+        for rule in self.generate_iptables_rules(subnet.ip()) {
+            self.iptables
+                .create(&rule)
+                .map_err(NetworkError::IptablesError)?;
+            self.rules.push(rule);
+        }
+        Ok(())
+    }
+
+    /// Forward each node port to the pod, the traffic being let through
+    /// towards the bridge
+    fn generate_iptables_rules(&self, pod_ip: Ipv4Addr) -> Vec<Rule> {
+        let mut rules = Vec::new();
+        for mapping in self.port_mapping.iter() {
+            let protocol = mapping.protocol.iptables_name();
+            rules.push(Rule {
+                rule: format!(
+                    "-p {} --dport {} -j DNAT --to-destination {}:{}",
+                    protocol, mapping.host_port, pod_ip, mapping.target_port
+                ),
+                chain: get_iptables_riklet_chain(),
+                table: Table::Nat,
+            });
+            rules.push(Rule {
+                rule: format!(
+                    "-d {} -p {} --dport {} -j ACCEPT -o {}",
+                    pod_ip, protocol, mapping.target_port, self.configuration.bridge
+                ),
+                chain: Chain::Forward,
+                table: Table::Filter,
+            });
+        }
+        rules
     }
 }
 
 #[async_trait]
 impl RuntimeNetwork for PodRuntimeNetwork {
+    /// Create the network namespace of the pod and connect it to the bridge.
+    /// What was created is removed if it fails.
+    #[tracing::instrument(skip(self), fields(instance_id = %self.identifier))]
     async fn init(&mut self) -> Result<()> {
-        // todo!()
-        Result::Ok(())
+        let result = self.create().await;
+        if result.is_err() {
+            if let Err(e) = self.destroy().await {
+                warn!("Could not clean up the network of the pod: {}", e);
+            }
+        }
+        result
     }
 
+    /// Remove the node port rules, the veth pair and the network namespace,
+    /// and give back the node ports and the address of the pod
+    #[tracing::instrument(skip(self), fields(instance_id = %self.identifier))]
     async fn destroy(&mut self) -> Result<()> {
-        // todo!()
-        Result::Ok(())
+        let mut result = Ok(());
+        for rule in std::mem::take(&mut self.rules) {
+            if let Err(e) = self.iptables.delete(&rule) {
+                result = Err(NetworkError::IptablesError(e));
+            }
+        }
+        for mapping in self.port_mapping.drain(..) {
+            release_node_port(mapping.host_port, mapping.protocol);
+        }
+        // Deleting one end of the pair deletes the other one
+        if let Some(veth) = self.veth.take() {
+            if let Err(e) = net_utils::delete_link(veth.clone()).await {
+                result = Err(NetworkError::Error(format!(
+                    "Could not delete veth {}: {}",
+                    veth, e
+                )));
+            }
+        }
+        if let Some(netns) = self.netns.take() {
+            if let Err(e) = net_utils::delete_netns(&netns) {
+                result = Err(NetworkError::Error(format!(
+                    "Could not delete namespace {}: {}",
+                    netns.display(),
+                    e
+                )));
+            }
+        }
+        if let Some(subnet) = self.subnet.take() {
+            pod_ip_allocator(&self.configuration)?
+                .lock()
+                .unwrap()
+                .free_subnet(subnet);
+        }
+        result
     }
 }
@@ -0,0 +1,177 @@
+//! What a previous riklet left on the node. The inventory of the riklet is
+//! only kept in memory, so after a restart it reports no instance and the
+//! scheduler reschedules them. What they left is torn down before the riklet
+//! registers, so it doesn't hold the resources of the node anymore.
+use crate::{
+    cli::config::Configuration,
+    constants::{DEFAULT_FIRECRACKER_WORKSPACE, DEFAULT_VOLUMES_DIRECTORY},
+    logs, net_utils,
+};
+use cri::container::{DeleteArgs, Runc};
+use nix::mount::{umount2, MntFlags};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use super::{
+    infra::INFRA_PROCESS_NAME,
+    network::pod_network::NETNS_PREFIX,
+    pod_runtime::{console_socket_path, is_under, CONTAINER_BUNDLES},
+};
+
+/// Tear down the containers, infra processes, microVMs, network namespaces,
+/// volumes and logs of the instances started by a previous riklet
+pub async fn tear_down(config: &Configuration) {
+    delete_containers(config).await;
+    kill_processes();
+    for path in entries(Path::new(net_utils::NETNS_DIRECTORY)) {
+        let is_pod = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(NETNS_PREFIX));
+        if is_pod {
+            match net_utils::delete_netns(&path) {
+                Ok(()) => debug!("Deleted network namespace {}", path.display()),
+                Err(e) => warn!("Could not delete {}: {}", path.display(), e),
+            }
+        }
+    }
+    remove_volumes();
+    for path in entries(Path::new(logs::LOGS_DIRECTORY)) {
+        if let Some(instance_id) = path.file_name().and_then(|name| name.to_str()) {
+            logs::remove(instance_id);
+        }
+    }
+}
+
+/// Paths of the entries of `directory`, none when it doesn't exist
+fn entries(directory: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            warn!("Could not read {}: {}", directory.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// Delete the containers created from the bundles of the riklet, along with
+/// their bundles. Other containers runc knows of are left alone.
+async fn delete_containers(config: &Configuration) {
+    let bundles_directory = match &config.manager.oci_manager.bundles_directory {
+        Some(directory) => directory.join(CONTAINER_BUNDLES),
+        None => return,
+    };
+    let runc = match Runc::new(config.runner.clone()) {
+        Ok(runc) => runc,
+        Err(e) => {
+            warn!("Containers left by the previous riklet kept: {}", e);
+            return;
+        }
+    };
+    let containers = match runc.list().await {
+        Ok(containers) => containers,
+        Err(e) => {
+            warn!("Containers left by the previous riklet kept: {}", e);
+            return;
+        }
+    };
+    for container in containers {
+        let (id, bundle) = match (container.id, container.bundle) {
+            (Some(id), Some(bundle)) => (id, bundle),
+            _ => continue,
+        };
+        if !is_under(Path::new(&bundle), &bundles_directory) {
+            continue;
+        }
+        match runc.delete(&id, Some(&DeleteArgs { force: true })).await {
+            Ok(()) => info!("Deleted container {} left by the previous riklet", id),
+            Err(e) => warn!("Could not delete container {}: {}", id, e),
+        }
+        let _ = fs::remove_file(console_socket_path(&id));
+    }
+    // No container of the riklet is left to use them
+    match fs::remove_dir_all(&bundles_directory) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => warn!("Could not remove {}: {}", bundles_directory.display(), e),
+    }
+}
+
+/// Whether the process named `name` running `cmdline` is an infra process
+/// or a Firecracker process started by a riklet
+fn is_orphan(name: &str, cmdline: &str) -> bool {
+    match name {
+        INFRA_PROCESS_NAME => true,
+        "firecracker" => cmdline.contains(DEFAULT_FIRECRACKER_WORKSPACE),
+        _ => false,
+    }
+}
+
+/// Kill the infra processes of the pods and the Firecracker processes of the
+/// microVMs. They are children of init since the riklet which started them
+/// is gone, which reaps them.
+fn kill_processes() {
+    for path in entries(Path::new("/proc")) {
+        let pid = match path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<i32>().ok())
+        {
+            Some(pid) if pid as u32 != std::process::id() => Pid::from_raw(pid),
+            _ => continue,
+        };
+        let name = fs::read_to_string(path.join("comm")).unwrap_or_default();
+        let cmdline = fs::read(path.join("cmdline")).unwrap_or_default();
+        if !is_orphan(name.trim(), &String::from_utf8_lossy(&cmdline)) {
+            continue;
+        }
+        match kill(pid, Signal::SIGKILL) {
+            Ok(()) => info!("Killed {} {} left by the previous riklet", name.trim(), pid),
+            Err(e) => warn!("Could not kill {} {}: {}", name.trim(), pid, e),
+        }
+    }
+}
+
+/// Unmount the secret volumes of the pods and remove their volumes
+fn remove_volumes() {
+    for directory in entries(Path::new(DEFAULT_VOLUMES_DIRECTORY)) {
+        for volume in entries(&directory) {
+            match umount2(&volume, MntFlags::MNT_DETACH) {
+                // Only secret volumes are mounts
+                Ok(()) | Err(nix::errno::Errno::EINVAL) | Err(nix::errno::Errno::ENOENT) => (),
+                Err(e) => warn!("Could not unmount {}: {}", volume.display(), e),
+            }
+        }
+        match fs::remove_dir_all(&directory) {
+            Ok(()) => debug!("Removed volumes {}", directory.display()),
+            Err(e) => warn!("Could not remove {}: {}", directory.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_orphan() {
+        assert!(is_orphan(INFRA_PROCESS_NAME, ""));
+        assert!(is_orphan(
+            "firecracker",
+            "firecracker\0--api-sock\0/var/lib/riklet/vm/function-1/firecracker.socket\0"
+        ));
+        assert!(!is_orphan(
+            "firecracker",
+            "firecracker\0--api-sock\0/tmp/firecracker.socket\0"
+        ));
+        assert!(!is_orphan("riklet", ""));
+    }
+}
@@ -1,94 +1,658 @@
 use crate::{
     cli::config::Configuration,
+    constants::DEFAULT_VOLUMES_DIRECTORY,
+    exec, logs,
+    metrics::IMAGE_PULL_DURATION,
     runtime::{network::RuntimeNetwork, RuntimeError},
-    structs::WorkloadDefinition,
+    structs::{Container, PortMapping, WorkloadDefinition},
 };
 use async_trait::async_trait;
 use cri::{
     console::ConsoleSocket,
-    container::{CreateArgs, Runc},
+    container::{CreateArgs, DeleteArgs, KillArgs, Runc},
 };
+use definition::workload::{
+    is_dns_label, validate_container_names, validate_volumes, SecretItems, Volume, VolumeMount,
+    VolumeSource,
+};
+use nix::mount::{mount, umount2, MntFlags, MsFlags};
 
+use oci::bundle::BundleConfig;
 use oci::image_manager::ImageManager;
 use proto::worker::InstanceScheduling;
-use std::path::PathBuf;
-use tracing::{error, event, Level};
+use std::convert::TryFrom;
+use std::net::Ipv4Addr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, event, info_span, warn, Instrument, Level};
+
+use super::{
+    infra::InfraProcess,
+    network::pod_network::PodRuntimeNetwork,
+    supervisor::{self, Supervised},
+    Runtime, RuntimeManager,
+};
 
-use super::{network::pod_network::PodRuntimeNetwork, Runtime, RuntimeManager};
+/// Delay between two checks of the state of stopping containers
+const STOP_INTERVAL: Duration = Duration::from_millis(100);
+/// Time given to killed containers to be reaped
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+/// Directory of the bundles of the containers, under the bundles directory
+pub const CONTAINER_BUNDLES: &str = "containers";
+/// Resolver configuration of the host, given to the containers
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+/// Resolver configuration of systemd-resolved, naming the actual servers
+/// rather than its stub listening on the loopback of the host
+const SYSTEMD_RESOLV_CONF: &str = "/run/systemd/resolve/resolv.conf";
 
-#[derive(Debug)]
 struct PodRuntime {
     image_manager: ImageManager,
     workload_definition: WorkloadDefinition,
     network: PodRuntimeNetwork,
-    container_runtime: Runc,
+    /// Process holding the namespaces shared by the containers, once started
+    infra: Option<InfraProcess>,
+    container_runtime: Arc<Runc>,
     instance_id: String,
+    /// Directory where the bundles of the images and the containers are
+    bundles_directory: PathBuf,
+    /// Ids of the containers started, or being started, by the runtime
+    containers: Vec<String>,
+    /// Bundles of the images the containers were created from
+    bundles: Vec<PathBuf>,
+    /// Tasks waiting for the consoles of the containers, shared with the
+    /// supervisor which starts new ones when restarting containers
+    consoles: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    /// Task watching the containers once started
+    supervisor: Option<JoinHandle<()>>,
 }
 
-#[async_trait]
-impl Runtime for PodRuntime {
-    async fn up(&mut self) -> super::Result<()> {
+/// Path of the socket runc hands the console of a container over
+pub fn console_socket_path(id: &str) -> PathBuf {
+    PathBuf::from(format!("/tmp/{}", id))
+}
+
+/// Start a container from its bundle, with its console captured in its log
+/// and kept for the sessions. Gives the task waiting for the console.
+pub(super) async fn start_container(
+    runtime: &Arc<Runc>,
+    instance_id: &str,
+    name: &str,
+    id: &str,
+    bundle: &Path,
+) -> super::Result<JoinHandle<()>> {
+    // New console socket for the container
+    let socket_path = console_socket_path(id);
+    let console_socket = ConsoleSocket::new(&socket_path).map_err(RuntimeError::CriError)?;
+
+    exec::register(instance_id, name, id, runtime.clone());
+    let instance = instance_id.to_string();
+    let container = name.to_string();
+    let console = tokio::spawn(async move {
+        match console_socket.receive_pty_master().await {
+            Ok(pty_master) => {
+                // Sessions write to the console while its output is captured
+                match pty_master.try_clone() {
+                    Ok(console) => exec::set_console(&instance, &container, console),
+                    Err(e) => event!(Level::WARN, "Console not kept: {}", e),
+                }
+                logs::capture(pty_master, logs::log_path(&instance, &container))
+            }
+            Err(err) => {
+                event!(Level::ERROR, "Receive PTY master error : {:?}", err)
+            }
+        }
+    });
+    let result = runtime
+        .run(
+            id,
+            bundle,
+            Some(&CreateArgs {
+                pid_file: None,
+                console_socket: Some(socket_path),
+                no_pivot: false,
+                no_new_keyring: false,
+                detach: true,
+            }),
+        )
+        .await;
+    if let Err(e) = result {
+        // runc won't connect, the socket is removed along with the task
+        console.abort();
+        return Err(RuntimeError::CriError(e));
+    }
+    Ok(console)
+}
+
+/// Whether `path` is in `directory`, without going up out of it
+pub fn is_under(path: &Path, directory: &Path) -> bool {
+    path.starts_with(directory)
+        && path
+            .components()
+            .all(|component| component != Component::ParentDir)
+}
+
+/// Image bundles no container is created from anymore, either directly or
+/// by using its root filesystem
+fn unused_bundles(bundles: &[PathBuf], containers: &[cri::Container]) -> Vec<PathBuf> {
+    bundles
+        .iter()
+        .filter(|bundle| {
+            !containers.iter().any(|container| {
+                container.bundle.as_deref().map(Path::new) == Some(bundle.as_path())
+                    || container
+                        .rootfs
+                        .as_deref()
+                        .map_or(false, |rootfs| Path::new(rootfs).starts_with(bundle))
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Files of the node mounted for `mount`, from the volumes of the pod, its
+/// emptyDir and secret volumes being in `volumes_directory`, along with
+/// whether they are mounted read-only, which secrets always are
+fn mount_source(
+    volumes: &[Volume],
+    mount: &VolumeMount,
+    volumes_directory: &Path,
+) -> super::Result<(PathBuf, bool)> {
+    if !Path::new(&mount.mount_path).is_absolute() {
+        return Err(RuntimeError::Error(format!(
+            "Mount path {} of volume {} is not absolute",
+            mount.mount_path, mount.name
+        )));
+    }
+    let volume = volumes
+        .iter()
+        .find(|volume| volume.name == mount.name)
+        .ok_or_else(|| RuntimeError::Error(format!("Volume {} not found", mount.name)))?;
+    if !is_dns_label(&volume.name) {
+        return Err(RuntimeError::Error(format!(
+            "Invalid volume name {}",
+            volume.name
+        )));
+    }
+    match &volume.source {
+        VolumeSource::EmptyDir {} => Ok((volumes_directory.join(&volume.name), mount.read_only)),
+        VolumeSource::Secret { .. } => Ok((volumes_directory.join(&volume.name), true)),
+        VolumeSource::HostPath { path } if Path::new(path).is_absolute() => {
+            Ok((PathBuf::from(path), mount.read_only))
+        }
+        VolumeSource::HostPath { path } => Err(RuntimeError::Error(format!(
+            "Host path {} of volume {} is not absolute",
+            path, volume.name
+        ))),
+    }
+}
+
+/// `name` joined to `parent`, refused unless it is right under it so nothing
+/// is ever created or removed out of `parent`
+fn path_under(parent: &Path, name: &str) -> super::Result<PathBuf> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(parent.join(name)),
+        _ => Err(RuntimeError::Error(format!(
+            "{} is not a valid name under {}",
+            name,
+            parent.display()
+        ))),
+    }
+}
+
+/// Write the values of a secret in files named after their keys, in a
+/// tmpfs mounted at `path` so they never reach the disk of the node
+fn write_secret_volume(path: &Path, items: &SecretItems) -> super::Result<()> {
+    std::fs::create_dir_all(path).map_err(RuntimeError::IoError)?;
+    mount(
+        Some("tmpfs"),
+        path,
+        Some("tmpfs"),
+        MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+        Some("mode=0755"),
+    )
+    .map_err(|e| RuntimeError::Error(format!("Could not mount {}: {}", path.display(), e)))?;
+    for (key, value) in &items.0 {
+        if key.is_empty() || key == "." || key == ".." || key.contains('/') {
+            return Err(RuntimeError::Error(format!("Invalid secret key {}", key)));
+        }
+        let file = path.join(key);
+        std::fs::write(&file, value).map_err(RuntimeError::IoError)?;
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o644))
+            .map_err(RuntimeError::IoError)?;
+    }
+    Ok(())
+}
+
+/// Resolver configuration the containers are given
+fn resolv_conf() -> &'static Path {
+    match Path::new(SYSTEMD_RESOLV_CONF).exists() {
+        true => Path::new(SYSTEMD_RESOLV_CONF),
+        false => Path::new(RESOLV_CONF),
+    }
+}
+
+impl PodRuntime {
+    /// Bundle of the container `id`
+    fn container_bundle(&self, id: &str) -> super::Result<PathBuf> {
+        path_under(&self.bundles_directory.join(CONTAINER_BUNDLES), id)
+    }
+
+    /// Directory of the emptyDir and secret volumes of the pod, named after
+    /// its instance
+    fn volumes_directory(&self) -> super::Result<PathBuf> {
+        if !is_dns_label(&self.instance_id) {
+            return Err(RuntimeError::Error(format!(
+                "Invalid instance id {}",
+                self.instance_id
+            )));
+        }
+        path_under(Path::new(DEFAULT_VOLUMES_DIRECTORY), &self.instance_id)
+    }
+
+    /// Create the emptyDir volumes of the pod, writable by any user the
+    /// containers run as, and its secret volumes
+    fn create_volumes(&self) -> super::Result<()> {
+        // Their names are paths under the volumes directory of the pod
+        validate_volumes(&self.workload_definition.spec.volumes).map_err(RuntimeError::Error)?;
+        let directory = self.volumes_directory()?;
+        for volume in &self.workload_definition.spec.volumes {
+            let path = directory.join(&volume.name);
+            match &volume.source {
+                VolumeSource::EmptyDir {} => {
+                    std::fs::create_dir_all(&path).map_err(RuntimeError::IoError)?;
+                    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o777))
+                        .map_err(RuntimeError::IoError)?;
+                }
+                VolumeSource::Secret { items, .. } => write_secret_volume(&path, items)?,
+                VolumeSource::HostPath { .. } => continue,
+            }
+            debug!("Created volume {} in {}", volume.name, path.display());
+        }
+        Ok(())
+    }
+
+    /// Unmount the secret volumes of the pod, so their directory can be
+    /// removed
+    fn unmount_secret_volumes(&self) {
+        // Nothing was mounted without a directory
+        let directory = match self.volumes_directory() {
+            Ok(directory) => directory,
+            Err(_) => return,
+        };
+        for volume in &self.workload_definition.spec.volumes {
+            if let VolumeSource::Secret { .. } = volume.source {
+                let path = directory.join(&volume.name);
+                match umount2(&path, MntFlags::MNT_DETACH) {
+                    // Never mounted, the pod did not go that far
+                    Ok(()) | Err(nix::errno::Errno::EINVAL) | Err(nix::errno::Errno::ENOENT) => (),
+                    Err(e) => event!(Level::WARN, "Could not unmount {}: {}", path.display(), e),
+                }
+            }
+        }
+    }
+
+    /// Create the bundle of the container `id` from the bundle of its image:
+    /// the container uses the root filesystem of the image and joins the
+    /// network and IPC namespaces of the pod, and its PID namespace when the
+    /// containers share it. Its process is the one of the image, changed as
+    /// the definition of the container says, and its hostname is the id of
+    /// the instance.
+    fn create_bundle(
+        &self,
+        container: &Container,
+        id: &str,
+        image_bundle: &Path,
+    ) -> super::Result<PathBuf> {
+        let netns = self
+            .network
+            .netns()
+            .ok_or_else(|| RuntimeError::Error("Pod network not initialized".to_string()))?;
+        let infra = self
+            .infra
+            .as_ref()
+            .ok_or_else(|| RuntimeError::Error("Infra process not started".to_string()))?;
+        let mut config = BundleConfig::read(image_bundle).map_err(RuntimeError::OciError)?;
+        config.resolve_root(image_bundle);
+        config.set_namespace_path("network", netns);
+        config.set_namespace_path("ipc", &infra.namespace_path("ipc"));
+        if self.workload_definition.spec.share_process_namespace {
+            config.set_namespace_path("pid", &infra.namespace_path("pid"));
+        }
+        config.set_hostname(&self.instance_id);
+
+        // The entrypoint is only kept apart from the command in the image
+        let entrypoint = match (&container.command, &container.args) {
+            (None, Some(_)) => self
+                .image_manager
+                .entrypoint(&container.image)
+                .map_err(RuntimeError::OciError)?,
+            _ => Vec::new(),
+        };
+        if let Some(args) = container.process_args(&entrypoint) {
+            config.set_args(&args);
+        }
+        if let Some(env) = &container.env {
+            let env: Vec<(String, String)> = env
+                .iter()
+                .map(|variable| (variable.name.clone(), variable.value.clone()))
+                .collect();
+            config.merge_env(&env);
+        }
+        if let Some(working_dir) = &container.working_dir {
+            config.set_cwd(working_dir);
+        }
+        if let Some(user) = container.user {
+            config.set_user(user.uid, user.gid);
+        }
+        for mount in &container.volume_mounts {
+            let (source, read_only) = mount_source(
+                &self.workload_definition.spec.volumes,
+                mount,
+                &self.volumes_directory()?,
+            )?;
+            config.add_bind_mount(&source, &mount.mount_path, read_only);
+        }
+        config.add_bind_mount(resolv_conf(), RESOLV_CONF, true);
+
+        let bundle = self.container_bundle(id)?;
+        config.write(&bundle).map_err(RuntimeError::OciError)?;
+        Ok(bundle)
+    }
+
+    /// Whether a container still has its processes, i.e. it exists and is
+    /// not stopped
+    async fn is_running(&self, id: &str) -> bool {
+        match self.container_runtime.state(id).await {
+            Ok(container) => container.status.as_deref() != Some("stopped"),
+            Err(_) => false,
+        }
+    }
+
+    /// Containers of `ids` still running
+    async fn running(&self, ids: Vec<String>) -> Vec<String> {
+        let mut running = Vec::new();
+        for id in ids {
+            if self.is_running(&id).await {
+                running.push(id);
+            }
+        }
+        running
+    }
+
+    /// Send `signal` to the containers then wait for them to stop, for at
+    /// most `timeout`. Gives the ones still running.
+    async fn stop(
+        &self,
+        ids: Vec<String>,
+        signal: i32,
+        opts: &KillArgs,
+        timeout: Duration,
+    ) -> Vec<String> {
+        for id in &ids {
+            if let Err(e) = self.container_runtime.kill(id, signal, Some(opts)).await {
+                debug!("Could not signal container {}: {}", id, e);
+            }
+        }
+
+        let deadline = Instant::now() + timeout;
+        let mut running = ids;
+        loop {
+            running = self.running(running).await;
+            if running.is_empty() || Instant::now() >= deadline {
+                return running;
+            }
+            sleep(STOP_INTERVAL).await;
+        }
+    }
+
+    /// Remove the bundles of the pod, unless other containers of the node
+    /// were created from them too
+    async fn remove_bundles(&mut self) {
+        let containers = match self.container_runtime.list().await {
+            Ok(containers) => containers,
+            Err(e) => {
+                event!(Level::WARN, "Bundles kept, containers not listed: {}", e);
+                return;
+            }
+        };
+        for bundle in unused_bundles(&self.bundles, &containers) {
+            if !is_under(&bundle, &self.bundles_directory) {
+                event!(
+                    Level::WARN,
+                    "Bundle {} kept, it is not in {}",
+                    bundle.display(),
+                    self.bundles_directory.display()
+                );
+                continue;
+            }
+            match tokio::fs::remove_dir_all(&bundle).await {
+                Ok(()) => debug!("Removed bundle {}", bundle.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+                Err(e) => event!(
+                    Level::WARN,
+                    "Could not remove bundle {}: {}",
+                    bundle.display(),
+                    e
+                ),
+            }
+        }
+        self.bundles.clear();
+    }
+
+    /// Set up the network and the infra process of the pod then start its
+    /// containers
+    async fn start(&mut self) -> super::Result<()> {
+        // Their names are part of the paths of their bundles and logs
+        validate_container_names(
+            self.workload_definition
+                .spec
+                .containers
+                .iter()
+                .map(|container| container.name.as_str()),
+        )
+        .map_err(RuntimeError::Error)?;
         self.network
             .init()
             .await
             .map_err(RuntimeError::NetworkError)?;
+        let infra = InfraProcess::spawn(self.workload_definition.spec.share_process_namespace)
+            .map_err(|e| RuntimeError::Error(format!("Could not start infra process: {}", e)))?;
+        debug!("Started infra process {:?}", infra);
+        self.infra = Some(infra);
+        self.create_volumes()?;
 
         event!(Level::INFO, "Container workload detected");
 
         let containers = self.workload_definition.get_containers(&self.instance_id);
 
+        let mut supervised = Vec::new();
         for container in containers {
-            if let Some(id) = container.id {
+            if let Some(id) = container.id.clone() {
+                let pull_timer = IMAGE_PULL_DURATION.start_timer();
                 let image = &self
                     .image_manager
                     .pull(&container.image[..])
+                    .instrument(info_span!("image_pull", image = %container.image))
                     .await
                     .map_err(RuntimeError::OciError)?;
+                pull_timer.observe_duration();
+                let image_bundle = image
+                    .bundle
+                    .clone()
+                    .ok_or_else(|| RuntimeError::Error("Image bundle not found".to_string()))?;
+                if !self.bundles.contains(&image_bundle) {
+                    self.bundles.push(image_bundle.clone());
+                }
 
-                // New console socket for the container
-                let socket_path = PathBuf::from(format!("/tmp/{}", &id));
-                let console_socket =
-                    ConsoleSocket::new(&socket_path).map_err(RuntimeError::CriError)?;
-
-                tokio::spawn(async move {
-                    if let Some(unix_listener) = console_socket.get_listener().as_ref() {
-                        match unix_listener.accept().await {
-                            Ok((stream, _socket_addr)) => {
-                                Box::leak(Box::new(stream));
-                            }
-                            Err(err) => {
-                                event!(Level::ERROR, "Receive PTY master error : {:?}", err)
-                            }
-                        }
-                    }
+                self.containers.push(id.clone());
+                let bundle = self.create_bundle(&container, &id, &image_bundle)?;
+                let console = start_container(
+                    &self.container_runtime,
+                    &self.instance_id,
+                    &container.name,
+                    &id,
+                    &bundle,
+                )
+                .await?;
+                self.consoles.lock().unwrap().push(console);
+                supervised.push(Supervised {
+                    name: container.name,
+                    id: id.clone(),
+                    bundle,
                 });
-                self.container_runtime
-                    .run(
-                        &id[..],
-                        image.bundle.as_ref().ok_or_else(|| {
-                            RuntimeError::Error("Image bundle not found".to_string())
-                        })?,
-                        Some(&CreateArgs {
-                            pid_file: None,
-                            console_socket: Some(socket_path),
-                            no_pivot: false,
-                            no_new_keyring: false,
-                            detach: true,
-                        }),
-                    )
-                    .await
-                    .map_err(RuntimeError::CriError)?;
 
                 event!(Level::INFO, "Started container {}", id);
             }
         }
+
+        self.supervisor = Some(supervisor::supervise(
+            self.instance_id.clone(),
+            self.container_runtime.clone(),
+            self.workload_definition.spec.restart_policy,
+            supervised,
+            self.consoles.clone(),
+            self.infra
+                .as_ref()
+                .map(InfraProcess::exits)
+                .unwrap_or_default(),
+        ));
         Ok(())
     }
+}
+
+#[async_trait]
+impl Runtime for PodRuntime {
+    #[tracing::instrument(skip(self), fields(instance_id = %self.instance_id))]
+    async fn up(&mut self) -> super::Result<()> {
+        let result = self.start().await;
+        if let Err(e) = &result {
+            // The instance is dropped, what was set up has to go along
+            warn!("Pod not started, tearing it down: {}", e);
+            if let Err(e) = self.down().await {
+                warn!("Could not tear down the pod: {}", e);
+            }
+        }
+        result
+    }
 
     #[tracing::instrument(skip(self), fields(instance_id = %self.instance_id))]
     async fn down(&mut self) -> super::Result<()> {
-        error!("Down not implemented for pod runtime");
-        Ok(())
+        // Containers are not restarted while stopping
+        if let Some(supervisor) = self.supervisor.take() {
+            supervisor.abort();
+            let _ = supervisor.await;
+        }
+
+        let grace_period = self.workload_definition.get_termination_grace_period();
+        let running = self.running(self.containers.clone()).await;
+        let mut pids = Vec::new();
+        for id in &running {
+            pids.extend(supervisor::main_pid(&self.container_runtime, id).await);
+        }
+        let running = self
+            .stop(
+                running,
+                libc::SIGTERM,
+                &KillArgs { all: false },
+                grace_period,
+            )
+            .await;
+        if !running.is_empty() {
+            event!(
+                Level::WARN,
+                "Killing containers {:?}, still running after {:?}",
+                running,
+                grace_period
+            );
+            self.stop(
+                running,
+                libc::SIGKILL,
+                &KillArgs { all: true },
+                KILL_TIMEOUT,
+            )
+            .await;
+        }
+        // The stopped containers are children of the riklet, which reaps them
+        for pid in pids {
+            let _ = supervisor::try_reap(pid);
+        }
+
+        // Sockets are removed once the tasks waiting on them are gone
+        let consoles = std::mem::take(&mut *self.consoles.lock().unwrap());
+        for console in consoles {
+            console.abort();
+            let _ = console.await;
+        }
+
+        let mut result = Ok(());
+        for id in std::mem::take(&mut self.containers) {
+            // Containers whose creation failed don't exist
+            if self.container_runtime.state(&id).await.is_ok() {
+                match self
+                    .container_runtime
+                    .delete(&id, Some(&DeleteArgs { force: true }))
+                    .await
+                {
+                    Ok(()) => event!(Level::INFO, "Deleted container {}", id),
+                    Err(e) => result = Err(RuntimeError::CriError(e)),
+                }
+            }
+            if let Err(e) = std::fs::remove_file(console_socket_path(&id)) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    event!(Level::WARN, "Console socket of {} not removed: {}", id, e);
+                }
+            }
+            match self.container_bundle(&id) {
+                Ok(bundle) => {
+                    if let Err(e) = std::fs::remove_dir_all(bundle) {
+                        if e.kind() != std::io::ErrorKind::NotFound {
+                            event!(Level::WARN, "Bundle of {} not removed: {}", id, e);
+                        }
+                    }
+                }
+                Err(e) => event!(Level::WARN, "Bundle of {} not removed: {}", id, e),
+            }
+        }
+
+        // Once the containers are gone, nothing is left in the namespaces
+        if let Some(infra) = self.infra.take() {
+            if let Err(e) = infra.stop() {
+                event!(Level::WARN, "Could not stop infra process: {}", e);
+            }
+        }
+
+        // emptyDir and secret volumes go along with the pod
+        self.unmount_secret_volumes();
+        match self.volumes_directory() {
+            Ok(directory) => {
+                if let Err(e) = std::fs::remove_dir_all(directory) {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        event!(Level::WARN, "Volumes not removed: {}", e);
+                    }
+                }
+            }
+            Err(e) => event!(Level::WARN, "Volumes not removed: {}", e),
+        }
+
+        self.remove_bundles().await;
+        self.network
+            .destroy()
+            .await
+            .map_err(RuntimeError::NetworkError)?;
+        result
+    }
+
+    fn guest_ip(&self) -> Option<Ipv4Addr> {
+        self.network.pod_ip()
+    }
+
+    fn port_mapping(&self) -> Vec<PortMapping> {
+        self.network.port_mapping()
     }
 }
 
@@ -100,18 +664,181 @@ impl RuntimeManager for PodRuntimeManager {
         workload: InstanceScheduling,
         config: Configuration,
     ) -> super::Result<Box<dyn Runtime>> {
-        let workload_definition: WorkloadDefinition =
-            serde_json::from_str(workload.definition.as_str())
-                .map_err(RuntimeError::ParsingError)?;
+        let workload_definition =
+            WorkloadDefinition::try_from(&workload).map_err(RuntimeError::ParsingError)?;
         let instance_id: String = workload.instance_id;
+        let port_mapping = workload_definition.get_port_mapping();
+        let bundles_directory = config
+            .manager
+            .oci_manager
+            .bundles_directory
+            .clone()
+            .ok_or_else(|| RuntimeError::Error("No bundles directory".to_string()))?;
 
         Ok(Box::new(PodRuntime {
             image_manager: ImageManager::new(config.manager.clone())
                 .map_err(RuntimeError::OciError)?,
             workload_definition,
-            network: PodRuntimeNetwork::new(),
-            container_runtime: Runc::new(config.runner).map_err(RuntimeError::CriError)?,
+            network: PodRuntimeNetwork::new(
+                instance_id.clone(),
+                config.pod_network.clone(),
+                port_mapping,
+            )
+            .map_err(RuntimeError::NetworkError)?,
+            infra: None,
+            container_runtime: Arc::new(Runc::new(config.runner).map_err(RuntimeError::CriError)?),
             instance_id,
+            bundles_directory,
+            containers: Vec::new(),
+            bundles: Vec::new(),
+            consoles: Arc::default(),
+            supervisor: None,
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(bundle: &str, rootfs: &str) -> cri::Container {
+        cri::Container {
+            id: None,
+            pid: None,
+            status: None,
+            bundle: Some(bundle.to_string()),
+            rootfs: Some(rootfs.to_string()),
+            created: None,
+            annotations: None,
+        }
+    }
+
+    #[test]
+    fn test_unused_bundles() {
+        let bundles = vec![
+            PathBuf::from("/var/lib/riklet/bundles/debian"),
+            PathBuf::from("/var/lib/riklet/bundles/alpine"),
+            PathBuf::from("/var/lib/riklet/bundles/nginx"),
+        ];
+        let containers = vec![
+            container(
+                "/var/lib/riklet/bundles/alpine",
+                "/var/lib/riklet/bundles/alpine/rootfs",
+            ),
+            container(
+                "/var/lib/riklet/bundles/containers/web",
+                "/var/lib/riklet/bundles/nginx/rootfs",
+            ),
+        ];
+
+        assert_eq!(
+            unused_bundles(&bundles, &containers),
+            vec![PathBuf::from("/var/lib/riklet/bundles/debian")]
+        );
+        assert_eq!(unused_bundles(&bundles, &[]), bundles);
+    }
+
+    #[test]
+    fn test_mount_source() {
+        let volumes = vec![
+            Volume {
+                name: "cache".to_string(),
+                source: VolumeSource::EmptyDir {},
+            },
+            Volume {
+                name: "config".to_string(),
+                source: VolumeSource::HostPath {
+                    path: "/etc/app".to_string(),
+                },
+            },
+            Volume {
+                name: "relative".to_string(),
+                source: VolumeSource::HostPath {
+                    path: "etc/app".to_string(),
+                },
+            },
+            Volume {
+                name: "credentials".to_string(),
+                source: VolumeSource::Secret {
+                    secret_name: "database".to_string(),
+                    items: SecretItems::default(),
+                },
+            },
+            Volume {
+                name: "../../../../etc".to_string(),
+                source: VolumeSource::EmptyDir {},
+            },
+        ];
+        let mount = |name: &str, mount_path: &str| VolumeMount {
+            name: name.to_string(),
+            mount_path: mount_path.to_string(),
+            read_only: false,
+        };
+        let directory = Path::new("/var/lib/riklet/volumes/instance");
+
+        assert_eq!(
+            mount_source(&volumes, &mount("cache", "/cache"), directory).unwrap(),
+            (
+                PathBuf::from("/var/lib/riklet/volumes/instance/cache"),
+                false
+            )
+        );
+        assert_eq!(
+            mount_source(&volumes, &mount("config", "/etc/app"), directory).unwrap(),
+            (PathBuf::from("/etc/app"), false)
+        );
+        // Secrets are never written to
+        assert_eq!(
+            mount_source(&volumes, &mount("credentials", "/run/secrets"), directory).unwrap(),
+            (
+                PathBuf::from("/var/lib/riklet/volumes/instance/credentials"),
+                true
+            )
+        );
+        assert!(mount_source(&volumes, &mount("relative", "/etc/app"), directory).is_err());
+        assert!(mount_source(&volumes, &mount("../../../../etc", "/etc"), directory).is_err());
+        assert!(validate_volumes(&volumes).is_err());
+        assert!(validate_volumes(&volumes[..4]).is_ok());
+        assert!(validate_volumes(&[volumes[0].clone(), volumes[0].clone()]).is_err());
+        assert!(mount_source(&volumes, &mount("cache", "cache"), directory).is_err());
+        assert!(mount_source(&volumes, &mount("missing", "/data"), directory).is_err());
+    }
+
+    #[test]
+    fn test_path_under() {
+        let parent = Path::new("/var/lib/riklet/volumes");
+        assert_eq!(
+            path_under(parent, "instance").unwrap(),
+            PathBuf::from("/var/lib/riklet/volumes/instance")
+        );
+        assert!(path_under(parent, "").is_err());
+        assert!(path_under(parent, "..").is_err());
+        assert!(path_under(parent, "../etc").is_err());
+        assert!(path_under(parent, "/etc").is_err());
+        assert!(path_under(parent, "a/b").is_err());
+    }
+
+    #[test]
+    fn test_is_under() {
+        let directory = Path::new("/var/lib/riklet/bundles");
+        assert!(is_under(
+            Path::new("/var/lib/riklet/bundles/containers/web"),
+            directory
+        ));
+        assert!(!is_under(Path::new("/var/lib/riklet/bundle"), directory));
+        assert!(!is_under(
+            Path::new("/var/lib/riklet/bundles/../../../etc"),
+            directory
+        ));
+        assert!(!is_under(Path::new("/etc"), directory));
+    }
+
+    #[test]
+    fn test_validate_container_names() {
+        assert!(validate_container_names(vec!["web", "sidecar-1"]).is_ok());
+        assert!(validate_container_names(vec!["web", "web"]).is_err());
+        assert!(validate_container_names(vec!["../../etc"]).is_err());
+        assert!(validate_container_names(vec!["Web"]).is_err());
+        assert!(validate_container_names(vec![""]).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+//! Supervision of the containers of the pods once started. Their exits are
+//! reported along with their codes, and they are restarted as the restart
+//! policy of their pod allows.
+//!
+//! The riklet is made a subreaper so the containers, left by runc once
+//! started, become its children and it can wait for their exit codes. The
+//! ones reaped by the infra process of their pod, as the init of the PID
+//! namespace they share, are told by it instead.
+use super::infra::Exits;
+use super::pod_runtime::start_container;
+use cri::container::{DeleteArgs, Runc};
+use definition::workload::RestartPolicy;
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::Pid;
+use once_cell::sync::Lazy;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, error, info, warn};
+
+/// Delay between two checks of the containers
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(1);
+/// Delay before the first restart of a container, doubled on each next one
+const INITIAL_BACKOFF: Duration = Duration::from_secs(10);
+/// Longest delay before restarting a container
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// Time a container has to run for its previous restarts to be forgotten
+const BACKOFF_RESET: Duration = Duration::from_secs(600);
+
+/// What happened to the containers of an instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Report {
+    /// A container exited, with its code when it could be known
+    Exited {
+        instance_id: String,
+        container: String,
+        code: Option<i32>,
+        restarting: bool,
+    },
+    /// A container was started again
+    Restarted {
+        instance_id: String,
+        container: String,
+        restarts: u32,
+    },
+    /// Every container exited and none is restarted, `failure` being the
+    /// first one which did not succeed along with its code
+    Finished {
+        instance_id: String,
+        failure: Option<(String, Option<i32>)>,
+    },
+}
+
+/// Where the reports of the supervisors are sent
+static REPORTS: Lazy<Mutex<Option<UnboundedSender<Report>>>> = Lazy::new(Default::default);
+
+/// Receive the reports of the supervisors, from now on
+pub fn subscribe() -> UnboundedReceiver<Report> {
+    let (sender, receiver) = unbounded_channel();
+    *REPORTS.lock().unwrap() = Some(sender);
+    receiver
+}
+
+fn report(report: Report) {
+    debug!("Supervisor report: {:?}", report);
+    if let Some(sender) = REPORTS.lock().unwrap().as_ref() {
+        let _ = sender.send(report);
+    }
+}
+
+/// Adopt the processes orphaned by the children of the riklet, such as the
+/// containers once runc detached from them
+pub fn become_subreaper() -> std::io::Result<()> {
+    // SAFETY: PR_SET_CHILD_SUBREAPER only reads its second argument
+    match unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// Exit code of a process given its wait status, a signal giving 128 plus
+/// its number as shells do
+pub fn exit_code_of(status: WaitStatus) -> Option<i32> {
+    match status {
+        WaitStatus::Exited(_, code) => Some(code),
+        WaitStatus::Signaled(_, signal, _) => Some(128 + signal as i32),
+        _ => None,
+    }
+}
+
+/// Exit code of a child process if it exited. Fails when the process is not
+/// a child of the riklet.
+pub fn try_reap(pid: i32) -> nix::Result<Option<i32>> {
+    waitpid(Pid::from_raw(pid), Some(WaitPidFlag::WNOHANG)).map(exit_code_of)
+}
+
+/// Pid of a process in its own PID namespace, the last one of the `NSpid`
+/// line of its status
+fn namespace_pid(pid: i32) -> Option<i32> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("NSpid:"))
+        .and_then(|pids| pids.split_whitespace().last())
+        .and_then(|pid| pid.parse().ok())
+}
+
+/// How an exit code is told to users
+pub fn describe_exit(code: Option<i32>) -> String {
+    match code {
+        Some(code) => format!("with code {}", code),
+        None => "with an unknown code".to_string(),
+    }
+}
+
+/// Delay before restarting a container which exited `exits` times in a row
+/// shortly after starting
+fn backoff(exits: u32) -> Duration {
+    INITIAL_BACKOFF
+        .checked_mul(2u32.saturating_pow(exits))
+        .map_or(MAX_BACKOFF, |backoff| backoff.min(MAX_BACKOFF))
+}
+
+/// A container of a pod to supervise
+#[derive(Debug, Clone)]
+pub struct Supervised {
+    pub name: String,
+    pub id: String,
+    pub bundle: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Running,
+    /// Waiting to be restarted
+    BackOff(Instant),
+    /// Exited for good, with its code when it could be known
+    Exited(Option<i32>),
+}
+
+struct Watched {
+    container: Supervised,
+    pid: Option<i32>,
+    /// Pid of the main process in the namespace of the pod, under which the
+    /// infra process tells its exit
+    namespace_pid: Option<i32>,
+    started: Instant,
+    restarts: u32,
+    /// Exits in a row, each one delaying the next restart further
+    exits: u32,
+    state: State,
+}
+
+impl Watched {
+    /// Follow a new main process of the container
+    fn set_pid(&mut self, pid: Option<i32>, exits: &Exits) {
+        self.pid = pid;
+        self.namespace_pid = pid.and_then(namespace_pid);
+        if let Some(pid) = self.namespace_pid {
+            exits.watch(pid);
+        }
+    }
+}
+
+/// Code of a container once it exited, `Some(None)` when it is known to
+/// have exited but not how
+async fn exit_code(runtime: &Runc, watched: &Watched, exits: &Exits) -> Option<Option<i32>> {
+    if let Some(pid) = watched.pid {
+        match try_reap(pid) {
+            Ok(Some(code)) => return Some(Some(code)),
+            Ok(None) => return None,
+            // Not a child, e.g. reaped by the infra process of the pod or
+            // started by a previous riklet
+            Err(_) => (),
+        }
+    }
+    if let Some(code) = watched.namespace_pid.and_then(|pid| exits.take(pid)) {
+        return Some(Some(code));
+    }
+    match runtime.state(&watched.container.id).await {
+        Ok(container) if container.status.as_deref() == Some("stopped") => {
+            // The infra process reaps as soon as the process exits
+            Some(watched.namespace_pid.and_then(|pid| exits.take(pid)))
+        }
+        Ok(_) => None,
+        Err(_) => Some(None),
+    }
+}
+
+/// Pid of the main process of a container
+pub async fn main_pid(runtime: &Runc, id: &str) -> Option<i32> {
+    runtime
+        .state(id)
+        .await
+        .ok()
+        .and_then(|container| container.pid)
+        .and_then(|pid| i32::try_from(pid).ok())
+}
+
+/// Start again a container which exited, from the same bundle. The task
+/// waiting for its new console joins `consoles`, so it goes with the pod.
+async fn restart(
+    runtime: &Arc<Runc>,
+    instance_id: &str,
+    watched: &mut Watched,
+    consoles: &Mutex<Vec<JoinHandle<()>>>,
+    exits: &Exits,
+) -> bool {
+    let container = &watched.container;
+    if let Err(e) = runtime
+        .delete(&container.id, Some(&DeleteArgs { force: true }))
+        .await
+    {
+        warn!("Could not delete exited container {}: {}", container.id, e);
+    }
+    match start_container(
+        runtime,
+        instance_id,
+        &container.name,
+        &container.id,
+        &container.bundle,
+    )
+    .await
+    {
+        Ok(console) => {
+            consoles.lock().unwrap().push(console);
+            let pid = main_pid(runtime, &container.id).await;
+            watched.set_pid(pid, exits);
+            true
+        }
+        Err(e) => {
+            error!("Could not restart container {}: {}", container.id, e);
+            false
+        }
+    }
+}
+
+/// Watch the containers of an instance until they all exited for good, or
+/// the returned task is aborted
+pub fn supervise(
+    instance_id: String,
+    runtime: Arc<Runc>,
+    policy: RestartPolicy,
+    containers: Vec<Supervised>,
+    consoles: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    exits: Exits,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut watched = Vec::new();
+        for container in containers {
+            let pid = main_pid(&runtime, &container.id).await;
+            let mut entry = Watched {
+                container,
+                pid: None,
+                namespace_pid: None,
+                started: Instant::now(),
+                restarts: 0,
+                exits: 0,
+                state: State::Running,
+            };
+            entry.set_pid(pid, &exits);
+            watched.push(entry);
+        }
+
+        loop {
+            sleep(SUPERVISE_INTERVAL).await;
+            for watched in watched.iter_mut() {
+                match watched.state {
+                    State::Running => {
+                        let code = match exit_code(&runtime, watched, &exits).await {
+                            Some(code) => code,
+                            None => continue,
+                        };
+                        let restarting = policy.allows_restart(code);
+                        info!(
+                            "Container {} exited {}",
+                            watched.container.id,
+                            describe_exit(code)
+                        );
+                        report(Report::Exited {
+                            instance_id: instance_id.clone(),
+                            container: watched.container.name.clone(),
+                            code,
+                            restarting,
+                        });
+                        watched.state = match restarting {
+                            true => {
+                                if watched.started.elapsed() >= BACKOFF_RESET {
+                                    watched.exits = 0;
+                                }
+                                watched.exits += 1;
+                                State::BackOff(Instant::now() + backoff(watched.exits - 1))
+                            }
+                            false => State::Exited(code),
+                        };
+                    }
+                    State::BackOff(until) if Instant::now() >= until => {
+                        let restarted =
+                            restart(&runtime, &instance_id, watched, &consoles, &exits).await;
+                        watched.state = match restarted {
+                            true => {
+                                watched.restarts += 1;
+                                watched.started = Instant::now();
+                                report(Report::Restarted {
+                                    instance_id: instance_id.clone(),
+                                    container: watched.container.name.clone(),
+                                    restarts: watched.restarts,
+                                });
+                                State::Running
+                            }
+                            false => {
+                                watched.exits += 1;
+                                State::BackOff(Instant::now() + backoff(watched.exits - 1))
+                            }
+                        };
+                    }
+                    State::BackOff(_) | State::Exited(_) => (),
+                }
+            }
+
+            let mut codes = Vec::new();
+            for watched in &watched {
+                match watched.state {
+                    State::Exited(code) => codes.push((watched.container.name.clone(), code)),
+                    _ => break,
+                }
+            }
+            if codes.len() == watched.len() {
+                report(Report::Finished {
+                    instance_id,
+                    failure: codes.into_iter().find(|(_, code)| *code != Some(0)),
+                });
+                return;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), Duration::from_secs(10));
+        assert_eq!(backoff(1), Duration::from_secs(20));
+        assert_eq!(backoff(4), Duration::from_secs(160));
+        assert_eq!(backoff(5), MAX_BACKOFF);
+        assert_eq!(backoff(64), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_try_reap() {
+        let child = std::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        let pid = child.id() as i32;
+
+        let mut code = None;
+        while code.is_none() {
+            std::thread::sleep(Duration::from_millis(10));
+            code = try_reap(pid).unwrap();
+        }
+        assert_eq!(code, Some(3));
+        assert!(try_reap(pid).is_err());
+    }
+}
@@ -1,6 +1,12 @@
+use crate::constants::DEFAULT_TERMINATION_GRACE_PERIOD;
+use definition::workload as def;
+use proto::worker::InstanceScheduling;
+use proto::ConversionError;
 use serde::{Deserialize, Serialize};
 use shared::utils::get_random_hash;
-use tracing::{event, warn, Level};
+use std::convert::TryFrom;
+use std::time::Duration;
+use tracing::warn;
 
 #[async_trait::async_trait]
 pub trait EventEmitter<U, T> {
@@ -10,20 +16,48 @@ pub trait EventEmitter<U, T> {
     ) -> std::result::Result<(), Box<dyn std::error::Error>>;
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct EnvConfig {
     pub name: String,
     pub value: String,
+    /// Whether the value was read from a secret, it is never printed then
+    #[serde(skip)]
+    pub secret: bool,
+}
+
+impl std::fmt::Debug for EnvConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value: &dyn std::fmt::Debug = match self.secret {
+            true => &"<redacted>",
+            false => &self.value,
+        };
+        f.debug_struct("EnvConfig")
+            .field("name", &self.name)
+            .field("value", value)
+            .finish()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PortConfig {
     pub port: u16,
     pub target_port: u16,
-    pub protocol: Option<String>,
+    pub protocol: Option<def::Protocol>,
     pub r#type: String,
 }
 
+impl PortConfig {
+    /// Whether the port is exposed on the node, on `port` or on a port the
+    /// riklet chooses when it is 0
+    pub fn is_node_port(&self) -> bool {
+        self.r#type.eq_ignore_ascii_case("NodePort")
+    }
+
+    pub fn protocol(&self) -> def::Protocol {
+        self.protocol.unwrap_or_default()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Container {
     pub id: Option<String>,
@@ -31,12 +65,35 @@ pub struct Container {
     pub image: String,
     pub env: Option<Vec<EnvConfig>>,
     pub ports: Option<PortConfig>,
+    pub command: Option<Vec<String>>,
+    pub args: Option<Vec<String>>,
+    pub working_dir: Option<String>,
+    pub user: Option<def::ContainerUser>,
+    pub volume_mounts: Vec<def::VolumeMount>,
 }
 
 impl Container {
     pub fn get_uuid(&self) -> String {
         get_random_hash(5)
     }
+
+    /// Arguments of the process of the container, when its `command` or its
+    /// `args` replace the ones of the image. `command` replaces the
+    /// `entrypoint` of the image, `args` are given to either of them.
+    pub fn process_args(&self, entrypoint: &[String]) -> Option<Vec<String>> {
+        match (&self.command, &self.args) {
+            (None, None) => None,
+            (command, args) => Some(
+                command
+                    .as_deref()
+                    .unwrap_or(entrypoint)
+                    .iter()
+                    .chain(args.iter().flatten())
+                    .cloned()
+                    .collect(),
+            ),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -60,6 +117,17 @@ pub struct FunctionPort {
     pub target_port: u16,
     #[serde(rename = "type")]
     pub port_type: NetworkPortExposureType,
+    #[serde(default)]
+    pub protocol: def::Protocol,
+}
+
+/// Port of the node forwarded to a port of a workload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortMapping {
+    /// Port of the node, 0 letting the riklet choose one
+    pub host_port: u16,
+    pub target_port: u16,
+    pub protocol: def::Protocol,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -72,6 +140,10 @@ pub struct Function {
 pub struct Spec {
     pub containers: Vec<Container>,
     pub function: Option<Function>,
+    pub termination_grace_period_seconds: Option<u64>,
+    pub restart_policy: def::RestartPolicy,
+    pub share_process_namespace: bool,
+    pub volumes: Vec<def::Volume>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -83,11 +155,79 @@ pub struct WorkloadDefinition {
     pub spec: Spec,
 }
 
+impl From<def::WorkloadDefinition> for WorkloadDefinition {
+    fn from(value: def::WorkloadDefinition) -> Self {
+        Self {
+            api_version: value.api_version,
+            kind: value.kind.to_string(),
+            name: value.name,
+            spec: Spec {
+                containers: value
+                    .spec
+                    .containers
+                    .into_iter()
+                    .map(|container| Container {
+                        id: None,
+                        name: container.name,
+                        image: container.image,
+                        env: container.env.map(|env| {
+                            env.into_iter()
+                                .map(|e| EnvConfig {
+                                    value: e.resolved_value().to_string(),
+                                    secret: e.value_from.is_some(),
+                                    name: e.name,
+                                })
+                                .collect()
+                        }),
+                        ports: container.ports.map(|ports| PortConfig {
+                            port: ports.port,
+                            target_port: ports.target_port,
+                            protocol: ports.protocol,
+                            r#type: ports.r#type,
+                        }),
+                        command: container.command,
+                        args: container.args,
+                        working_dir: container.working_dir,
+                        user: container.user,
+                        volume_mounts: container.volume_mounts,
+                    })
+                    .collect(),
+                function: value.spec.function.map(|function| Function {
+                    execution: FunctionExecution {
+                        rootfs: function.execution.rootfs,
+                    },
+                    exposure: function.exposure.map(|exposure| FunctionPort {
+                        port: exposure.port,
+                        target_port: exposure.target_port,
+                        port_type: NetworkPortExposureType::NodePort,
+                        protocol: exposure.protocol,
+                    }),
+                }),
+                termination_grace_period_seconds: value.spec.termination_grace_period_seconds,
+                restart_policy: value.spec.restart_policy,
+                share_process_namespace: value.spec.share_process_namespace,
+                volumes: value.spec.volumes,
+            },
+        }
+    }
+}
+
+impl TryFrom<&InstanceScheduling> for WorkloadDefinition {
+    type Error = ConversionError;
+
+    fn try_from(workload: &InstanceScheduling) -> Result<Self, Self::Error> {
+        let definition = workload
+            .definition
+            .clone()
+            .ok_or(ConversionError::MissingField("definition"))?;
+        Ok(def::WorkloadDefinition::try_from(definition)?.into())
+    }
+}
+
 impl WorkloadDefinition {
     pub fn get_containers(&self, instance_id: &str) -> Vec<Container> {
         let mut containers = Vec::<Container>::new();
         for mut container in self.spec.containers.clone() {
-            event!(Level::DEBUG, "Container: {:?}", &container);
             container.id = Some(format!(
                 "{}-{}-{}",
                 instance_id,
@@ -99,6 +239,15 @@ impl WorkloadDefinition {
         containers
     }
 
+    /// Time the containers are given to stop before being killed
+    pub fn get_termination_grace_period(&self) -> Duration {
+        Duration::from_secs(
+            self.spec
+                .termination_grace_period_seconds
+                .unwrap_or(DEFAULT_TERMINATION_GRACE_PERIOD),
+        )
+    }
+
     pub fn get_rootfs_url(&self) -> Option<String> {
         self.spec
             .function
@@ -107,21 +256,37 @@ impl WorkloadDefinition {
     }
 
     /// Give expected ports exposed by the workload.
-    /// Returns a tuple of (host_port, target_port)
     #[tracing::instrument(skip(self), fields(self.name))]
-    pub fn get_port_mapping(&self) -> Vec<(u16, u16)> {
-        let mut port_mapping = Vec::<(u16, u16)>::new();
+    pub fn get_port_mapping(&self) -> Vec<PortMapping> {
+        let mut port_mapping = Vec::<PortMapping>::new();
         let function_exposure = self
             .spec
             .function
             .as_ref()
-            .and_then(|f| f.exposure.as_ref().map(|e| (e.port, e.target_port)));
+            .and_then(|f| f.exposure.as_ref());
 
-        if let Some((host_port, target_port)) = function_exposure {
+        if let Some(exposure) = function_exposure {
             // FIXME: This is a domain violation, as we want to get away from binding to this "FUNCTION_RUNTIME" things
             // which refers to a specific implementation of a VM
-            port_mapping.push((host_port, target_port));
-        } else {
+            port_mapping.push(PortMapping {
+                host_port: exposure.port,
+                target_port: exposure.target_port,
+                protocol: exposure.protocol,
+            });
+        }
+        port_mapping.extend(
+            self.spec
+                .containers
+                .iter()
+                .filter_map(|container| container.ports.as_ref())
+                .filter(|ports| ports.is_node_port())
+                .map(|ports| PortMapping {
+                    host_port: ports.port,
+                    target_port: ports.target_port,
+                    protocol: ports.protocol(),
+                }),
+        );
+        if port_mapping.is_empty() {
             warn!("No port mapping found for workload {}", self.name);
         }
         port_mapping
@@ -148,17 +313,151 @@ mod tests {
                         port: 8080,
                         target_port: 8081,
                         port_type: NetworkPortExposureType::NodePort,
+                        protocol: def::Protocol::Udp,
                     }),
                 }),
+                termination_grace_period_seconds: None,
+                restart_policy: Default::default(),
+                share_process_namespace: false,
+                volumes: vec![],
             },
         };
 
         let port_mapping = workload.get_port_mapping();
         assert_eq!(port_mapping.len(), 1);
-        // host port
-        assert_eq!(port_mapping[0].0, 8080);
-        // internal port
-        assert_eq!(port_mapping[0].1, 8081);
+        assert_eq!(port_mapping[0].host_port, 8080);
+        assert_eq!(port_mapping[0].target_port, 8081);
+        assert_eq!(port_mapping[0].protocol, def::Protocol::Udp);
+    }
+
+    #[test]
+    fn test_workload_container_port_mapping() {
+        let container = |name: &str, port: u16, r#type: &str, protocol| Container {
+            id: None,
+            name: name.to_string(),
+            image: "nginx:latest".to_string(),
+            env: None,
+            ports: Some(PortConfig {
+                port,
+                target_port: 80,
+                protocol,
+                r#type: r#type.to_string(),
+            }),
+            command: None,
+            args: None,
+            working_dir: None,
+            user: None,
+            volume_mounts: vec![],
+        };
+        let workload = WorkloadDefinition {
+            api_version: "v1".to_string(),
+            kind: "Pod".to_string(),
+            name: "test".to_string(),
+            spec: Spec {
+                containers: vec![
+                    container("web", 30080, "NodePort", None),
+                    container("dns", 0, "nodePort", Some(def::Protocol::Udp)),
+                    container("internal", 8080, "clusterIP", None),
+                ],
+                function: None,
+                termination_grace_period_seconds: None,
+                restart_policy: Default::default(),
+                share_process_namespace: false,
+                volumes: vec![],
+            },
+        };
+
+        assert_eq!(
+            workload.get_port_mapping(),
+            vec![
+                PortMapping {
+                    host_port: 30080,
+                    target_port: 80,
+                    protocol: def::Protocol::Tcp,
+                },
+                PortMapping {
+                    host_port: 0,
+                    target_port: 80,
+                    protocol: def::Protocol::Udp,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_secret_env_not_printed() {
+        let mut definition: def::WorkloadDefinition = serde_json::from_str(
+            r#"{
+                "apiVersion": "v0",
+                "kind": "Pod",
+                "name": "web",
+                "spec": {
+                    "containers": [{
+                        "name": "web",
+                        "image": "nginx:latest",
+                        "env": [
+                            {"name": "MODE", "value": "production"},
+                            {"name": "PASSWORD", "valueFrom": {"secretKeyRef": {"name": "db", "key": "password"}}}
+                        ]
+                    }]
+                }
+            }"#,
+        )
+        .unwrap();
+        let env = definition.spec.containers[0].env.as_mut().unwrap();
+        env[1].value_from.as_mut().unwrap().value = def::SecretValue("hunter2".to_string());
+
+        let workload = WorkloadDefinition::from(definition);
+        let containers = workload.get_containers("web-1");
+        let env = containers[0].env.as_ref().unwrap();
+
+        assert_eq!(env[0].value, "production");
+        assert_eq!(env[1].value, "hunter2");
+        assert!(format!("{:?}", env[0]).contains("production"));
+        assert!(!format!("{:?}", containers).contains("hunter2"));
+        assert!(!format!("{:?}", workload).contains("hunter2"));
+    }
+
+    #[test]
+    fn test_container_process_args() {
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+        let container = |command: Option<Vec<String>>, args: Option<Vec<String>>| Container {
+            id: None,
+            name: "web".to_string(),
+            image: "nginx:latest".to_string(),
+            env: None,
+            ports: None,
+            command,
+            args,
+            working_dir: None,
+            user: None,
+            volume_mounts: vec![],
+        };
+        let entrypoint: Vec<String> = strings(&["/docker-entrypoint.sh"]);
+
+        assert_eq!(container(None, None).process_args(&entrypoint), None);
+        assert_eq!(
+            container(None, Some(strings(&["nginx", "-g", "daemon off;"])))
+                .process_args(&entrypoint),
+            Some(strings(&[
+                "/docker-entrypoint.sh",
+                "nginx",
+                "-g",
+                "daemon off;"
+            ]))
+        );
+        assert_eq!(
+            container(Some(strings(&["/bin/sh"])), None).process_args(&entrypoint),
+            Some(strings(&["/bin/sh"]))
+        );
+        assert_eq!(
+            container(
+                Some(strings(&["/bin/sh", "-c"])),
+                Some(strings(&["sleep 1"]))
+            )
+            .process_args(&[]),
+            Some(strings(&["/bin/sh", "-c", "sleep 1"]))
+        );
     }
 
     #[test]
@@ -170,6 +469,10 @@ mod tests {
             spec: Spec {
                 containers: vec![],
                 function: None,
+                termination_grace_period_seconds: None,
+                restart_policy: Default::default(),
+                share_process_namespace: false,
+                volumes: vec![],
             },
         };
 
@@ -7,13 +7,16 @@ use std::net::SocketAddrV4;
 pub struct ConfigParser {
     pub workers_endpoint: SocketAddrV4,
     pub controller_endpoint: SocketAddrV4,
+    pub http_endpoint: SocketAddrV4,
     pub verbosity_level: String,
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum ConfigParserError {
     InvalidWorkersEndpoint,
     InvalidControllersEndpoint,
+    InvalidHttpEndpoint,
 }
 
 impl ConfigParser {
@@ -39,6 +42,14 @@ impl ConfigParser {
                     .takes_value(true)
                     .default_value("0.0.0.0:4996"),
             )
+            .arg(
+                Arg::with_name("http_ip")
+                    .long("httpip")
+                    .value_name("HTTP_IP")
+                    .help("Metrics and health HTTP endpoint IPv4")
+                    .takes_value(true)
+                    .default_value("0.0.0.0:4997"),
+            )
             .get_matches();
 
         let workers_ip: SocketAddrV4 = matches
@@ -53,9 +64,16 @@ impl ConfigParser {
             .parse()
             .map_err(|_| ConfigParserError::InvalidControllersEndpoint)?;
 
+        let http_ip: SocketAddrV4 = matches
+            .value_of("http_ip")
+            .unwrap()
+            .parse()
+            .map_err(|_| ConfigParserError::InvalidHttpEndpoint)?;
+
         Ok(ConfigParser {
             workers_endpoint: workers_ip,
             controller_endpoint: controllers_ip,
+            http_endpoint: http_ip,
             verbosity_level: ConfigParser::get_verbosity_level(matches.occurrences_of("v")),
         })
     }
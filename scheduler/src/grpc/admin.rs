@@ -0,0 +1,95 @@
+use crate::grpc::GRPCService;
+use proto::admin::admin_server::Admin;
+use proto::admin::{PlacementList, StateDump, WorkerList};
+use scheduler::{Event, Send};
+use tokio::sync::oneshot;
+use tonic::{Request, Response, Status};
+
+/// Wait for the answer of the Manager to an admin request
+async fn receive<T>(receiver: oneshot::Receiver<T>) -> Result<T, Status> {
+    receiver
+        .await
+        .map_err(|_| Status::internal("Scheduler could not answer the request"))
+}
+
+#[tonic::async_trait]
+impl Admin for GRPCService {
+    async fn list_workers(&self, _request: Request<()>) -> Result<Response<WorkerList>, Status> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(Event::ListWorkers(sender)).await?;
+
+        Ok(Response::new(WorkerList {
+            workers: receive(receiver).await?,
+        }))
+    }
+
+    async fn list_placements(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<PlacementList>, Status> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(Event::ListPlacements(sender)).await?;
+
+        Ok(Response::new(PlacementList {
+            workloads: receive(receiver).await?,
+        }))
+    }
+
+    async fn dump_state(&self, _request: Request<()>) -> Result<Response<StateDump>, Status> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(Event::DumpState(sender)).await?;
+
+        Ok(Response::new(StateDump {
+            json: receive(receiver).await?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::admin::{WorkerInfo, WorkerState};
+    use tokio::sync::mpsc::channel;
+
+    #[tokio::test]
+    async fn test_list_workers() {
+        let (sender, mut receiver) = channel::<Event>(1024);
+
+        let service = GRPCService::new(sender);
+
+        tokio::spawn(async move {
+            if let Some(Event::ListWorkers(answer)) = receiver.recv().await {
+                let _ = answer.send(vec![WorkerInfo {
+                    id: "debian".to_string(),
+                    address: "127.0.0.1:4995".to_string(),
+                    state: WorkerState::Ready.into(),
+                    ..Default::default()
+                }]);
+            }
+        });
+
+        let workers = service
+            .list_workers(Request::new(()))
+            .await
+            .unwrap()
+            .into_inner()
+            .workers;
+        assert_eq!(workers.len(), 1);
+        assert_eq!(workers[0].id, "debian");
+    }
+
+    #[tokio::test]
+    async fn test_dump_state_unanswered() {
+        let (sender, mut receiver) = channel::<Event>(1024);
+
+        let service = GRPCService::new(sender);
+
+        tokio::spawn(async move {
+            // Drop the answer channel without replying
+            let _ = receiver.recv().await;
+        });
+
+        let response = service.dump_state(Request::new(())).await;
+        assert_eq!(response.unwrap_err().code(), tonic::Code::Internal);
+    }
+}
@@ -2,26 +2,30 @@ use crate::grpc::GRPCService;
 use proto::common::WorkerStatus;
 use proto::controller::controller_server::Controller as ControllerClient;
 use proto::controller::WorkloadScheduling;
+use proto::ConversionError;
 use scheduler::Send;
 use scheduler::{Event, WorkloadRequest};
 use tokio::sync::mpsc::channel;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
-use tracing::error;
+use tracing::{error, Span};
 
 #[tonic::async_trait]
 impl ControllerClient for GRPCService {
+    #[tracing::instrument(skip_all, fields(instance_id = %_request.get_ref().instance_id))]
     async fn schedule_instance(
         &self,
         _request: Request<WorkloadScheduling>,
     ) -> Result<Response<()>, Status> {
-        let parsed_body = _request.get_ref().clone().unpack().map_err(|e| {
+        telemetry::set_parent_from_metadata(&Span::current(), _request.metadata());
+        let mut parsed_body = _request.get_ref().clone().unpack().map_err(|e| {
             error!(
                 "Failed to parse ScheduleInstance from controller, reason: {}",
                 e
             );
             Status::invalid_argument(e.to_string())
         })?;
+        parsed_body.trace_context = telemetry::current_context();
 
         self.send(Event::ScheduleRequest(parsed_body)).await?;
 
@@ -61,22 +65,33 @@ mod tests {
 
         let workload = WorkloadScheduling {
             workload_id: "test".to_string(),
-            definition: serde_json::to_string(&WorkloadDefinition {
-                api_version: "v0".to_string(),
-                kind: WorkloadKind::Pod,
-                name: "workload-debian".to_string(),
-                replicas: Some(2),
-                spec: Spec {
-                    function: None,
-                    containers: vec![Container {
-                        name: " debian".to_string(),
-                        image: "debian:latest".to_string(),
-                        env: None,
-                        ports: None,
-                    }],
-                },
-            })
-            .map_err(|e| Status::invalid_argument(e.to_string()))?,
+            definition: Some(
+                WorkloadDefinition {
+                    api_version: "v0".to_string(),
+                    kind: WorkloadKind::Pod,
+                    name: "workload-debian".to_string(),
+                    replicas: Some(2),
+                    spec: Spec {
+                        function: None,
+                        termination_grace_period_seconds: None,
+                        restart_policy: Default::default(),
+                        share_process_namespace: false,
+                        volumes: vec![],
+                        containers: vec![Container {
+                            name: " debian".to_string(),
+                            image: "debian:latest".to_string(),
+                            env: None,
+                            ports: None,
+                            command: None,
+                            args: None,
+                            working_dir: None,
+                            user: None,
+                            volume_mounts: vec![],
+                        }],
+                    },
+                }
+                .into(),
+            ),
             action: WorkloadRequestKind::Create.into(),
             instance_id: "".to_string(),
         };
@@ -148,11 +163,11 @@ mod tests {
 }
 
 trait UnPacker<T> {
-    fn unpack(self) -> Result<T, serde_json::Error>;
+    fn unpack(self) -> Result<T, ConversionError>;
 }
 
 impl UnPacker<WorkloadRequest> for WorkloadScheduling {
-    fn unpack(self) -> Result<WorkloadRequest, serde_json::Error> {
+    fn unpack(self) -> Result<WorkloadRequest, ConversionError> {
         WorkloadRequest::new(self)
     }
 }
@@ -1,3 +1,4 @@
+mod admin;
 mod controller;
 mod worker;
 
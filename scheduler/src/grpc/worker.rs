@@ -22,13 +22,12 @@ impl WorkerClient for GRPCService {
         let addr = _request
             .remote_addr()
             .unwrap_or_else(|| "0.0.0.0:000".parse().unwrap());
-        let body: String = match &_request.get_ref().hostname {
-            hostname if hostname.is_empty() => {
-                Err(tonic::Status::failed_precondition("No hostname specified"))
-            }
-            hostname => Ok(hostname.clone()),
-        }?;
-        self.send(Event::Register(stream_tx, addr, body)).await?;
+        let registration = _request.into_inner();
+        if registration.hostname.is_empty() {
+            return Err(tonic::Status::failed_precondition("No hostname specified"));
+        }
+        self.send(Event::Register(stream_tx, addr, registration))
+            .await?;
 
         Ok(Response::new(ReceiverStream::new(stream_rx)))
     }
@@ -58,6 +57,7 @@ impl WorkerClient for GRPCService {
                     self.send(Event::InstanceMetricsUpdate(identifier, metrics))
                         .await?
                 }
+                Status::Event(event) => self.send(Event::ClusterEvent(identifier, event)).await?,
             };
         }
 
@@ -68,6 +68,7 @@ impl WorkerClient for GRPCService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proto::common::{InstancePort, ResourceStatus, WorkerInstance, WorkloadKind};
     use proto::worker::InstanceScheduling;
     use std::net::SocketAddr;
     use tokio::sync::mpsc::error::SendError;
@@ -82,14 +83,16 @@ mod tests {
 
         let mock_request = Request::new(WorkerRegistration {
             hostname: hostname.clone(),
+            instances: vec![],
+            capabilities: None,
         });
 
         let _ = service.register(mock_request).await;
 
         let message = receiver.recv().await.unwrap();
         match message {
-            Event::Register(_, socket, host) => {
-                assert_eq!(hostname, host);
+            Event::Register(_, socket, registration) => {
+                assert_eq!(hostname, registration.hostname);
                 let default_socket: SocketAddr = "0.0.0.0:0".parse().unwrap();
                 assert_eq!(default_socket, socket);
             }
@@ -105,6 +108,8 @@ mod tests {
 
         let mock_request = Request::new(WorkerRegistration {
             hostname: "".to_string(),
+            instances: vec![],
+            capabilities: None,
         });
         let fallback = service.register(mock_request).await;
         assert!(fallback.is_err());
@@ -123,6 +128,8 @@ mod tests {
 
         let mock_request = Request::new(WorkerRegistration {
             hostname: hostname.clone(),
+            instances: vec![],
+            capabilities: None,
         });
 
         service.register(mock_request).await?;
@@ -135,6 +142,39 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_register_inventory() {
+        let (sender, mut receiver) = channel::<Event>(1024);
+
+        let service = GRPCService::new(sender);
+        let instance = WorkerInstance {
+            instance_id: "instance-1".to_string(),
+            kind: WorkloadKind::Function.into(),
+            status: ResourceStatus::Running.into(),
+            ports: vec![InstancePort {
+                port: 45000,
+                target_port: 8080,
+                ..Default::default()
+            }],
+        };
+
+        let mock_request = Request::new(WorkerRegistration {
+            hostname: "debian".to_string(),
+            instances: vec![instance.clone()],
+            capabilities: None,
+        });
+
+        let _ = service.register(mock_request).await;
+
+        let message = receiver.recv().await.unwrap();
+        match message {
+            Event::Register(_, _, registration) => {
+                assert_eq!(registration.instances, vec![instance])
+            }
+            _ => assert!(false),
+        };
+    }
+
     #[tokio::test]
     async fn test_register_stream(
     ) -> Result<(), SendError<Result<InstanceScheduling, tonic::Status>>> {
@@ -145,6 +185,8 @@ mod tests {
 
         let mock_request = Request::new(WorkerRegistration {
             hostname: hostname.clone(),
+            instances: vec![],
+            capabilities: None,
         });
 
         let mut stream = service
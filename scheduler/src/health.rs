@@ -0,0 +1,23 @@
+//! Liveness and readiness of the scheduler, served on `/healthz` and `/readyz`
+use probes::health::{report, Check};
+
+/// Loop dispatching events between workers, controller and state manager
+pub static MANAGER: Check = Check::new("manager");
+pub static STATE_MANAGER: Check = Check::new("state_manager");
+pub static WORKERS_LISTENER: Check = Check::new("workers_listener");
+pub static CONTROLLERS_LISTENER: Check = Check::new("controllers_listener");
+
+/// Whether the scheduler is able to make progress at all
+pub fn liveness() -> (bool, String) {
+    report(&[MANAGER.status(), STATE_MANAGER.status()])
+}
+
+/// Whether the scheduler is able to serve workers and controllers
+pub fn readiness() -> (bool, String) {
+    report(&[
+        MANAGER.status(),
+        STATE_MANAGER.status(),
+        WORKERS_LISTENER.status(),
+        CONTROLLERS_LISTENER.status(),
+    ])
+}
@@ -1,13 +1,22 @@
 use definition::workload::WorkloadDefinition;
 use node_metrics::metrics::Metrics;
-use proto::common::{InstanceMetric, WorkerMetric, WorkerStatus, WorkloadRequestKind};
+use proto::admin::{WorkerInfo, WorkerState as AdminWorkerState, WorkloadPlacement};
+use proto::common::{
+    ClusterEvent, InstanceMetric, WorkerCapabilities, WorkerMetric, WorkerRegistration,
+    WorkerStatus, WorkloadKind, WorkloadRequestKind,
+};
 use proto::controller::WorkloadScheduling;
 use proto::worker::InstanceScheduling;
+use proto::ConversionError;
+use std::convert::TryInto;
 use std::error::Error;
 use std::fmt;
 use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use telemetry::TraceContext;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
 use tonic::Status;
 use tracing::{error, info};
 
@@ -20,8 +29,13 @@ pub type WorkerRegisterChannelType = Result<InstanceScheduling, tonic::Status>;
 #[derive(Debug)]
 pub enum Event {
     /// Workers register to the Scheduler so they can serve
-    /// the cluster
-    Register(Sender<WorkerRegisterChannelType>, SocketAddr, String),
+    /// the cluster, they give the instances they are already running
+    /// so the scheduler can reconcile its state, and what they are able to run
+    Register(
+        Sender<WorkerRegisterChannelType>,
+        SocketAddr,
+        WorkerRegistration,
+    ),
     /// Controller can send workload, we use the verb Schedule to describe
     /// this event
     ScheduleRequest(WorkloadRequest),
@@ -35,10 +49,14 @@ pub enum Event {
     /// The first string is the identifier, this event will send your metrics
     /// to the controller
     /// ```
-    /// use proto::common::{WorkerMetric};
+    /// use proto::common::WorkerMetric;
+    /// use proto::metrics::{CpuMetrics, NodeMetrics};
     /// let metrics = WorkerMetric {
     ///     status: 1,
-    ///     metrics: "{metricA: 10, metricB: 100}".to_string()
+    ///     metrics: Some(NodeMetrics {
+    ///         cpu: Some(CpuMetrics { total: 4, free: 80.0 }),
+    ///         ..Default::default()
+    ///     }),
     /// };
     /// ```
     WorkerMetric(String, WorkerMetric),
@@ -51,14 +69,25 @@ pub enum Event {
     /// use proto::common::{InstanceMetric};
     /// let metrics = InstanceMetric {
     ///     status: 1,
-    ///     metrics: "{metricA: 10, metricB: 100}".to_string(),
-    ///     instance_id: "test".to_string()
+    ///     instance_id: "test".to_string(),
+    ///     details: None,
+    ///     trace_context: Default::default(),
+    ///     timestamp: 0,
+    ///     placement: None,
     /// };
     /// ```
     InstanceMetric(String, InstanceMetric),
     /// Metrics received from workers to tell about themselves
     /// These metrics will be used inside the state manager
     InstanceMetricsUpdate(String, InstanceMetric),
+    /// Event reported by a worker or the state manager, forwarded to the controller
+    ClusterEvent(String, ClusterEvent),
+    /// Admin request to list registered workers
+    ListWorkers(oneshot::Sender<Vec<WorkerInfo>>),
+    /// Admin request to list the workers assigned to each instance
+    ListPlacements(oneshot::Sender<Vec<WorkloadPlacement>>),
+    /// Admin request to dump the state of the StateManager as JSON
+    DumpState(oneshot::Sender<String>),
 }
 
 #[derive(Debug)]
@@ -160,6 +189,10 @@ pub struct Worker {
     state: WorkerState,
     /// Most recent metric the worker has on its state
     metric: Option<Metrics>,
+    /// What the worker advertised it can run, `None` if it did not tell
+    capabilities: Option<WorkerCapabilities>,
+    /// Last time the worker sent a status update
+    last_heartbeat: Option<SystemTime>,
 }
 
 impl Worker {
@@ -170,6 +203,34 @@ impl Worker {
             addr,
             state: WorkerState::NotReady,
             metric: None,
+            capabilities: None,
+            last_heartbeat: None,
+        }
+    }
+
+    /// Record that the worker just sent a status update
+    pub fn heartbeat(&mut self) {
+        self.last_heartbeat = Some(SystemTime::now());
+    }
+
+    pub fn get_last_heartbeat(&self) -> &Option<SystemTime> {
+        &self.last_heartbeat
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: Option<WorkerCapabilities>) {
+        self.capabilities = capabilities;
+    }
+
+    pub fn get_capabilities(&self) -> &Option<WorkerCapabilities> {
+        &self.capabilities
+    }
+
+    /// Whether the worker advertised it can run this kind of workload,
+    /// workers without capabilities are assumed to run every kind
+    pub fn can_run(&self, kind: WorkloadKind) -> bool {
+        match &self.capabilities {
+            Some(capabilities) => capabilities.kinds.contains(&kind.into()),
+            None => true,
         }
     }
 
@@ -224,6 +285,26 @@ impl Worker {
     }
 }
 
+impl From<&Worker> for WorkerInfo {
+    fn from(worker: &Worker) -> Self {
+        let state = match worker.get_state() {
+            WorkerState::Ready => AdminWorkerState::Ready,
+            WorkerState::NotReady => AdminWorkerState::NotReady,
+        };
+        WorkerInfo {
+            id: worker.id.clone(),
+            address: worker.addr.to_string(),
+            state: state.into(),
+            last_heartbeat: worker
+                .get_last_heartbeat()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_millis() as u64),
+            metrics: worker.get_metrics().as_ref().map(Into::into),
+            capabilities: worker.get_capabilities().clone(),
+        }
+    }
+}
+
 #[tonic::async_trait]
 pub trait Send<T> {
     async fn send(&self, data: T) -> Result<(), Status>;
@@ -235,18 +316,24 @@ pub struct WorkloadRequest {
     pub definition: WorkloadDefinition,
     pub action: WorkloadRequestKind,
     pub instance_id: String,
+    /// Context of the trace the request is part of
+    pub trace_context: TraceContext,
 }
 
 impl WorkloadRequest {
-    pub fn new(workload: WorkloadScheduling) -> Result<WorkloadRequest, serde_json::Error> {
+    pub fn new(workload: WorkloadScheduling) -> Result<WorkloadRequest, ConversionError> {
         Ok(WorkloadRequest {
             workload_id: workload.workload_id,
-            definition: serde_json::from_str(&workload.definition)?,
+            definition: workload
+                .definition
+                .ok_or(ConversionError::MissingField("definition"))?
+                .try_into()?,
             action: match workload.action {
                 1 => WorkloadRequestKind::Destroy,
                 _ => WorkloadRequestKind::Create,
             },
             instance_id: workload.instance_id,
+            trace_context: TraceContext::new(),
         })
     }
 }
@@ -1,28 +1,33 @@
 mod config_parser;
 mod grpc;
+mod health;
+mod metrics;
 mod state_manager;
 
 use crate::config_parser::ConfigParser;
 use crate::grpc::GRPCService;
 use crate::state_manager::{StateManager, StateManagerEvent};
 
+use node_metrics::metrics::Metrics;
+use probes::Probes;
+use proto::admin::admin_server::AdminServer;
 use proto::common::worker_status::Status;
-use proto::common::{ResourceStatus, WorkerMetric as WorkerMetricProto, WorkerStatus};
+use proto::common::{
+    ResourceStatus, WorkerCapabilities, WorkerMetric as WorkerMetricProto, WorkerStatus,
+};
 use proto::controller::controller_server::ControllerServer;
 use proto::worker::worker_server::WorkerServer;
 use scheduler::Event;
 use scheduler::{Controller, SchedulerError, Worker, WorkerRegisterChannelType};
-use tracing::metadata::LevelFilter;
-use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::{fmt, EnvFilter};
 
+use std::convert::TryFrom;
 use std::net::{SocketAddr, SocketAddrV4};
 use std::sync::Arc;
 use tracing::{debug, error, info, warn};
 
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::sync::Mutex;
+use tonic::transport::server::TcpIncoming;
 use tonic::transport::Server;
 
 #[derive(Debug)]
@@ -51,6 +56,7 @@ impl Manager {
         instance.run_controllers_listener(controllers_listener, sender.clone());
         let workers = instance.workers.clone();
         tokio::spawn(async move {
+            let _alive = health::STATE_MANAGER.guard();
             let mut sm = StateManager::new(sender.clone(), workers);
             if let Err(e) = sm.run(receiver_sender).await {
                 error!("StateManager failed, reason: {}", e);
@@ -65,7 +71,17 @@ impl Manager {
     fn run_workers_listener(&self, listener: SocketAddrV4, sender: Sender<Event>) {
         let server = WorkerServer::new(GRPCService::new(sender));
         tokio::spawn(async move {
-            let server = Server::builder().add_service(server).serve(listener.into());
+            let incoming = match TcpIncoming::new(listener.into(), true, None) {
+                Ok(incoming) => incoming,
+                Err(e) => {
+                    error!("Could not listen on {}, reason: {}", listener, e);
+                    return;
+                }
+            };
+            let _bound = health::WORKERS_LISTENER.guard();
+            let server = Server::builder()
+                .add_service(server)
+                .serve_with_incoming(incoming);
 
             info!("Worker gRPC listening on {}", listener);
 
@@ -76,11 +92,23 @@ impl Manager {
     }
 
     fn run_controllers_listener(&self, listener: SocketAddrV4, sender: Sender<Event>) {
-        let server = ControllerServer::new(GRPCService::new(sender));
+        let server = ControllerServer::new(GRPCService::new(sender.clone()));
+        let admin = AdminServer::new(GRPCService::new(sender));
         tokio::spawn(async move {
-            let server = Server::builder().add_service(server).serve(listener.into());
+            let incoming = match TcpIncoming::new(listener.into(), true, None) {
+                Ok(incoming) => incoming,
+                Err(e) => {
+                    error!("Could not listen on {}, reason: {}", listener, e);
+                    return;
+                }
+            };
+            let _bound = health::CONTROLLERS_LISTENER.guard();
+            let server = Server::builder()
+                .add_service(server)
+                .add_service(admin)
+                .serve_with_incoming(incoming);
 
-            info!("Controller gRPC listening on {}", listener);
+            info!("Controller and Admin gRPC listening on {}", listener);
 
             if let Err(e) = server.await {
                 error!("{}", e);
@@ -89,14 +117,35 @@ impl Manager {
     }
 
     async fn listen(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let _alive = health::MANAGER.guard();
         while let Some(e) = self.channel.recv().await {
             match e {
-                Event::Register(channel, addr, hostname) => {
-                    if let Err(e) = self.register(channel.clone(), addr, hostname.clone()).await {
+                Event::Register(channel, addr, registration) => {
+                    let hostname = registration.hostname.clone();
+                    if let Err(e) = self
+                        .register(
+                            channel.clone(),
+                            addr,
+                            hostname.clone(),
+                            registration.capabilities,
+                        )
+                        .await
+                    {
                         error!(
                             "Failed to register worker {} ({}), reason: {}",
                             hostname, addr, e
-                        )
+                        );
+                        continue;
+                    }
+                    if let Err(e) = self
+                        .state_manager
+                        .send(StateManagerEvent::Reconcile(
+                            hostname,
+                            registration.instances,
+                        ))
+                        .await
+                    {
+                        error!("Failed to communicate with StateManager, reason: {}", e);
                     }
                 }
                 Event::ScheduleRequest(workload) => {
@@ -144,9 +193,10 @@ impl Manager {
                         workers.iter_mut().find(|worker| worker.id.eq(&*identifier))
                     {
                         debug!("Updated worker metrics for {}({})", identifier, worker.id);
-                        match serde_json::from_str(&data.metrics) {
-                            Ok(metric) => worker.set_metrics(metric),
-                            Err(e) => warn!("Could not deserialize metrics, error: {}", e),
+                        match data.metrics.map(Metrics::try_from) {
+                            Some(Ok(metric)) => worker.set_metrics(metric),
+                            Some(Err(e)) => warn!("Could not convert metrics, error: {}", e),
+                            None => warn!("Received empty metrics from {}", identifier),
                         };
                     } else {
                         warn!(
@@ -155,7 +205,13 @@ impl Manager {
                         );
                     }
                 }
-                Event::InstanceMetric(identifier, metrics) => {
+                Event::InstanceMetric(identifier, mut metrics) => {
+                    // Workers only know their address from their own point of view
+                    if let Some(placement) = metrics.placement.as_mut() {
+                        if let Some(address) = self.worker_address(&identifier).await {
+                            placement.host_ip = address.ip().to_string();
+                        }
+                    }
                     if let Some(controller) = &self.controller {
                         if let Err(e) = controller
                             .send(Ok(WorkerStatus {
@@ -169,10 +225,24 @@ impl Manager {
                         }
                     }
                 }
-                Event::InstanceMetricsUpdate(_, metrics) => {
+                Event::ClusterEvent(identifier, event) => {
+                    if let Some(controller) = &self.controller {
+                        if let Err(e) = controller
+                            .send(Ok(WorkerStatus {
+                                identifier,
+                                status: Some(Status::Event(event)),
+                                host_address: None,
+                            }))
+                            .await
+                        {
+                            error!("Failed to send ClusterEvent to controller, reason: {}", e);
+                        }
+                    }
+                }
+                Event::InstanceMetricsUpdate(identifier, metrics) => {
                     if self
                         .state_manager
-                        .send(StateManagerEvent::InstanceUpdate(metrics))
+                        .send(StateManagerEvent::InstanceUpdate(identifier, metrics))
                         .await
                         .is_err()
                     {
@@ -182,6 +252,7 @@ impl Manager {
                     }
                 }
                 Event::WorkerMetricsUpdate(identifier, metrics) => {
+                    self.forward_worker_metrics(&identifier, &metrics).await;
                     if self
                         .state_manager
                         .send(StateManagerEvent::WorkerUpdate(identifier, metrics))
@@ -193,11 +264,68 @@ impl Manager {
                         );
                     }
                 }
+                Event::ListWorkers(sender) => {
+                    let workers = self.workers.lock().await;
+                    let _ = sender.send(workers.iter().map(Into::into).collect());
+                }
+                Event::ListPlacements(sender) => {
+                    if self
+                        .state_manager
+                        .send(StateManagerEvent::Placements(sender))
+                        .await
+                        .is_err()
+                    {
+                        error!("StateManager is in failed state, cannot list placements");
+                    }
+                }
+                Event::DumpState(sender) => {
+                    if self
+                        .state_manager
+                        .send(StateManagerEvent::Dump(sender))
+                        .await
+                        .is_err()
+                    {
+                        error!("StateManager is in failed state, cannot dump its state");
+                    }
+                }
             }
         }
         Ok(())
     }
 
+    /// Let the controller know the worker is still alive along with its latest metrics
+    async fn forward_worker_metrics(&self, identifier: &str, metrics: &WorkerMetricProto) {
+        let controller = match &self.controller {
+            Some(controller) => controller,
+            None => return,
+        };
+        let address = match self.worker_address(identifier).await {
+            Some(address) => address,
+            None => return,
+        };
+        let message = WorkerStatus {
+            identifier: identifier.to_string(),
+            status: Some(Status::Worker(metrics.clone())),
+            host_address: Some(address.to_string()),
+        };
+        if let Err(e) = controller.send(Ok(message)).await {
+            error!(
+                "Failed to send WorkerMetricsUpdate to controller, reason: {}",
+                e
+            );
+        }
+    }
+
+    /// Address a registered worker connected from
+    async fn worker_address(&self, identifier: &str) -> Option<SocketAddr> {
+        self.workers
+            .lock()
+            .await
+            .iter()
+            .find(|worker| worker.id.eq(identifier))
+            .map(|worker| worker.addr)
+    }
+
     async fn get_worker_sender(&self, hostname: &str) -> Option<Sender<WorkerRegisterChannelType>> {
         if let Some(worker) = self
             .workers
@@ -217,6 +345,7 @@ impl Manager {
         channel: Sender<WorkerRegisterChannelType>,
         addr: SocketAddr,
         hostname: String,
+        capabilities: Option<WorkerCapabilities>,
     ) -> Result<(), SchedulerError> {
         let mut workers = self.workers.lock().await;
         if let Some(worker) = workers.iter_mut().find(|worker| worker.id.eq(&*hostname)) {
@@ -231,20 +360,18 @@ impl Manager {
                     )))
                     .await
                     .map_err(|_| SchedulerError::ClientDisconnected)?;
+                return Err(SchedulerError::RegistrationFailed(format!(
+                    "hostname {} is already taken",
+                    hostname
+                )));
             } else {
                 info!("Worker {} is back ready", hostname);
                 worker.set_channel(channel);
+                worker.set_capabilities(capabilities);
                 if let Some(controller) = &self.controller {
-                    let metrics = match serde_json::to_string(&worker.get_metrics()) {
-                        Ok(metric) => Some(metric),
-                        Err(e) => {
-                            warn!("Could not deserialize metrics, error: {}", e);
-                            None
-                        }
-                    };
                     let worker_metrics = WorkerMetricProto {
                         status: ResourceStatus::Running as i32,
-                        metrics: metrics.unwrap_or_default(),
+                        metrics: worker.get_metrics().as_ref().map(Into::into),
                     };
                     let message = WorkerStatus {
                         identifier: worker.id.clone(),
@@ -261,22 +388,16 @@ impl Manager {
                 }
             }
         } else {
-            let worker = Worker::new(hostname, channel, addr);
+            let mut worker = Worker::new(hostname, channel, addr);
             info!(
-                "Worker {} is now registered, ip: {}",
-                worker.id, worker.addr
+                "Worker {} is now registered, ip: {}, capabilities: {:?}",
+                worker.id, worker.addr, capabilities
             );
+            worker.set_capabilities(capabilities);
             if let Some(controller) = &self.controller {
-                let metrics = match serde_json::to_string(&worker.get_metrics()) {
-                    Ok(metric) => Some(metric),
-                    Err(e) => {
-                        warn!("Could not deserialize metrics, error: {}", e);
-                        None
-                    }
-                };
                 let worker_metrics = WorkerMetricProto {
                     status: ResourceStatus::Running as i32,
-                    metrics: metrics.unwrap_or_default(),
+                    metrics: worker.get_metrics().as_ref().map(Into::into),
                 };
                 let message = WorkerStatus {
                     identifier: worker.id.clone(),
@@ -300,15 +421,18 @@ impl Manager {
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = ConfigParser::new()?;
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(
-            EnvFilter::builder()
-                .with_default_directive(LevelFilter::INFO.into())
-                .from_env_lossy(),
-        )
-        .init();
+    let _telemetry = telemetry::init("rik-scheduler")?;
     info!("Starting up...");
+    let endpoints = Probes {
+        metrics: metrics::render,
+        liveness: health::liveness,
+        readiness: health::readiness,
+    };
+    tokio::spawn(probes::serve(
+        config.http_endpoint.into(),
+        endpoints,
+        probes::not_found,
+    ));
     let manager = Manager::run(config.workers_endpoint, config.controller_endpoint);
     manager.await?;
     Ok(())
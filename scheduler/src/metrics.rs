@@ -0,0 +1,42 @@
+//! Operational metrics of the scheduler, exposed in Prometheus format on `/metrics`
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram, register_int_gauge, register_int_gauge_vec, Histogram, IntGauge,
+    IntGaugeVec, TextEncoder,
+};
+
+pub static WORKERS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "rik_scheduler_workers",
+        "Workers known by the scheduler",
+        &["state"]
+    )
+    .unwrap()
+});
+
+pub static PENDING_INSTANCES: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "rik_scheduler_pending_instances",
+        "Instances waiting to be placed on a worker"
+    )
+    .unwrap()
+});
+
+pub static SCHEDULING_LATENCY: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "rik_scheduler_scheduling_latency_seconds",
+        "Time an instance spent pending before being placed on a worker"
+    )
+    .unwrap()
+});
+
+/// Encode every registered metric in Prometheus text format
+pub fn render() -> String {
+    // Metrics are registered lazily, make sure they show up before being used
+    Lazy::force(&WORKERS);
+    Lazy::force(&PENDING_INSTANCES);
+    Lazy::force(&SCHEDULING_LATENCY);
+    TextEncoder::new()
+        .encode_to_string(&prometheus::gather())
+        .unwrap_or_default()
+}
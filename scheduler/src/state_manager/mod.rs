@@ -1,25 +1,46 @@
 mod lib;
 
+use crate::metrics::{PENDING_INSTANCES, SCHEDULING_LATENCY, WORKERS};
 use crate::state_manager::lib::int_to_resource_status;
-use definition::workload::WorkloadDefinition;
-use proto::common::{InstanceMetric, ResourceStatus, WorkerMetric, WorkloadRequestKind};
+use definition::workload::{HostPort, WorkloadDefinition};
+use node_metrics::metrics::Metrics;
+use proto::admin::{InstancePlacement, WorkloadPlacement};
+use proto::common::{
+    ClusterEvent, InstanceDetails, InstanceMetric, ResourceStatus, WorkerInstance, WorkerMetric,
+    WorkloadKind, WorkloadRequestKind,
+};
 use proto::worker::InstanceScheduling;
 use rand::seq::IteratorRandom;
 use scheduler::{Event, SchedulerError, Worker, WorkerState, WorkloadRequest};
-use std::collections::HashMap;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt;
 use std::sync::Arc;
+use std::time::Instant;
+use telemetry::TraceContext;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::Mutex;
-use tracing::{debug, error, info};
+use tokio::sync::{oneshot, Mutex};
+use tracing::{debug, error, info, info_span, warn, Span};
+
+/// Reason given when no ready worker advertised the kind of an instance
+const UNSCHEDULABLE: &str = "Unschedulable";
+/// Source of the events reported by the scheduler
+const EVENT_SOURCE: &str = "scheduler";
 
 #[derive(Debug)]
 pub enum StateManagerEvent {
     Schedule(WorkloadRequest),
     #[allow(dead_code)]
     Shutdown,
-    InstanceUpdate(InstanceMetric),
+    InstanceUpdate(String, InstanceMetric),
     WorkerUpdate(String, WorkerMetric),
+    /// A worker (re-)registered and reported the instances it is currently running
+    Reconcile(String, Vec<WorkerInstance>),
+    /// Give the worker assigned to each instance
+    Placements(oneshot::Sender<Vec<WorkloadPlacement>>),
+    /// Give the whole state serialized as JSON
+    Dump(oneshot::Sender<String>),
 }
 
 impl fmt::Display for StateManagerEvent {
@@ -34,6 +55,14 @@ pub struct StateManager {
     manager_channel: Sender<Event>,
 }
 
+/// Report an event about an instance to the controller
+async fn emit_event(channel: &Sender<Event>, instance_id: &str, reason: &str, message: String) {
+    let event = ClusterEvent::instance(instance_id, reason, message, EVENT_SOURCE.to_string());
+    let _ = channel
+        .send(Event::ClusterEvent(EVENT_SOURCE.to_string(), event))
+        .await;
+}
+
 impl StateManager {
     pub fn new(manager_channel: Sender<Event>, workers: Arc<Mutex<Vec<Worker>>>) -> StateManager {
         StateManager {
@@ -55,22 +84,33 @@ impl StateManager {
                     return Ok(());
                 }
                 StateManagerEvent::Schedule(workload) => self.process_schedule_request(workload),
-                StateManagerEvent::InstanceUpdate(metrics) => {
-                    let _ = self
-                        .manager_channel
-                        .send(Event::InstanceMetric(
-                            "scheduler".to_string(),
-                            metrics.clone(),
-                        ))
-                        .await;
-                    self.process_instance_update(metrics)
+                StateManagerEvent::InstanceUpdate(identifier, metrics) => {
+                    if self.process_instance_update(&metrics) {
+                        let _ = self
+                            .manager_channel
+                            .send(Event::InstanceMetric(identifier, metrics))
+                            .await;
+                    }
+                    Ok(())
                 }
                 StateManagerEvent::WorkerUpdate(identifier, metrics) => {
                     self.process_metric_update(identifier, metrics).await
                 }
+                StateManagerEvent::Reconcile(identifier, instances) => {
+                    self.process_reconcile(identifier, instances).await
+                }
+                StateManagerEvent::Placements(sender) => {
+                    let _ = sender.send(self.placements());
+                    continue;
+                }
+                StateManagerEvent::Dump(sender) => {
+                    let _ = sender.send(self.dump());
+                    continue;
+                }
             };
             self.scan_workers().await;
             self.update_state().await;
+            self.record_metrics().await;
         }
         Err(SchedulerError::StateManagerFailed)
     }
@@ -88,28 +128,171 @@ impl StateManager {
         }
 
         // In the case we deactivated any worker, we want to reschedule the instances linked to that
-        let mut instances_to_delete = Vec::new();
-        let instances = self.state.iter_mut();
-        {
-            for (id, workload) in instances {
-                for (instance_id, instance) in workload.instances.iter() {
-                    if let Some(worker_id) = &instance.worker_id {
-                        if deactivated_workers.contains(worker_id) {
-                            instances_to_delete.push((id.clone(), instance_id.clone()));
-                        }
-                    }
+        for workload in self.state.values_mut() {
+            let mut instances_to_delete = Vec::new();
+            for instance in workload.instances.values_mut() {
+                let lost = match &instance.worker_id {
+                    Some(worker_id) => deactivated_workers.contains(worker_id),
+                    None => false,
+                };
+                if !lost {
+                    continue;
                 }
+                if instance.status == ResourceStatus::Destroying {
+                    instances_to_delete.push(instance.id.clone());
+                } else {
+                    info!(
+                        "Worker of instance {} is not ready anymore, rescheduling it",
+                        instance.id
+                    );
+                    instance.requeue();
+                }
+            }
+            for instance_id in &instances_to_delete {
+                workload.instances.remove(instance_id);
             }
         }
+    }
 
-        for (workload_id, instance_id) in &instances_to_delete {
-            if let Some(workload) = self.state.get_mut(workload_id) {
-                workload.instances.remove(instance_id);
+    /// Compare the inventory reported by a worker with the expected state.
+    ///
+    /// Known instances are adopted, instances unknown to the scheduler are
+    /// destroyed on the worker, and instances expected on this worker but
+    /// missing from its inventory are rescheduled.
+    async fn process_reconcile(
+        &mut self,
+        identifier: String,
+        reported: Vec<WorkerInstance>,
+    ) -> Result<(), SchedulerError> {
+        info!(
+            "Reconciling worker {} which reported {} instance(s)",
+            identifier,
+            reported.len()
+        );
+        let mut orphans = Vec::new();
+        let mut updates = Vec::new();
+
+        for remote in reported.iter() {
+            let workload = self
+                .state
+                .values_mut()
+                .find(|workload| workload.instances.contains_key(&remote.instance_id));
+            let workload = match workload {
+                Some(workload) => workload,
+                None => {
+                    warn!(
+                        "Instance {} reported by worker {} is unknown, destroying it",
+                        remote.instance_id, identifier
+                    );
+                    orphans.push(remote.instance_id.clone());
+                    continue;
+                }
+            };
+            let instance = workload.instances.get_mut(&remote.instance_id).unwrap();
+
+            let owned = match &instance.worker_id {
+                Some(worker_id) => worker_id.eq(&identifier),
+                None => true,
+            };
+            if !owned || instance.status == ResourceStatus::Destroying {
+                warn!(
+                    "Instance {} reported by worker {} is not expected there, destroying it",
+                    remote.instance_id, identifier
+                );
+                orphans.push(remote.instance_id.clone());
+                continue;
             }
+
+            debug!(
+                "Adopting instance {} on worker {}",
+                remote.instance_id, identifier
+            );
+            instance.set_worker(Some(identifier.clone()));
+            instance.set_status(int_to_resource_status(&remote.status));
+            updates.push((workload.id.clone(), instance.id.clone(), instance.status));
         }
+
+        // Instances expected on this worker but missing from its inventory
+        let mut terminated = Vec::new();
+        for workload in self.state.values_mut() {
+            let mut missing_destroyed = Vec::new();
+            for instance in workload.instances.values_mut() {
+                let expected_here = instance
+                    .worker_id
+                    .as_ref()
+                    .map(|worker_id| worker_id.eq(&identifier))
+                    .unwrap_or(false);
+                let is_reported = reported
+                    .iter()
+                    .any(|remote| remote.instance_id.eq(&instance.id));
+                if !expected_here || is_reported {
+                    continue;
+                }
+                if instance.status == ResourceStatus::Destroying {
+                    missing_destroyed.push(instance.id.clone());
+                } else {
+                    info!(
+                        "Instance {} is missing on worker {}, rescheduling it",
+                        instance.id, identifier
+                    );
+                    instance.requeue();
+                    updates.push((workload.id.clone(), instance.id.clone(), instance.status));
+                }
+            }
+            for instance_id in missing_destroyed {
+                workload.instances.remove(&instance_id);
+                terminated.push((workload.id.clone(), instance_id));
+            }
+        }
+
+        for instance_id in orphans {
+            let _ = self
+                .manager_channel
+                .send(Event::Schedule(
+                    identifier.clone(),
+                    InstanceScheduling {
+                        instance_id,
+                        action: WorkloadRequestKind::Destroy as i32,
+                        definition: None,
+                        trace_context: TraceContext::new(),
+                    },
+                ))
+                .await;
+        }
+
+        let terminated = terminated.into_iter().map(|(workload_id, instance_id)| {
+            (workload_id, instance_id, ResourceStatus::Terminated)
+        });
+        for (workload_id, instance_id, status) in updates.into_iter().chain(terminated) {
+            let _ = self
+                .manager_channel
+                .send(Event::InstanceMetric(
+                    "scheduler".to_string(),
+                    InstanceMetric {
+                        status: status.into(),
+                        details: Some(InstanceDetails {
+                            workload_id,
+                            reason: None,
+                            message: None,
+                            exit_code: None,
+                        }),
+                        instance_id,
+                        trace_context: TraceContext::new(),
+                        timestamp: definition::now_millis(),
+                        placement: None,
+                    },
+                ))
+                .await;
+        }
+
+        Ok(())
     }
 
-    fn process_instance_update(&mut self, metrics: InstanceMetric) -> Result<(), SchedulerError> {
+    /// Apply the status reported by a worker to its instance, returns
+    /// whether the metric tells the controller something new: a change of
+    /// status, or details such as the reason of a restart or the placement
+    /// of the instance. Repeated statuses are not forwarded.
+    fn process_instance_update(&mut self, metrics: &InstanceMetric) -> bool {
         debug!(
             "[process_instance_update] Instance {} and received {} status",
             metrics.instance_id, &metrics.status
@@ -119,30 +302,41 @@ impl StateManager {
             .iter_mut()
             .find(|(_, workload)| workload.instances.contains_key(&metrics.instance_id));
 
-        if let Some((_, workload)) = workload {
-            let status = int_to_resource_status(&metrics.status);
-            if status == ResourceStatus::Terminated {
-                debug!(
-                    "Deleted instance {} on workload {}",
-                    &metrics.instance_id, &workload.id
-                );
-                workload.instances.remove(&metrics.instance_id);
-            } else {
-                let instance = workload.instances.get_mut(&metrics.instance_id).unwrap();
-                instance.status = int_to_resource_status(&metrics.status);
-                info!(
-                    "Instance {} updated status to {:#?}",
-                    instance.id, &instance.status
+        let (_, workload) = match workload {
+            Some(workload) => workload,
+            None => {
+                error!(
+                    "Could not process instance {} update, as it does not exist",
+                    metrics.instance_id
                 );
+                return false;
             }
-        } else {
-            error!(
-                "Could not process instance {} update, as it does not exist",
-                metrics.instance_id
+        };
+
+        let status = int_to_resource_status(&metrics.status);
+        if status == ResourceStatus::Terminated {
+            debug!(
+                "Deleted instance {} on workload {}",
+                &metrics.instance_id, &workload.id
             );
+            workload.instances.remove(&metrics.instance_id);
+            return true;
         }
 
-        Ok(())
+        let instance = workload.instances.get_mut(&metrics.instance_id).unwrap();
+        let changed = instance.status != status;
+        instance.status = status;
+        info!(
+            "Instance {} updated status to {:#?}",
+            instance.id, &instance.status
+        );
+        let has_details = matches!(
+            &metrics.details,
+            Some(details) if details.reason.is_some()
+                || details.message.is_some()
+                || details.exit_code.is_some()
+        );
+        changed || has_details || metrics.placement.is_some()
     }
 
     async fn process_metric_update(
@@ -152,6 +346,12 @@ impl StateManager {
     ) -> Result<(), SchedulerError> {
         let mut lock = self.workers.lock().await;
         if let Some(worker) = lock.iter_mut().find(|worker| worker.id.eq(&identifier)) {
+            worker.heartbeat();
+            match metrics.metrics.clone().map(Metrics::try_from) {
+                Some(Ok(node_metrics)) => worker.set_metrics(node_metrics),
+                Some(Err(e)) => warn!("Could not convert metrics of {}: {}", identifier, e),
+                None => (),
+            }
             if int_to_resource_status(&metrics.status) == ResourceStatus::Running {
                 worker.set_state(WorkerState::Ready);
             } else {
@@ -175,9 +375,13 @@ impl StateManager {
             return;
         }
 
-        let mut workers = ready_workers.iter().cycle();
+        // Index of the next worker to try, so instances are spread round-robin
+        let mut cursor = 0;
+        // Host ports already taken on each worker by the instances placed there
+        let mut used_ports = self.used_host_ports();
         // Scheduling of new instances
         for (_id, workload) in self.state.iter_mut() {
+            let kind = WorkloadKind::from(workload.definition.kind.clone());
             let pending_instances: Vec<&mut WorkloadInstance> = workload
                 .instances
                 .iter_mut()
@@ -188,10 +392,78 @@ impl StateManager {
                 .collect();
 
             for instance in pending_instances {
-                let worker = workers.next().unwrap();
+                let host_ports = instance.definition.host_ports();
+                let capable = |index: &usize| ready_workers[*index].1.contains(&kind);
+                let worker = (0..ready_workers.len())
+                    .map(|offset| (cursor + offset) % ready_workers.len())
+                    .filter(capable)
+                    .find(|index| match used_ports.get(&ready_workers[*index].0) {
+                        Some(used) => !host_ports.iter().any(|port| used.contains(port)),
+                        None => true,
+                    });
+                let worker = match worker {
+                    Some(index) => {
+                        cursor = index + 1;
+                        &ready_workers[index].0
+                    }
+                    None => {
+                        if instance.is_unschedulable {
+                            continue;
+                        }
+                        let message = if (0..ready_workers.len()).any(|index| capable(&index)) {
+                            format!(
+                                "No ready worker has the host ports {} free",
+                                host_ports
+                                    .iter()
+                                    .map(HostPort::to_string)
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            )
+                        } else {
+                            format!("No ready worker is able to run {:?} workloads", kind)
+                        };
+                        warn!("Instance {} can't be scheduled: {}", instance.id, message);
+                        instance.is_unschedulable = true;
+                        let _ = self
+                            .manager_channel
+                            .send(Event::InstanceMetric(
+                                "scheduler".to_string(),
+                                InstanceMetric {
+                                    status: ResourceStatus::Pending.into(),
+                                    details: Some(InstanceDetails {
+                                        workload_id: workload.id.clone(),
+                                        reason: Some(UNSCHEDULABLE.to_string()),
+                                        message: Some(message.clone()),
+                                        exit_code: None,
+                                    }),
+                                    instance_id: instance.id.clone(),
+                                    trace_context: instance.trace_context.clone(),
+                                    timestamp: definition::now_millis(),
+                                    placement: None,
+                                },
+                            ))
+                            .await;
+                        emit_event(
+                            &self.manager_channel,
+                            &instance.id,
+                            "FailedScheduling",
+                            message,
+                        )
+                        .await;
+                        continue;
+                    }
+                };
 
+                used_ports
+                    .entry(worker.clone())
+                    .or_default()
+                    .extend(host_ports);
                 instance.set_worker(Some(worker.clone()));
                 instance.set_status(ResourceStatus::Creating);
+                SCHEDULING_LATENCY.observe(instance.pending_since.elapsed().as_secs_f64());
+                let trace_context = instance.continue_trace(
+                    info_span!("place_instance", instance_id = %instance.id, %worker),
+                );
 
                 let _ = self
                     .manager_channel
@@ -200,22 +472,37 @@ impl StateManager {
                         InstanceScheduling {
                             instance_id: instance.id.clone(),
                             action: WorkloadRequestKind::Create as i32,
-                            definition: serde_json::to_string(&instance.definition.clone())
-                                .unwrap(),
+                            definition: Some(instance.definition.clone().into()),
+                            trace_context: trace_context.clone(),
                         },
                     ))
                     .await;
                 let _ = self
                     .manager_channel
                     .send(Event::InstanceMetric(
-                        "scheduler".to_string(),
+                        worker.clone(),
                         InstanceMetric {
                             status: ResourceStatus::Creating.into(),
-                            metrics: format!("\"workload_id\": \"{}\"", workload.id.clone()),
+                            details: Some(InstanceDetails {
+                                workload_id: workload.id.clone(),
+                                reason: None,
+                                message: None,
+                                exit_code: None,
+                            }),
                             instance_id: instance.id.clone(),
+                            trace_context,
+                            timestamp: definition::now_millis(),
+                            placement: None,
                         },
                     ))
                     .await;
+                emit_event(
+                    &self.manager_channel,
+                    &instance.id,
+                    "Scheduled",
+                    format!("Assigned to worker {}", worker),
+                )
+                .await;
             }
 
             let deleting_instances: Vec<&mut WorkloadInstance> = workload
@@ -227,14 +514,25 @@ impl StateManager {
                 })
                 .collect();
 
+            let mut never_placed = Vec::new();
             for instance in deleting_instances {
-                let worker = workers.next().unwrap();
+                // Destroy requests must reach the worker running the instance
+                let worker = match &instance.worker_id {
+                    Some(worker_id) => worker_id,
+                    None => {
+                        never_placed.push((instance.id.clone(), instance.trace_context.clone()));
+                        continue;
+                    }
+                };
 
                 // For now we don't check whether the instance is properly deleted, we assume it is
                 // as if we keep the destroying state, it will loop here and spam riklet of events
                 instance.is_destroying = true;
 
                 info!("Deleting instance {}", instance.id.clone());
+                let trace_context = instance.continue_trace(
+                    info_span!("destroy_instance", instance_id = %instance.id, %worker),
+                );
 
                 let _ = self
                     .manager_channel
@@ -243,19 +541,51 @@ impl StateManager {
                         InstanceScheduling {
                             instance_id: instance.id.clone(),
                             action: WorkloadRequestKind::Destroy as i32,
-                            definition: serde_json::to_string(&instance.definition.clone())
-                                .unwrap(),
+                            definition: Some(instance.definition.clone().into()),
+                            trace_context: trace_context.clone(),
                         },
                     ))
                     .await;
                 let _ = self
                     .manager_channel
                     .send(Event::InstanceMetric(
-                        "scheduler".to_string(),
+                        worker.clone(),
                         InstanceMetric {
                             status: ResourceStatus::Destroying.into(),
-                            metrics: format!("\"workload_id\": \"{}\"", workload.id.clone()),
+                            details: Some(InstanceDetails {
+                                workload_id: workload.id.clone(),
+                                reason: None,
+                                message: None,
+                                exit_code: None,
+                            }),
                             instance_id: instance.id.clone(),
+                            trace_context,
+                            timestamp: definition::now_millis(),
+                            placement: None,
+                        },
+                    ))
+                    .await;
+            }
+
+            // Instances that never reached a worker have nothing to destroy
+            for (instance_id, trace_context) in never_placed {
+                workload.instances.remove(&instance_id);
+                let _ = self
+                    .manager_channel
+                    .send(Event::InstanceMetric(
+                        "scheduler".to_string(),
+                        InstanceMetric {
+                            status: ResourceStatus::Terminated.into(),
+                            details: Some(InstanceDetails {
+                                workload_id: workload.id.clone(),
+                                reason: None,
+                                message: None,
+                                exit_code: None,
+                            }),
+                            instance_id,
+                            trace_context,
+                            timestamp: definition::now_millis(),
+                            placement: None,
                         },
                     ))
                     .await;
@@ -290,16 +620,17 @@ impl StateManager {
     }
 
     #[tracing::instrument(
-        skip(self),
+        skip(self, request),
         fields(workload_id = %request.workload_id, instance_id = %request.instance_id),
     )]
     fn action_create_workload(&mut self, request: WorkloadRequest) -> Result<(), SchedulerError> {
-        let instance = WorkloadInstance::new(
+        let mut instance = WorkloadInstance::new(
             request.instance_id.clone(),
             ResourceStatus::Pending,
             None,
             request.definition.clone(),
         );
+        instance.trace_context = request.trace_context.clone();
         if let Some(workload) = self.state.get_mut(&request.workload_id) {
             if workload.status == ResourceStatus::Destroying {
                 error!("Cannot double replicas while workload is being destroyed");
@@ -407,6 +738,7 @@ impl StateManager {
 
         let instance = instance.unwrap();
         instance.set_status(ResourceStatus::Destroying);
+        instance.trace_context = request.trace_context.clone();
 
         if workload.replicas > *def_replicas {
             self.action_minus_replicas(&request.workload_id, def_replicas)?;
@@ -436,12 +768,92 @@ impl StateManager {
         None
     }
 
-    async fn get_workers_ready(&self) -> Vec<String> {
+    fn placements(&self) -> Vec<WorkloadPlacement> {
+        self.state
+            .values()
+            .map(|workload| WorkloadPlacement {
+                workload_id: workload.id.clone(),
+                replicas: workload.replicas.into(),
+                status: workload.status.into(),
+                instances: workload
+                    .instances
+                    .values()
+                    .map(|instance| InstancePlacement {
+                        instance_id: instance.id.clone(),
+                        worker_id: instance.worker_id.clone(),
+                        status: instance.status.into(),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn dump(&self) -> String {
+        let state: serde_json::Map<String, serde_json::Value> = self
+            .state
+            .iter()
+            .map(|(id, workload)| (id.clone(), workload.to_json()))
+            .collect();
+        serde_json::Value::Object(state).to_string()
+    }
+
+    /// Refresh the gauges describing the cluster as seen by the scheduler
+    async fn record_metrics(&self) {
+        let (ready, not_ready): (Vec<_>, Vec<_>) = self
+            .workers
+            .lock()
+            .await
+            .iter()
+            .map(|worker| worker.is_ready())
+            .partition(|ready| *ready);
+        WORKERS
+            .with_label_values(&["ready"])
+            .set(ready.len() as i64);
+        WORKERS
+            .with_label_values(&["not_ready"])
+            .set(not_ready.len() as i64);
+
+        let pending = self
+            .state
+            .values()
+            .flat_map(|workload| workload.instances.values())
+            .filter(|instance| instance.is_pending())
+            .count();
+        PENDING_INSTANCES.set(pending as i64);
+    }
+
+    /// Host ports used by the placed instances, by worker
+    fn used_host_ports(&self) -> HashMap<String, HashSet<HostPort>> {
+        let mut used_ports: HashMap<String, HashSet<HostPort>> = HashMap::new();
+        for instance in self
+            .state
+            .values()
+            .flat_map(|workload| workload.instances.values())
+        {
+            if let Some(worker_id) = &instance.worker_id {
+                used_ports
+                    .entry(worker_id.clone())
+                    .or_default()
+                    .extend(instance.definition.host_ports());
+            }
+        }
+        used_ports
+    }
+
+    /// Ready workers along with the workload kinds they can run
+    async fn get_workers_ready(&self) -> Vec<(String, Vec<WorkloadKind>)> {
         let workers = self.workers.lock().await;
         workers
             .iter()
             .filter(|worker| worker.is_ready())
-            .map(|worker| worker.id.clone())
+            .map(|worker| {
+                let kinds = [WorkloadKind::Pod, WorkloadKind::Function]
+                    .iter()
+                    .copied()
+                    .filter(|kind| worker.can_run(*kind))
+                    .collect();
+                (worker.id.clone(), kinds)
+            })
             .collect()
     }
 }
@@ -456,6 +868,23 @@ pub struct Workload {
     id: String,
 }
 
+impl Workload {
+    fn to_json(&self) -> serde_json::Value {
+        let instances: serde_json::Map<String, serde_json::Value> = self
+            .instances
+            .iter()
+            .map(|(id, instance)| (id.clone(), instance.to_json()))
+            .collect();
+        json!({
+            "id": self.id,
+            "replicas": self.replicas,
+            "status": self.status.as_str_name(),
+            "definition": self.definition,
+            "instances": instances,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WorkloadInstance {
     /// Part of the instance id that define the instance
@@ -468,6 +897,13 @@ pub struct WorkloadInstance {
     definition: WorkloadDefinition,
     /// Flag to indicate that this instance is being destroyed
     is_destroying: bool,
+    /// Flag to indicate that no worker can run this instance, so it is
+    /// only reported once
+    is_unschedulable: bool,
+    /// When the instance started waiting for a worker
+    pending_since: Instant,
+    /// Context of the trace of the last request about this instance
+    trace_context: TraceContext,
 }
 
 impl WorkloadInstance {
@@ -483,6 +919,9 @@ impl WorkloadInstance {
             worker_id,
             definition,
             is_destroying: false,
+            is_unschedulable: false,
+            pending_since: Instant::now(),
+            trace_context: TraceContext::new(),
         }
     }
 
@@ -492,9 +931,20 @@ impl WorkloadInstance {
             self.id,
             worker.clone().unwrap_or_else(|| "None".to_string())
         );
+        if worker.is_some() {
+            self.is_unschedulable = false;
+        }
         self.worker_id = worker;
     }
 
+    /// Detach the instance from its worker so it gets scheduled again
+    pub fn requeue(&mut self) {
+        self.set_worker(None);
+        self.set_status(ResourceStatus::Pending);
+        self.is_destroying = false;
+        self.pending_since = Instant::now();
+    }
+
     pub fn is_pending(&self) -> bool {
         self.status == ResourceStatus::Pending
     }
@@ -510,4 +960,314 @@ impl WorkloadInstance {
     pub fn set_status(&mut self, status: ResourceStatus) {
         self.status = status;
     }
+
+    /// Record the span in the trace of the last request about this instance,
+    /// and give its context so the worker can continue the trace
+    fn continue_trace(&self, span: Span) -> TraceContext {
+        telemetry::set_parent(&span, &self.trace_context);
+        span.in_scope(telemetry::current_context)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "id": self.id,
+            "status": self.status.as_str_name(),
+            "worker_id": self.worker_id,
+            "is_destroying": self.is_destroying,
+            "is_unschedulable": self.is_unschedulable,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use definition::workload::{Spec, WorkloadKind as DefinitionKind};
+    use proto::common::WorkerCapabilities;
+    use scheduler::WorkerRegisterChannelType;
+    use tokio::sync::mpsc::{channel, error::TryRecvError};
+
+    fn definition(kind: DefinitionKind) -> WorkloadDefinition {
+        WorkloadDefinition {
+            api_version: "v0".to_string(),
+            kind,
+            name: "workload".to_string(),
+            spec: Spec {
+                containers: vec![],
+                function: None,
+                termination_grace_period_seconds: None,
+                restart_policy: Default::default(),
+                share_process_namespace: false,
+                volumes: vec![],
+            },
+            replicas: None,
+        }
+    }
+
+    fn manager() -> (StateManager, Receiver<Event>) {
+        let (sender, receiver) = channel(64);
+        let manager = StateManager::new(sender, Arc::new(Mutex::new(Vec::new())));
+        (manager, receiver)
+    }
+
+    /// Register a ready worker, whose channel stays open as long as the
+    /// receiver is kept
+    async fn add_worker(manager: &StateManager, id: &str) -> Receiver<WorkerRegisterChannelType> {
+        let (sender, receiver) = channel(64);
+        let mut worker = Worker::new(id.to_string(), sender, "127.0.0.1:4995".parse().unwrap());
+        worker.set_state(WorkerState::Ready);
+        manager.workers.lock().await.push(worker);
+        receiver
+    }
+
+    /// Add an instance of the workload `workload`, placed on `worker` when given
+    fn add_instance(
+        manager: &mut StateManager,
+        instance_id: &str,
+        kind: DefinitionKind,
+        worker: Option<&str>,
+        status: ResourceStatus,
+    ) {
+        manager
+            .action_create_workload(WorkloadRequest {
+                workload_id: "workload".to_string(),
+                definition: definition(kind),
+                action: WorkloadRequestKind::Create,
+                instance_id: instance_id.to_string(),
+                trace_context: TraceContext::new(),
+            })
+            .unwrap();
+        let instance = manager
+            .state
+            .get_mut("workload")
+            .unwrap()
+            .instances
+            .get_mut(instance_id)
+            .unwrap();
+        instance.set_worker(worker.map(String::from));
+        instance.set_status(status);
+    }
+
+    fn instance(manager: &StateManager, instance_id: &str) -> WorkloadInstance {
+        manager.state["workload"].instances[instance_id].clone()
+    }
+
+    fn events(receiver: &mut Receiver<Event>) -> Vec<Event> {
+        let mut events = Vec::new();
+        loop {
+            match receiver.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return events,
+            }
+        }
+    }
+
+    fn reported(instance_id: &str, status: ResourceStatus) -> WorkerInstance {
+        WorkerInstance {
+            instance_id: instance_id.to_string(),
+            kind: WorkloadKind::Pod.into(),
+            status: status.into(),
+            ports: vec![],
+        }
+    }
+
+    /// Statuses sent to the controller by instance
+    fn statuses(events: &[Event]) -> Vec<(String, ResourceStatus)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::InstanceMetric(_, metric) => Some((
+                    metric.instance_id.clone(),
+                    int_to_resource_status(&metric.status),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_orphan_destroyed() {
+        let (mut manager, mut receiver) = manager();
+
+        manager
+            .process_reconcile(
+                "worker".to_string(),
+                vec![reported("unknown", ResourceStatus::Running)],
+            )
+            .await
+            .unwrap();
+
+        let events = events(&mut receiver);
+        assert!(matches!(
+            &events[..],
+            [Event::Schedule(worker, scheduling)]
+                if worker == "worker"
+                    && scheduling.instance_id == "unknown"
+                    && scheduling.action == WorkloadRequestKind::Destroy as i32
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_missing_rescheduled() {
+        let (mut manager, mut receiver) = manager();
+        add_instance(
+            &mut manager,
+            "instance",
+            DefinitionKind::Pod,
+            Some("worker"),
+            ResourceStatus::Running,
+        );
+
+        manager
+            .process_reconcile("worker".to_string(), vec![])
+            .await
+            .unwrap();
+
+        let instance = instance(&manager, "instance");
+        assert_eq!(instance.worker_id, None);
+        assert!(instance.is_pending());
+        assert_eq!(
+            statuses(&events(&mut receiver)),
+            vec![("instance".to_string(), ResourceStatus::Pending)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_matching_adopted() {
+        let (mut manager, mut receiver) = manager();
+        add_instance(
+            &mut manager,
+            "instance",
+            DefinitionKind::Pod,
+            None,
+            ResourceStatus::Creating,
+        );
+
+        manager
+            .process_reconcile(
+                "worker".to_string(),
+                vec![reported("instance", ResourceStatus::Running)],
+            )
+            .await
+            .unwrap();
+
+        let instance = instance(&manager, "instance");
+        assert_eq!(instance.worker_id.as_deref(), Some("worker"));
+        assert_eq!(instance.status, ResourceStatus::Running);
+        let events = events(&mut receiver);
+        assert_eq!(
+            statuses(&events),
+            vec![("instance".to_string(), ResourceStatus::Running)]
+        );
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, Event::Schedule(..))));
+    }
+
+    #[tokio::test]
+    async fn test_only_changes_forwarded() {
+        let (mut manager, _receiver) = manager();
+        let _worker = add_worker(&manager, "worker").await;
+        add_instance(
+            &mut manager,
+            "instance",
+            DefinitionKind::Pod,
+            Some("worker"),
+            ResourceStatus::Creating,
+        );
+        let metric = |status: ResourceStatus, reason: Option<&str>| InstanceMetric {
+            status: status.into(),
+            details: Some(InstanceDetails {
+                workload_id: "workload".to_string(),
+                reason: reason.map(String::from),
+                message: None,
+                exit_code: None,
+            }),
+            instance_id: "instance".to_string(),
+            trace_context: TraceContext::new(),
+            timestamp: 0,
+            placement: None,
+        };
+
+        assert!(manager.process_instance_update(&metric(ResourceStatus::Running, None)));
+        assert!(!manager.process_instance_update(&metric(ResourceStatus::Running, None)));
+        assert!(
+            manager.process_instance_update(&metric(ResourceStatus::Running, Some("Restarted")))
+        );
+        assert!(manager.process_instance_update(&metric(ResourceStatus::Terminated, None)));
+        assert!(!manager.process_instance_update(&metric(ResourceStatus::Running, None)));
+    }
+
+    /// Advertise that the worker `id` only runs `kinds`
+    async fn set_kinds(manager: &StateManager, id: &str, kinds: &[WorkloadKind]) {
+        let mut workers = manager.workers.lock().await;
+        let worker = workers.iter_mut().find(|worker| worker.id == id).unwrap();
+        worker.set_capabilities(Some(WorkerCapabilities {
+            kinds: kinds.iter().map(|kind| (*kind).into()).collect(),
+            arch: "x86_64".to_string(),
+            runtimes: vec![],
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_capable_worker_chosen() {
+        let (mut manager, mut receiver) = manager();
+        let _pods = add_worker(&manager, "pods").await;
+        let _functions = add_worker(&manager, "functions").await;
+        set_kinds(&manager, "pods", &[WorkloadKind::Pod]).await;
+        set_kinds(&manager, "functions", &[WorkloadKind::Function]).await;
+        add_instance(
+            &mut manager,
+            "instance",
+            DefinitionKind::Function,
+            None,
+            ResourceStatus::Pending,
+        );
+
+        manager.update_state().await;
+
+        assert_eq!(
+            instance(&manager, "instance").worker_id.as_deref(),
+            Some("functions")
+        );
+        assert!(events(&mut receiver).iter().any(|event| matches!(
+            event,
+            Event::Schedule(worker, scheduling)
+                if worker == "functions" && scheduling.instance_id == "instance"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_no_capable_worker_unschedulable() {
+        let (mut manager, mut receiver) = manager();
+        let _pods = add_worker(&manager, "pods").await;
+        set_kinds(&manager, "pods", &[WorkloadKind::Pod]).await;
+        add_instance(
+            &mut manager,
+            "instance",
+            DefinitionKind::Function,
+            None,
+            ResourceStatus::Pending,
+        );
+
+        manager.update_state().await;
+        // Only reported once
+        manager.update_state().await;
+
+        let instance = instance(&manager, "instance");
+        assert!(instance.is_pending());
+        assert_eq!(instance.worker_id, None);
+        let events = events(&mut receiver);
+        let reasons: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::InstanceMetric(_, metric) => metric.details.as_ref()?.reason.clone(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasons, vec![UNSCHEDULABLE.to_string()]);
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, Event::Schedule(..))));
+    }
 }